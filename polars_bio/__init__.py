@@ -1,27 +1,68 @@
-from polars_bio.polars_bio import InputFormat, ReadOptions, VcfReadOptions
+from polars_bio.polars_bio import (
+    BedReadOptions,
+    BigWigReadOptions,
+    CsvReadOptions,
+    GffReadOptions,
+    InputFormat,
+    OutputFormat,
+    ReadOptions,
+    VcfReadOptions,
+)
 
-from .context import ctx, set_option
+from .context import ctx, register_object_store, set_option
 from .io import (
     describe_vcf,
     from_polars,
+    getfasta,
     read_bam,
+    read_bed,
+    read_bedpe,
+    read_bigbed,
+    read_bigwig,
+    read_csv,
     read_fasta,
     read_fastq,
+    read_gff,
+    read_paf,
+    read_paired_fastq,
+    read_pairs,
+    read_sam,
     read_table,
     read_vcf,
+    register_gene_model,
+    register_paired_fastq,
+    register_table,
     register_vcf,
     register_view,
     sql,
+    write_bam,
+    write_bed,
+    write_bedgraph,
+    write_bigwig,
+    write_fastq,
+    write_gff,
+    write_vcf,
 )
 from .polars_ext import PolarsRangesOperations as LazyFrame
-from .range_op import FilterOp, count_overlaps, coverage, merge, nearest, overlap
+from .qc import fastq_qc
+from .range_op import (
+    FilterOp,
+    count_overlaps,
+    coverage,
+    merge,
+    nearest,
+    overlap,
+    paired_overlap,
+)
 from .range_viz import visualize_intervals
+from .trim import trim_fastq
 POLARS_BIO_MAX_THREADS = "datafusion.execution.target_partitions"
 from .count_kmers import plot_kmer_counts
 
 __version__ = "0.6.3"
 __all__ = [
     "overlap",
+    "paired_overlap",
     "nearest",
     "merge",
     "count_overlaps",
@@ -30,19 +71,48 @@ __all__ = [
     "FilterOp",
     "visualize_intervals",
     "read_bam",
+    "read_bed",
+    "read_bedpe",
+    "read_bigwig",
+    "read_bigbed",
+    "read_csv",
     "read_vcf",
     "read_fasta",
     "read_fastq",
+    "read_gff",
+    "read_paf",
+    "read_paired_fastq",
+    "read_pairs",
+    "read_sam",
     "read_table",
     "register_vcf",
+    "register_gene_model",
+    "register_paired_fastq",
+    "register_table",
     "describe_vcf",
     "register_view",
     "from_polars",
+    "getfasta",
     "sql",
     "InputFormat",
+    "OutputFormat",
     "LazyFrame",
     "ReadOptions",
     "VcfReadOptions",
+    "CsvReadOptions",
+    "BedReadOptions",
+    "GffReadOptions",
+    "BigWigReadOptions",
     "set_option",
+    "register_object_store",
+    "write_vcf",
+    "write_bam",
+    "write_bed",
+    "write_bedgraph",
+    "write_bigwig",
+    "write_gff",
+    "write_fastq",
     "plot_kmer_counts",
+    "fastq_qc",
+    "trim_fastq",
 ]
@@ -1,3 +1,11 @@
 DEFAULT_INTERVAL_COLUMNS = ["chrom", "start", "end"]
+DEFAULT_PAIRED_INTERVAL_COLUMNS = [
+    "chrom1",
+    "start1",
+    "end1",
+    "chrom2",
+    "start2",
+    "end2",
+]
 DEFAULT_BATCH_SIZE = 8192
 TMP_CATALOG_DIR = "./tmp/catalog_pb"
@@ -1,5 +1,6 @@
 import datetime
 from pathlib import Path
+from typing import Union
 
 import datafusion
 
@@ -53,4 +54,34 @@ def set_option(key, value):
     Context().set_option(key, value)
 
 
+def register_object_store(url: str, options: Union[dict[str, str], None] = None):
+    """
+    Register an object store for an `s3://`, `gs://` or `http(s)://` bucket or host.
+    All tables with paths under the bucket are read through it, including range requests
+    of indexed formats. VCF and BAM files with a `.tbi`, `.csi` or `.bai` index next to them
+    are detected as `InputFormat.IndexedVcf` and `InputFormat.IndexedBam`.
+
+    Parameters:
+        url: The URL of the bucket or host, e.g. `s3://my-bucket`.
+        options: The object store configuration, e.g. `endpoint`, `region`, `access_key_id`,
+            `secret_access_key` and `allow_http` for S3-compatible stores. Credentials not set here
+            are read from the environment (e.g. `AWS_ACCESS_KEY_ID`).
+
+    !!! note
+        VCF files under the bucket are read through the registered object store as well. Without
+        a registered object store, `read_vcf` uses its own client configured with environment variables.
+
+    !!! Example
+        ```python
+        import polars_bio as pb
+        pb.register_object_store(
+            "s3://genomics",
+            {"endpoint": "http://localhost:9000", "allow_http": "true", "region": "us-east-1"},
+        )
+        pb.register_table("s3://genomics/peaks.parquet", "peaks")
+        ```
+    """
+    Context().ctx.register_object_store(url, options)
+
+
 ctx = Context().ctx
@@ -1,23 +1,39 @@
+import uuid
 from typing import Dict, Iterator, Union
 
 import polars as pl
+import pyarrow as pa
 from bioframe import SCHEMAS
 from datafusion import DataFrame, SessionContext
 from polars.io.plugins import register_io_source
 from tqdm.auto import tqdm
 
 from polars_bio.polars_bio import (
+    BedReadOptions,
+    BigWigReadOptions,
+    CsvReadOptions,
+    GffReadOptions,
     InputFormat,
+    OutputFormat,
     ReadOptions,
     VcfReadOptions,
+    py_deregister_table,
     py_describe_vcf,
     py_from_polars,
+    py_get_input_format,
+    py_register_gene_model,
+    py_register_paired_fastq,
     py_read_sql,
     py_read_table,
     py_register_table,
+    py_register_tables,
     py_register_view,
     py_scan_sql,
     py_scan_table,
+    py_write_bam,
+    py_write_frame,
+    py_write_table,
+    py_write_track,
 )
 
 from .context import ctx
@@ -63,7 +79,7 @@ def read_bam(path: str) -> pl.LazyFrame:
 def read_vcf(
     path: str,
     info_fields: Union[list[str], None] = None,
-    thread_num: int = 1,
+    thread_num: Union[int, None] = None,
     chunk_size: int = 8,
     concurrent_fetches: int = 1,
     streaming: bool = False,
@@ -74,7 +90,7 @@ def read_vcf(
     Parameters:
         path: The path to the VCF file.
         info_fields: The fields to read from the INFO column.
-        thread_num: The number of threads to use for reading the VCF file. Used **only** for parallel decompression of BGZF blocks. Works only for **local** files.
+        thread_num: The number of threads to use for reading the VCF file. Used **only** for parallel decompression of BGZF blocks. Works only for **local** files. Defaults to the `bio.bgzf_threads` option.
         chunk_size: The size in MB of a chunk when reading from an object store. The default is 8 MB. For large scale operations, it is recommended to increase this value to 64.
         concurrent_fetches: The number of concurrent fetches when reading from an object store. The default is 1. For large scale operations, it is recommended to increase this value to 8 or even more.
         streaming: Whether to read the VCF file in streaming mode.
@@ -118,6 +134,228 @@ def read_fastq(path: str) -> pl.LazyFrame:
     return lazy_scan(df)
 
 
+def read_paired_fastq(r1: str, r2: Union[str, None] = None) -> pl.LazyFrame:
+    """
+    Read paired-end FASTQ files into a LazyFrame with one row per read pair.
+
+    Parameters:
+        r1: The path to the R1 FASTQ file, or to an interleaved FASTQ file if `r2` is *None*.
+        r2: The path to the R2 FASTQ file.
+
+    !!! note
+        See [register_paired_fastq](api.md#polars_bio.register_paired_fastq) for the schema and the pairing validation.
+    """
+    table = register_paired_fastq(r1, r2)
+    return lazy_scan(py_read_table(ctx, table))
+
+
+def read_bed(
+    path: str, bed_type: Union[str, None] = None, explode_blocks: bool = False
+) -> pl.LazyFrame:
+    """
+    Read a BED-like file into a LazyFrame with a typed schema.
+
+    Parameters:
+        path: The path to the file. Files with `.narrowPeak`, `.broadPeak` and `.bedGraph` extensions are read with the matching ENCODE schema.
+        bed_type: One of `bed3`...`bed12`, `narrowPeak`, `broadPeak` or `bedGraph`. A type with fewer columns than the file reads its first columns. If *None*, the number of BED columns is inferred from the first line of the file.
+        explode_blocks: For BED12, whether to return one row per block (e.g. exon) with absolute `start` and `end` coordinates.
+
+    !!! note
+        Column names follow the Bioframe's [schemas](https://github.com/open2c/bioframe/blob/main/bioframe/io/schemas.py). `blockSizes` and `blockStarts` are parsed as lists of integers.
+        Lines starting with `#` are skipped.
+    """
+    input_format = InputFormat.Bed
+    if bed_type is None:
+        detected = py_get_input_format(path)
+        if detected in [
+            InputFormat.NarrowPeak,
+            InputFormat.BroadPeak,
+            InputFormat.BedGraph,
+        ]:
+            input_format = detected
+    bed_read_options = BedReadOptions(bed_type=bed_type, explode_blocks=explode_blocks)
+    read_options = ReadOptions(bed_read_options=bed_read_options)
+    df = read_file(path, input_format, read_options)
+    return lazy_scan(df)
+
+
+def read_paf(path: str) -> pl.LazyFrame:
+    """
+    Read a PAF (e.g. minimap2) file into a LazyFrame.
+
+    Parameters:
+        path: The path to the PAF file (optionally gzipped).
+
+    !!! note
+        Target coordinates are available as `chrom`, `start` and `end` (**0-based**, half-open), so alignments can be used
+        directly in range operations, e.g. [overlap](api.md#polars_bio.overlap). Query coordinates are in `query_name`, `query_start` and `query_end`.
+        Optional fields are collected in the `tags` map column keyed by the tag name, e.g. `tags['NM']`.
+        A mapping quality of 255 (missing) is read as null.
+
+    !!! warning
+        Unlike [read_sam](api.md#polars_bio.read_sam), which returns **1-based** coordinates, PAF coordinates are kept **0-based**,
+        as in the file. Add 1 to `start` before combining PAF and SAM alignments.
+    """
+    df = read_file(path, InputFormat.Paf, None)
+    return lazy_scan(df)
+
+
+def read_sam(path: str) -> pl.LazyFrame:
+    """
+    Read a SAM text file into a LazyFrame.
+
+    Parameters:
+        path: The path to the SAM file (optionally gzipped).
+
+    !!! note
+        Alignments have `chrom`, `start` and `end` (**1-based**, closed) columns, the end being computed from the CIGAR string.
+        Missing values (`*`, position 0, mapping quality 255) are read as nulls. Optional fields are collected in the `tags` map column keyed by the tag name.
+
+    !!! warning
+        Unlike [read_paf](api.md#polars_bio.read_paf), which returns **0-based**, half-open coordinates, SAM coordinates are **1-based**,
+        as in the file. Subtract 1 from `start` before combining SAM and PAF alignments.
+    """
+    df = read_file(path, InputFormat.Sam, None)
+    return lazy_scan(df)
+
+
+def read_pairs(path: str) -> pl.LazyFrame:
+    """
+    Read a 4DN pairs (e.g. Hi-C contacts) file into a LazyFrame.
+
+    Parameters:
+        path: The path to the pairs file (optionally gzipped).
+
+    !!! note
+        Column names are taken from the `#columns:` header line. Both ends of a contact are also available as
+        `chrom1`, `start1`, `end1` and `chrom2`, `start2`, `end2` (**0-based**, half-open) intervals, so pairs can be used
+        in [paired_overlap](api.md#polars_bio.paired_overlap). The original 1-based positions are kept in `pos1` and `pos2`.
+    """
+    df = read_file(path, InputFormat.Pairs, None)
+    return lazy_scan(df)
+
+
+def read_bedpe(path: str) -> pl.LazyFrame:
+    """
+    Read a BEDPE file into a LazyFrame.
+
+    Parameters:
+        path: The path to the BEDPE file (optionally gzipped).
+
+    !!! note
+        The standard columns are `chrom1`, `start1`, `end1`, `chrom2`, `start2`, `end2`, `name`, `score`, `strand1` and `strand2`,
+        additional columns are named `field_11`, `field_12`, etc. Coordinates are **0-based**, half-open.
+    """
+    df = read_file(path, InputFormat.Bedpe, None)
+    return lazy_scan(df)
+
+
+def read_bigwig(path: str, zoom_level: Union[int, None] = None) -> pl.LazyFrame:
+    """
+    Read a local bigWig file into a LazyFrame with `chrom`, `start`, `end` and `value` columns.
+
+    Parameters:
+        path: The path to the bigWig file.
+        zoom_level: The index of the zoom level to read summaries from (0 is the highest resolution). If *None*, base-level values are read.
+            Zoom level summaries have `chrom`, `start`, `end`, `valid_count`, `min`, `max`, `mean`, `sum` and `sum_squares` columns.
+
+    !!! note
+        Filters on `chrom`, `start` and `end` (e.g. in [sql](api.md#polars_bio.sql)) are pushed down to the R-tree index of the file,
+        so only the data blocks overlapping the region are read. Coordinates are 0-based, half-open.
+    """
+    read_options = ReadOptions(
+        bigwig_read_options=BigWigReadOptions(zoom_level=zoom_level)
+    )
+    df = read_file(path, InputFormat.BigWig, read_options)
+    return lazy_scan(df)
+
+
+def read_bigbed(path: str) -> pl.LazyFrame:
+    """
+    Read a local bigBed file into a LazyFrame.
+
+    Parameters:
+        path: The path to the bigBed file.
+
+    !!! note
+        Standard BED columns follow the Bioframe's [schemas](https://github.com/open2c/bioframe/blob/main/bioframe/io/schemas.py),
+        extra columns are named and typed after the autoSql definition embedded in the file (integer and `float`/`double` fields are numeric, other fields are strings). Filters on `chrom`, `start` and `end`
+        are pushed down to the R-tree index of the file.
+    """
+    df = read_file(path, InputFormat.BigBed, None)
+    return lazy_scan(df)
+
+
+def read_gff(
+    path: str,
+    attr_fields: Union[list[str], None] = None,
+    resolve_parents: bool = False,
+) -> pl.LazyFrame:
+    """
+    Read a GFF3 or GTF file into a LazyFrame. The format is detected from the file extension.
+
+    Parameters:
+        path: The path to the file.
+        attr_fields: Attribute keys to extract as top-level columns, e.g. `["gene_id", "transcript_id", "gene_type"]`. Values are strings, except for the integer `exon_number`, `level` and `rank` attributes. All attributes are still available in the `attributes` column.
+        resolve_parents: Whether to add `root_id` and `root_type` columns with the ID and type of the top-level ancestor of each feature, resolved using the `ID` and `Parent` attributes of GFF3, or the `gene_id` and `transcript_id` attributes of GTF.
+
+    !!! Example
+        ```python
+        import polars_bio as pb
+        pb.read_gff("/tmp/gencode.v47.annotation.gtf.gz", attr_fields=["gene_id", "gene_type"]) \
+            .filter(pl.col("gene_type") == "protein_coding") \
+            .collect()
+        ```
+    """
+    input_format = py_get_input_format(path)
+    gff_read_options = GffReadOptions(
+        attr_fields=_cleanse_infos(attr_fields), resolve_parents=resolve_parents
+    )
+    read_options = ReadOptions(gff_read_options=gff_read_options)
+    df = read_file(path, input_format, read_options)
+    return lazy_scan(df)
+
+
+def read_csv(
+    path: str,
+    delimiter: Union[str, None] = None,
+    has_header: bool = True,
+    comment: Union[str, None] = None,
+    schema: Union[pa.Schema, None] = None,
+    null_values: Union[list[str], None] = None,
+    compression: Union[str, None] = None,
+) -> pl.LazyFrame:
+    """
+    Read a delimited text (CSV/TSV) file into a LazyFrame.
+
+    Parameters:
+        path: The path to the file.
+        delimiter: The field delimiter. Defaults to a tab for `.tsv` and `.txt` files and to a comma otherwise.
+        has_header: Whether the first line contains column names.
+        comment: Lines starting with this character are skipped, e.g. `#`.
+        schema: An explicit Arrow schema. If *None*, the schema is inferred.
+        null_values: Values to be interpreted as nulls, e.g. `["NA", "."]`.
+        compression: The compression of the file (`gzip`, `bzip2`, `xz`, `zstd`). If *None*, it is inferred from the file extension.
+
+    !!! Example
+        ```python
+        import polars_bio as pb
+        pb.read_csv("/tmp/annotations.tsv.gz", has_header=False, comment="#", null_values=["."]).collect()
+        ```
+    """
+    csv_read_options = CsvReadOptions(
+        delimiter=delimiter,
+        has_header=has_header,
+        comment=comment,
+        schema=schema,
+        null_values=null_values,
+        compression=compression,
+    )
+    read_options = ReadOptions(csv_read_options=csv_read_options)
+    df = read_file(path, InputFormat.Csv, read_options)
+    return lazy_scan(df)
+
+
 def lazy_scan(df: Union[pl.DataFrame, pl.LazyFrame]) -> pl.LazyFrame:
     df_lazy: DataFrame = df
     arrow_schema = df_lazy.schema()
@@ -244,11 +482,52 @@ def describe_vcf(path: str) -> pl.DataFrame:
     return py_describe_vcf(ctx, path).to_polars()
 
 
+def register_table(
+    path: Union[str, list[str]],
+    name: Union[str, None] = None,
+    input_format: Union[InputFormat, None] = None,
+    read_options: Union[ReadOptions, None] = None,
+) -> str:
+    """
+    Register one or many files as a Datafusion table.
+
+    Parameters:
+        path: The path to a file, a glob pattern (e.g. `/data/*.vcf.gz`) or a list of paths and patterns.
+        name: The name of the table. If *None*, the name is generated from the path. Required for multiple files.
+        input_format: The input format. If *None*, it is detected from the (first) path.
+        read_options: Additional options for reading the input files.
+
+    Returns:
+        The name of the registered table.
+
+    !!! note
+        Files matching a glob pattern or given as a list (even of a single file) are registered as one table with two
+        additional columns: `sample`, the file name without extensions, and `file`, the full path. They must have the same
+        format and schema, a `ValueError` names the first file that differs. Every file is scanned as a separate partition,
+        so files are read in parallel. A single path without wildcards is registered as is, without these columns.
+
+    !!! Example
+        ```python
+        import polars_bio as pb
+        pb.register_table("/data/samples/*.bed.gz", "peaks")
+        pb.sql("SELECT sample, count(*) AS peaks FROM peaks GROUP BY sample").collect()
+        ```
+    """
+    if isinstance(path, str) and not any(c in path for c in "*?["):
+        if input_format is None:
+            input_format = py_get_input_format(path, ctx)
+        return py_register_table(ctx, path, name, input_format, read_options).name
+    paths = [path] if isinstance(path, str) else list(path)
+    if name is None:
+        raise ValueError("A table name is required when registering multiple files")
+    return py_register_tables(ctx, paths, name, input_format, read_options).name
+
+
 def register_vcf(
     path: str,
     name: Union[str, None] = None,
     info_fields: Union[list[str], None] = None,
-    thread_num: int = 1,
+    thread_num: Union[int, None] = None,
     chunk_size: int = 64,
     concurrent_fetches: int = 8,
 ) -> None:
@@ -259,7 +538,7 @@ def register_vcf(
         path: The path to the VCF file.
         name: The name of the table. If *None*, the name of the table will be generated automatically based on the path.
         info_fields: The fields to read from the INFO column.
-        thread_num: The number of threads to use for reading the VCF file. Used **only** for parallel decompression of BGZF blocks. Works only for **local** files.
+        thread_num: The number of threads to use for reading the VCF file. Used **only** for parallel decompression of BGZF blocks. Works only for **local** files. Defaults to the `bio.bgzf_threads` option.
         chunk_size: The size in MB of a chunk when reading from an object store. Default settings are optimized for large scale operations. For small scale (interactive) operations, it is recommended to decrease this value to **8-16**.
         concurrent_fetches: The number of concurrent fetches when reading from an object store. Default settings are optimized for large scale operations. For small scale (interactive) operations, it is recommended to decrease this value to **1-2**.
 
@@ -288,6 +567,122 @@ def register_vcf(
     py_register_table(ctx, path, name, InputFormat.Vcf, read_options)
 
 
+def register_gene_model(
+    table: str,
+    prefix: Union[str, None] = None,
+    upstream: int = 2000,
+    downstream: int = 200,
+) -> list[str]:
+    """
+    Register interval views derived from a registered GTF/GFF table.
+    The following views are registered: `{prefix}_features`, `{prefix}_transcripts`, `{prefix}_exons`,
+    `{prefix}_genes`, `{prefix}_introns`, `{prefix}_tss`, `{prefix}_promoters`, `{prefix}_utr5` and `{prefix}_utr3`.
+    All of them have `chrom`, `start`, `end` and `strand` columns and can be used directly in range operations.
+
+    Parameters:
+        table: The name of a registered GTF/GFF table.
+        prefix: The prefix of the view names. If *None*, the table name is used.
+        upstream: The number of bases upstream of the TSS included in promoters.
+        downstream: The number of bases downstream of the TSS included in promoters.
+
+    !!! note
+        Transcript and gene spans are computed from exons. Introns are the gaps between consecutive exons
+        of a transcript and UTRs are the exon parts outside of the CDS span. Coordinates follow the source table.
+
+    !!! Example
+        ```python
+        import polars_bio as pb
+        pb.read_gff("/tmp/gencode.v47.annotation.gtf")
+        pb.register_gene_model("gencode_v47_annotation", prefix="gencode")
+        pb.overlap("gencode_promoters", "peaks").collect()
+        ```
+    """
+    return py_register_gene_model(ctx, table, prefix, upstream, downstream)
+
+
+def register_paired_fastq(
+    r1: str, r2: Union[str, None] = None, name: Union[str, None] = None
+) -> str:
+    """
+    Register paired-end FASTQ files as one table with `name`, `seq1`, `qual1`, `seq2` and `qual2` columns.
+
+    Parameters:
+        r1: The path to the R1 FASTQ file (optionally compressed), or to an interleaved FASTQ file if `r2` is *None*.
+        r2: The path to the R2 FASTQ file (optionally compressed).
+        name: The name of the table. If *None*, the name is generated from the R1 path.
+
+    Returns:
+        The name of the registered table.
+
+    !!! note
+        Read names are compared without their `/1` and `/2` suffixes, which must be on the first and second mate respectively, and `name` is the common read name.
+        Reading fails if the names of a pair differ or if one file has more reads than the other
+        (an odd number of reads for interleaved input).
+
+    !!! Example
+        ```python
+        import polars_bio as pb
+        pb.register_paired_fastq("sample_R1.fastq.gz", "sample_R2.fastq.gz", name="sample")
+        pb.sql("SELECT name, length(seq1) AS len1, length(seq2) AS len2 FROM sample").collect()
+        ```
+    """
+    return py_register_paired_fastq(ctx, r1, r2, name)
+
+
+def getfasta(
+    df: Union[str, pl.DataFrame, pl.LazyFrame],
+    fasta_path: str,
+    cols: list[str] = ["chrom", "start", "end"],
+    strand_col: Union[str, None] = None,
+) -> pl.LazyFrame:
+    """
+    Extract reference sequences for intervals from an uncompressed FASTA file with random access.
+    The sequence is added as a `sequence` column.
+
+    Parameters:
+        df: A polars DataFrame/LazyFrame or the name of a registered table.
+        fasta_path: The path to the FASTA file. The `.fai` index is used when present (e.g. created with `samtools faidx`), otherwise it is built in memory.
+        cols: The names of columns containing the chromosome, start and end of the intervals.
+        strand_col: The name of the strand column. If set, sequences of intervals on the `-` strand are reverse complemented.
+
+    !!! note
+        Intervals are **0-based**, half-open (BED-like) and clipped to the contig length. Sequences of unknown contigs are *null*.
+        The same function is available in SQL as `getfasta(fasta_path, chrom, start, end [, strand])`.
+
+    !!! Example
+        ```python
+        import polars_bio as pb
+        pb.getfasta("variants_context", "/tmp/GRCh38.fa").collect()
+        pb.sql("SELECT getfasta('/tmp/GRCh38.fa', chrom, start - 10, \"end\" + 10, '+') AS context FROM variants").collect()
+        ```
+    """
+    if isinstance(df, str):
+        return _getfasta(df, fasta_path, cols, strand_col)
+    table = f"getfasta_input_{uuid.uuid4().hex}"
+    from_polars(table, df)
+    try:
+        return _getfasta(table, fasta_path, cols, strand_col)
+    finally:
+        # the returned frame keeps the resolved table
+        py_deregister_table(ctx, table)
+
+
+def _getfasta(
+    table: str, fasta_path: str, cols: list[str], strand_col: Union[str, None]
+) -> pl.LazyFrame:
+    escaped_path = fasta_path.replace("'", "''")
+    args = [f"'{escaped_path}'"] + [_quote_identifier(c) for c in cols]
+    if strand_col is not None:
+        args.append(_quote_identifier(strand_col))
+    return sql(
+        f"SELECT *, getfasta({', '.join(args)}) AS sequence FROM {_quote_identifier(table)}"
+    )
+
+
+def _quote_identifier(name: str) -> str:
+    return '"' + name.replace('"', '""') + '"'
+
+
 def register_view(name: str, query: str) -> None:
     """
     Register a query as a Datafusion view. This view can be used in genomic ranges operations,
@@ -385,6 +780,196 @@ def from_polars(name: str, df: Union[pl.DataFrame, pl.LazyFrame]) -> None:
     py_from_polars(ctx, name, reader)
 
 
+def write_vcf(df: Union[str, pl.DataFrame, pl.LazyFrame], path: str) -> int:
+    """
+    Write a table or a DataFrame with the VCF table schema to a VCF file.
+    The output is BGZF-compressed if the path ends with `.gz` or `.bgz`.
+
+    Parameters:
+        df: The name of a registered table or a Polars DataFrame/LazyFrame.
+        path: The path to the output file.
+
+    !!! note
+        The `start` column is written as `POS`, so it is expected to be **1-based**.
+        The header of a table registered from a VCF file is reused. Otherwise `##contig`
+        lines are written for the `chrom` values and INFO header lines are reconstructed
+        from the non-fixed columns. FORMAT and sample columns are not written.
+    """
+    return _write(df, path, OutputFormat.Vcf)
+
+
+def write_bed(df: Union[str, pl.DataFrame, pl.LazyFrame], path: str) -> int:
+    """
+    Write a table or a DataFrame to a BED file. All columns are written in order,
+    so the first three are expected to be `chrom`, `start` and `end`.
+
+    Parameters:
+        df: The name of a registered table or a Polars DataFrame/LazyFrame.
+        path: The path to the output file.
+    """
+    return _write(df, path, OutputFormat.Bed)
+
+
+def write_bedgraph(
+    df: Union[str, pl.DataFrame, pl.LazyFrame],
+    path: str,
+    chrom_sizes: Union[str, dict, pl.DataFrame, None] = None,
+    cols: list[str] = ["chrom", "start", "end"],
+    value_column: Union[str, None] = None,
+) -> int:
+    """
+    Write a table or a DataFrame with exactly four columns (chrom, start, end, value) to a bedGraph file.
+    If `chrom_sizes` is given, any signal table (e.g. the output of [coverage](api.md#polars_bio.coverage)) is written
+    as a track, see [write_bigwig](api.md#polars_bio.write_bigwig).
+
+    Parameters:
+        df: The name of a registered table or a Polars DataFrame/LazyFrame.
+        path: The path to the output file.
+        chrom_sizes: The chromosome sizes: a path to a two-column `chrom.sizes` file, a dict or a DataFrame.
+        cols: The names of columns containing the chromosome, start and end of the intervals. Used with `chrom_sizes`.
+        value_column: The name of the column with values. If *None*, the last column is used. Used with `chrom_sizes`.
+    """
+    if chrom_sizes is None:
+        return _write(df, path, OutputFormat.BedGraph)
+    return _write_track(
+        df, path, chrom_sizes, cols, value_column, OutputFormat.BedGraph
+    )
+
+
+def write_bigwig(
+    df: Union[str, pl.DataFrame, pl.LazyFrame],
+    path: str,
+    chrom_sizes: Union[str, dict, pl.DataFrame],
+    cols: list[str] = ["chrom", "start", "end"],
+    value_column: Union[str, None] = None,
+) -> int:
+    """
+    Write a signal table or DataFrame, e.g. depth or the output of [coverage](api.md#polars_bio.coverage), to a bigWig file.
+
+    Parameters:
+        df: The name of a registered table or a Polars DataFrame/LazyFrame.
+        path: The path to the output file.
+        chrom_sizes: The chromosome sizes: a path to a two-column `chrom.sizes` file, a dict or a DataFrame.
+        cols: The names of columns containing the chromosome, start and end of the intervals.
+        value_column: The name of the column with values. If *None*, the last column is used (`coverage` or `count`).
+
+    Returns:
+        The number of written intervals.
+
+    !!! note
+        Intervals are expected to be **0-based**, half-open and non-overlapping. They are clipped to the chromosome sizes
+        and adjacent intervals with equal values are merged. Zoom levels are computed for fast browsing in genome browsers.
+
+    !!! Example
+        ```python
+        import polars_bio as pb
+        cov = pb.coverage("/tmp/reads.parquet", "/tmp/windows.parquet", output_type="polars.DataFrame")
+        pb.write_bigwig(cov, "/tmp/coverage.bw", "/tmp/hg38.chrom.sizes")
+        ```
+    """
+    return _write_track(df, path, chrom_sizes, cols, value_column, OutputFormat.BigWig)
+
+
+def write_gff(df: Union[str, pl.DataFrame, pl.LazyFrame], path: str) -> int:
+    """
+    Write a table or a DataFrame with the GFF table schema to a GFF3 file.
+
+    Parameters:
+        df: The name of a registered table or a Polars DataFrame/LazyFrame.
+        path: The path to the output file.
+    """
+    return _write(df, path, OutputFormat.Gff)
+
+
+def write_fastq(df: Union[str, pl.DataFrame, pl.LazyFrame], path: str) -> int:
+    """
+    Write a table or a DataFrame with the FASTQ table schema to a FASTQ file.
+
+    Parameters:
+        df: The name of a registered table or a Polars DataFrame/LazyFrame.
+        path: The path to the output file.
+
+    !!! Example
+        ```python
+        import polars_bio as pb
+        pb.read_fastq("example.fastq")
+        pb.register_view("long_reads", "SELECT * FROM example WHERE length(sequence) > 100")
+        pb.write_fastq("long_reads", "/tmp/long_reads.fastq.gz")
+        ```
+    """
+    return _write(df, path, OutputFormat.Fastq)
+
+
+def write_bam(
+    df: Union[str, pl.DataFrame, pl.LazyFrame],
+    path: str,
+    header_path: str,
+    sort: bool = False,
+    index: bool = False,
+) -> int:
+    """
+    Write a table or a DataFrame with the BAM table schema to a BAM file.
+
+    Parameters:
+        df: The name of a registered table or a Polars DataFrame/LazyFrame.
+        path: The path to the output BAM file.
+        header_path: The path to the BAM file the header is copied from (usually the input BAM).
+        sort: Whether to coordinate-sort the records using the reference sequence order of the header.
+        index: Whether to write a `.bai` index next to the output file. Implies `sort`.
+
+    !!! Example
+        ```python
+        import polars_bio as pb
+        pb.read_bam("/tmp/NA12878.bam")
+        pb.register_view("mapped", "SELECT * FROM na12878 WHERE mapping_quality >= 30")
+        pb.write_bam("mapped", "/tmp/NA12878.mapq30.bam", header_path="/tmp/NA12878.bam", index=True)
+        ```
+    """
+    if not isinstance(df, str):
+        name = "bam_sink"
+        from_polars(name, df)
+        df = name
+    return py_write_bam(ctx, df, path, header_path, sort, index)
+
+
+def _write(
+    df: Union[str, pl.DataFrame, pl.LazyFrame], path: str, output_format: OutputFormat
+) -> int:
+    if isinstance(df, str):
+        return py_write_table(ctx, df, path, output_format)
+    if isinstance(df, pl.LazyFrame):
+        df = df.collect()
+    contigs = (
+        df["chrom"].unique(maintain_order=True).cast(pl.Utf8).to_list()
+        if output_format == OutputFormat.Vcf and "chrom" in df.columns
+        else []
+    )
+    return py_write_frame(df.to_arrow().to_reader(), path, output_format, contigs)
+
+
+def _write_track(
+    df: Union[str, pl.DataFrame, pl.LazyFrame],
+    path: str,
+    chrom_sizes: Union[str, dict, pl.DataFrame],
+    cols: list[str],
+    value_column: Union[str, None],
+    output_format: OutputFormat,
+) -> int:
+    if not isinstance(df, str):
+        name = "track_sink"
+        from_polars(name, df)
+        df = name
+    if isinstance(chrom_sizes, str):
+        chrom_sizes = pl.read_csv(
+            chrom_sizes, separator="\t", has_header=False, comment_prefix="#"
+        )
+    if isinstance(chrom_sizes, pl.DataFrame):
+        chrom_sizes = dict(chrom_sizes.select(chrom_sizes.columns[:2]).iter_rows())
+    return py_write_track(
+        ctx, df, path, list(chrom_sizes.items()), cols, value_column, output_format
+    )
+
+
 def _cleanse_infos(t: Union[list[str], None]) -> Union[list[str], None]:
     if t is None:
         return None
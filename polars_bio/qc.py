@@ -0,0 +1,64 @@
+import json
+import os
+from typing import Union
+
+import polars as pl
+
+from polars_bio.polars_bio import py_fastq_qc
+
+from .context import ctx
+from .io import register_table
+
+
+def fastq_qc(
+    data: str,
+    output_dir: Union[str, None] = None,
+    sequence_column: str = "sequence",
+    quality_column: str = "quality_scores",
+    phred_offset: int = 33,
+) -> dict[str, pl.DataFrame]:
+    """
+    Compute FastQC quality control modules of FASTQ reads in a single parallel pass.
+
+    Parameters:
+        data: The path to a FASTQ file (or a glob pattern) or the name of a registered table.
+        output_dir: If set, every module is also written to `{output_dir}/fastqc_{module}.json` in the fastqc-rs format.
+        sequence_column: The column with read sequences.
+        quality_column: The column with Phred quality strings.
+        phred_offset: The quality encoding offset, 33 (Sanger/Illumina 1.8+) or 64 (Illumina 1.3-1.7).
+
+    Returns:
+        A dictionary of DataFrames: `per_base_quality`, `per_sequence_quality`, `per_base_content`,
+        `per_sequence_gc`, `length_distribution`, `duplication_levels`, `overrepresented_sequences`
+        and `adapter_content`.
+
+    !!! note
+        Positions are reported individually, as with FastQC `--nogroup`. Like FastQC, duplication levels and
+        overrepresented sequences are estimated from at most 100,000 distinct sequences, reads longer than
+        75 bases are compared by their first 50 bases. Unlike FastQC, which tracks the first sequences of
+        the file, past the limit a hash-based sample of sequences is tracked, so results do not depend on the
+        number of partitions.
+
+    !!! Example
+        ```python
+        import polars_bio as pb
+        qc = pb.fastq_qc("example.fastq", output_dir="qc")
+        qc["per_base_quality"].head()
+        ```
+    """
+    if os.path.exists(data) or "://" in data:
+        data = register_table(data)
+    elif any(c in data for c in "*?["):
+        data = register_table(data, "fastq_qc_input")
+    modules = {
+        name: df.to_polars()
+        for name, df in py_fastq_qc(
+            ctx, data, sequence_column, quality_column, phred_offset
+        )
+    }
+    if output_dir is not None:
+        os.makedirs(output_dir, exist_ok=True)
+        for name, df in modules.items():
+            with open(os.path.join(output_dir, f"fastqc_{name}.json"), "w") as f:
+                json.dump({"values": df.to_dicts()}, f, indent=4, sort_keys=True)
+    return modules
@@ -8,7 +8,7 @@ from typing_extensions import TYPE_CHECKING, Union
 
 from polars_bio.polars_bio import ReadOptions
 
-from .constants import DEFAULT_INTERVAL_COLUMNS
+from .constants import DEFAULT_INTERVAL_COLUMNS, DEFAULT_PAIRED_INTERVAL_COLUMNS
 from .context import ctx
 from .interval_op_helpers import convert_result, get_py_ctx, read_df_to_datafusion
 from .range_op_helpers import _validate_overlap_input, range_operation
@@ -114,6 +114,72 @@ def overlap(
     )
 
 
+def paired_overlap(
+    df1: Union[str, pl.DataFrame, pl.LazyFrame, pd.DataFrame],
+    df2: Union[str, pl.DataFrame, pl.LazyFrame, pd.DataFrame],
+    overlap_filter: FilterOp = FilterOp.Strict,
+    suffixes: tuple[str, str] = ("_1", "_2"),
+    cols1: Union[list[str], None] = DEFAULT_PAIRED_INTERVAL_COLUMNS,
+    cols2: Union[list[str], None] = DEFAULT_PAIRED_INTERVAL_COLUMNS,
+    algorithm: str = "Coitrees",
+    output_type: str = "polars.LazyFrame",
+    streaming: bool = False,
+    read_options1: Union[ReadOptions, None] = None,
+    read_options2: Union[ReadOptions, None] = None,
+) -> Union[pl.LazyFrame, pl.DataFrame, pd.DataFrame, datafusion.DataFrame]:
+    """
+    Find pairs of overlapping paired intervals (e.g. BEDPE records or Hi-C contacts), i.e. rows
+    where **both** ends overlap their counterparts: the first end with the first end and the second end with the second end.
+
+    Parameters:
+        df1: Can be a path to a file, a polars DataFrame, or a pandas DataFrame or a registered table. BEDPE, pairs and Parquet are supported.
+        df2: Can be a path to a file, a polars DataFrame, or a pandas DataFrame or a registered table. BEDPE, pairs and Parquet are supported.
+        overlap_filter: FilterOp, optional. The type of overlap to consider(Weak or Strict). Strict for **0-based**, Weak for **1-based** coordinate systems.
+        suffixes: Suffixes for the columns of the two overlapped sets.
+        cols1: The names of columns containing the chromosome, start and end of the first end followed by
+            the chromosome, start and end of the second end, provided separately for each set.
+        cols2: The names of columns containing the chromosome, start and end of the first end followed by
+            the chromosome, start and end of the second end, provided separately for each set.
+        algorithm: The algorithm to use for the overlap operation.
+        output_type: Type of the output. default is "polars.LazyFrame", "polars.DataFrame", or "pandas.DataFrame" or "datafusion.DataFrame" are also supported.
+        streaming: **EXPERIMENTAL** If True, use Polars [streaming](features.md#streaming) engine.
+        read_options1: Additional options for reading the input files.
+        read_options2: Additional options for reading the input files.
+
+    Returns:
+        **polars.LazyFrame** or polars.DataFrame or pandas.DataFrame of the overlapping paired intervals.
+
+    Example:
+        ```python
+        import polars_bio as pb
+
+        loops = pb.read_bedpe("loops.bedpe").collect()
+        contacts = pb.read_pairs("contacts.pairs.gz").collect()
+        supported = pb.paired_overlap(loops, contacts, output_type="polars.DataFrame")
+        ```
+    """
+    _validate_overlap_input(cols1, cols2, None, suffixes, output_type, how="inner")
+
+    cols1 = DEFAULT_PAIRED_INTERVAL_COLUMNS if cols1 is None else cols1
+    cols2 = DEFAULT_PAIRED_INTERVAL_COLUMNS if cols2 is None else cols2
+    if len(cols1) != 6 or len(cols2) != 6:
+        raise ValueError(
+            "Paired overlap requires 6 interval columns: chrom, start and end of both ends"
+        )
+    range_options = RangeOptions(
+        range_op=RangeOp.PairedOverlap,
+        filter_op=overlap_filter,
+        suffixes=suffixes,
+        columns_1=cols1,
+        columns_2=cols2,
+        overlap_alg=algorithm,
+        streaming=streaming,
+    )
+    return range_operation(
+        df1, df2, range_options, output_type, ctx, read_options1, read_options2
+    )
+
+
 def nearest(
     df1: Union[str, pl.DataFrame, pl.LazyFrame, pd.DataFrame],
     df2: Union[str, pl.DataFrame, pl.LazyFrame, pd.DataFrame],
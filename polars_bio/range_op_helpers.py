@@ -1,4 +1,4 @@
-from pathlib import Path
+import shutil
 from typing import Union
 
 import pandas as pd
@@ -29,15 +29,7 @@ def range_operation(
 ) -> Union[pl.LazyFrame, pl.DataFrame, pd.DataFrame]:
     ctx.sync_options()
     if isinstance(df1, str) and isinstance(df2, str):
-        supported_exts = set([".parquet", ".csv", ".bed", ".vcf"])
-        ext1 = set(Path(df1).suffixes)
-        assert (
-            len(supported_exts.intersection(ext1)) > 0 or len(ext1) == 0
-        ), "Dataframe1 must be a Parquet, a BED or CSV or VCF file"
-        ext2 = set(Path(df2).suffixes)
-        assert (
-            len(supported_exts.intersection(ext2)) > 0 or len(ext2) == 0
-        ), "Dataframe2 must be a Parquet, a BED or CSV or VCF file"
+        # input formats are detected (and validated) on the Rust side
         # use suffixes to avoid column name conflicts
         if range_options.streaming:
             # FIXME: Parallelism is not supported
@@ -159,9 +151,6 @@ def stream_wrapper(pyldf):
 
 
 def tmp_cleanup(session_catalog_path: str):
-    # remove temp parquet files
+    # remove temp parquet files and staged inputs
     logger.info(f"Cleaning up temp files for catalog path: '{session_catalog_path}'")
-    path = Path(session_catalog_path)
-    for path in path.glob("*.parquet"):
-        path.unlink(missing_ok=True)
-    path.rmdir()
+    shutil.rmtree(session_catalog_path, ignore_errors=True)
@@ -15,6 +15,7 @@ from polars_bio.polars_bio import (
     InputFormat,
     RangeOptions,
     ReadOptions,
+    py_get_input_format,
     py_read_table,
     py_register_table,
 )
@@ -106,36 +107,33 @@ def _get_schema(
     suffix=None,
     read_options: Union[ReadOptions, None] = None,
 ) -> pl.Schema:
-    ext = Path(path).suffixes
-    if len(ext) == 0:
-        df: DataFrame = py_read_table(ctx, path)
-        arrow_schema = df.schema()
-        empty_table = pa.Table.from_arrays(
-            [pa.array([], type=field.type) for field in arrow_schema],
-            schema=arrow_schema,
-        )
-        df = pl.from_arrow(empty_table)
-
-    elif ext[-1] == ".parquet":
-        df = pl.read_parquet(path)
-    elif ".csv" in ext:
-        df = pl.read_csv(path)
-    elif ".vcf" in ext:
-        table = py_register_table(ctx, path, None, InputFormat.Vcf, read_options)
-        df: DataFrame = py_read_table(ctx, table.name)
-        arrow_schema = df.schema()
-        empty_table = pa.Table.from_arrays(
-            [pa.array([], type=field.type) for field in arrow_schema],
-            schema=arrow_schema,
-        )
-        df = pl.from_arrow(empty_table)
+    if _is_path(path):
+        input_format = py_get_input_format(path, ctx)
+        if input_format == InputFormat.Parquet:
+            df = pl.read_parquet(path)
+        else:
+            table = py_register_table(ctx, path, None, input_format, read_options)
+            df = _empty_frame(py_read_table(ctx, table.name))
     else:
-        raise ValueError("Only CSV and Parquet files are supported")
+        df = _empty_frame(py_read_table(ctx, path))
     if suffix is not None:
         df = _rename_columns(df, suffix)
     return df.schema
 
 
+def _is_path(path: str) -> bool:
+    return "://" in path or "/" in path or Path(path).is_file()
+
+
+def _empty_frame(df: DataFrame) -> pl.DataFrame:
+    arrow_schema = df.schema()
+    empty_table = pa.Table.from_arrays(
+        [pa.array([], type=field.type) for field in arrow_schema],
+        schema=arrow_schema,
+    )
+    return pl.from_arrow(empty_table)
+
+
 def _df_to_arrow(df: pd.DataFrame, col: str) -> pa.Table:
     table_1 = pa.Table.from_pandas(df)
     return _string_to_largestring(table_1, col)
@@ -0,0 +1,99 @@
+import os
+from typing import Union
+
+from .io import register_table, register_view, write_fastq
+
+
+def _quote(value: str) -> str:
+    return "'" + value.replace("'", "''") + "'"
+
+
+def trim_fastq(
+    data: str,
+    name: Union[str, None] = None,
+    output_path: Union[str, None] = None,
+    adapter: Union[str, None] = None,
+    adapter_error_rate: float = 0.1,
+    adapter_min_overlap: int = 3,
+    window_size: Union[int, None] = None,
+    window_quality: int = 20,
+    mott_limit: Union[float, None] = None,
+    poly_g: bool = False,
+    poly_a: bool = False,
+    poly_min_length: int = 10,
+    min_length: int = 0,
+    phred_offset: int = 33,
+) -> str:
+    """
+    Trim and filter FASTQ reads in a single streaming query, a replacement for simple fastp preprocessing steps.
+
+    Steps are applied in order: adapter trimming, poly-G and poly-A tail trimming, sliding window and Mott
+    quality trimming, and finally length filtering.
+
+    Parameters:
+        data: The path to a FASTQ file (or a glob pattern) or the name of a registered table.
+        name: The name of the registered view with trimmed reads. If *None*, `{table}_trimmed` is used. Files matching
+            a glob pattern are registered as the `{name}_input` table, `fastq_input` if `name` is *None*.
+        output_path: If set, trimmed reads are also written to this FASTQ file.
+        adapter: The 3' adapter sequence to trim, also partially overlapping the read end.
+        adapter_error_rate: The maximum fraction of mismatching bases in the adapter alignment.
+        adapter_min_overlap: The minimum overlap of a partial adapter at the read end.
+        window_size: If set, reads are cut at the first window of this size with a mean quality below `window_quality`.
+        window_quality: The minimum mean quality of a sliding window.
+        mott_limit: If set, reads are trimmed with the modified Mott algorithm with this error probability limit.
+        poly_g: Trim 3' poly-G tails (two-color chemistry artifacts).
+        poly_a: Trim 3' poly-A tails.
+        poly_min_length: The minimum length of a trimmed poly-X tail.
+        min_length: Reads shorter than this after trimming are removed.
+        phred_offset: The quality encoding offset, 33 (Sanger/Illumina 1.8+) or 64 (Illumina 1.3-1.7).
+
+    Returns:
+        The name of the registered view.
+
+    !!! Example
+        ```python
+        import polars_bio as pb
+        pb.trim_fastq("example.fastq", adapter="AGATCGGAAGAGC", window_size=4, poly_g=True, min_length=36,
+                      output_path="/tmp/trimmed.fastq.gz")
+        pb.sql("SELECT * FROM example_trimmed").collect()
+        ```
+    """
+    if os.path.exists(data) or "://" in data:
+        data = register_table(data)
+    elif any(c in data for c in "*?["):
+        # the view reads from the input table, so it is named after the view
+        data = register_table(data, f"{name or 'fastq'}_input")
+    name = name or f"{data}_trimmed"
+
+    trims = []
+    if adapter is not None:
+        trims.append(
+            f"trim_adapter(sequence, quality_scores, {_quote(adapter)}, "
+            f"CAST({adapter_error_rate} AS DOUBLE), {adapter_min_overlap})"
+        )
+    for base, enabled in (("G", poly_g), ("A", poly_a)):
+        if enabled:
+            trims.append(
+                f"trim_poly_tail(sequence, quality_scores, '{base}', {poly_min_length})"
+            )
+    if window_size is not None:
+        trims.append(
+            f"trim_quality_window(sequence, quality_scores, {window_size}, {window_quality}, {phred_offset})"
+        )
+    if mott_limit is not None:
+        trims.append(
+            f"trim_quality_mott(sequence, quality_scores, CAST({mott_limit} AS DOUBLE), {phred_offset})"
+        )
+
+    query = f"SELECT name, description, sequence, quality_scores FROM {data}"
+    for trim in trims:
+        query = (
+            f"SELECT name, description, t['sequence'] AS sequence, t['quality'] AS quality_scores "
+            f"FROM (SELECT name, description, {trim} AS t FROM ({query}))"
+        )
+    register_view(
+        name, f"SELECT * FROM ({query}) WHERE length(sequence) >= {min_length}"
+    )
+    if output_path is not None:
+        write_fastq(name, output_path)
+    return name
@@ -0,0 +1,362 @@
+use std::fmt::{Debug, Formatter};
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, Int64Builder, MapBuilder, RecordBatch, StringBuilder};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use datafusion::common::{DataFusionError, Result};
+use datafusion::datasource::streaming::StreamingTable;
+use datafusion::execution::{SendableRecordBatchStream, TaskContext};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::streaming::PartitionStream;
+
+use crate::option::InputFormat;
+use crate::scan::open_decompressed;
+
+const BATCH_SIZE: usize = 8192;
+const SAM_HEADER_PREFIX: char = '@';
+const SAM_MISSING_VALUE: &str = "*";
+const SAM_SAME_REFERENCE: &str = "=";
+const SAM_REVERSE_FLAG: i64 = 0x10;
+/// Mapping quality of alignments without one, in both PAF and SAM.
+const MAPQ_MISSING_VALUE: &str = "255";
+const PAF_MANDATORY_FIELDS: usize = 12;
+const PAF_MAPQ_FIELD: usize = 11;
+const SAM_MANDATORY_FIELDS: usize = 11;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ColumnType {
+    Utf8,
+    Int64,
+    Tags,
+}
+
+/// PAF target coordinates are exposed as `chrom`, `start` and `end` (0-based, half-open,
+/// as in the file, unlike the 1-based SAM columns), so alignments can be used directly in
+/// range operations.
+const PAF_COLUMNS: [(&str, ColumnType); 13] = [
+    ("query_name", ColumnType::Utf8),
+    ("query_length", ColumnType::Int64),
+    ("query_start", ColumnType::Int64),
+    ("query_end", ColumnType::Int64),
+    ("strand", ColumnType::Utf8),
+    ("chrom", ColumnType::Utf8),
+    ("target_length", ColumnType::Int64),
+    ("start", ColumnType::Int64),
+    ("end", ColumnType::Int64),
+    ("residue_matches", ColumnType::Int64),
+    ("alignment_length", ColumnType::Int64),
+    ("mapping_quality", ColumnType::Int64),
+    ("tags", ColumnType::Tags),
+];
+
+/// SAM alignments use 1-based, closed `start` and `end`, the latter computed from the CIGAR.
+const SAM_COLUMNS: [(&str, ColumnType); 14] = [
+    ("name", ColumnType::Utf8),
+    ("flag", ColumnType::Int64),
+    ("chrom", ColumnType::Utf8),
+    ("start", ColumnType::Int64),
+    ("end", ColumnType::Int64),
+    ("strand", ColumnType::Utf8),
+    ("mapping_quality", ColumnType::Int64),
+    ("cigar", ColumnType::Utf8),
+    ("mate_chrom", ColumnType::Utf8),
+    ("mate_start", ColumnType::Int64),
+    ("template_length", ColumnType::Int64),
+    ("sequence", ColumnType::Utf8),
+    ("quality_scores", ColumnType::Utf8),
+    ("tags", ColumnType::Tags),
+];
+
+/// Returns a table provider reading a (optionally gzipped) PAF or SAM text file.
+pub(crate) fn alignment_table(path: &str, format: &InputFormat) -> Result<StreamingTable> {
+    let columns = match format {
+        InputFormat::Paf => &PAF_COLUMNS[..],
+        InputFormat::Sam => &SAM_COLUMNS[..],
+        _ => {
+            return Err(DataFusionError::Plan(format!(
+                "Not an alignment text format: {}",
+                format
+            )))
+        },
+    };
+    let schema = Arc::new(Schema::new(
+        columns
+            .iter()
+            .map(|(name, column_type)| Field::new(*name, column_type.data_type(), true))
+            .collect::<Vec<Field>>(),
+    ));
+    let partition = AlignmentPartition {
+        path: path.to_string(),
+        format: format.clone(),
+        schema: schema.clone(),
+    };
+    StreamingTable::try_new(schema, vec![Arc::new(partition)])
+}
+
+impl ColumnType {
+    fn data_type(&self) -> DataType {
+        match self {
+            ColumnType::Utf8 => DataType::Utf8,
+            ColumnType::Int64 => DataType::Int64,
+            // tags are keyed by their two-letter name, the type character is dropped
+            ColumnType::Tags => tags_builder().finish().data_type().clone(),
+        }
+    }
+}
+
+fn tags_builder() -> MapBuilder<StringBuilder, StringBuilder> {
+    MapBuilder::new(None, StringBuilder::new(), StringBuilder::new())
+}
+
+struct AlignmentPartition {
+    path: String,
+    format: InputFormat,
+    schema: SchemaRef,
+}
+
+impl Debug for AlignmentPartition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "AlignmentPartition {{ path: {}, format: {} }}",
+            self.path, self.format
+        )
+    }
+}
+
+impl PartitionStream for AlignmentPartition {
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    fn execute(&self, _ctx: Arc<TaskContext>) -> SendableRecordBatchStream {
+        let batches: Box<dyn Iterator<Item = Result<RecordBatch>> + Send> = match open(&self.path) {
+            Ok(reader) => Box::new(AlignmentBatchIterator {
+                lines: reader.lines(),
+                format: self.format.clone(),
+                schema: self.schema.clone(),
+            }),
+            Err(e) => Box::new(std::iter::once(Err(e))),
+        };
+        Box::pin(RecordBatchStreamAdapter::new(
+            self.schema.clone(),
+            futures::stream::iter(batches),
+        ))
+    }
+}
+
+fn open(path: &str) -> Result<Box<dyn BufRead + Send>> {
+    Ok(Box::new(BufReader::new(open_decompressed(path, 1)?)))
+}
+
+struct AlignmentBatchIterator {
+    lines: std::io::Lines<Box<dyn BufRead + Send>>,
+    format: InputFormat,
+    schema: SchemaRef,
+}
+
+impl AlignmentBatchIterator {
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
+        let mut builder = AlignmentBatchBuilder::new(&self.schema);
+        while builder.len < BATCH_SIZE {
+            let Some(line) = self.lines.next() else {
+                break;
+            };
+            let line = line?;
+            if line.is_empty() || line.starts_with(SAM_HEADER_PREFIX) {
+                continue;
+            }
+            let fields = line.split('\t').collect::<Vec<&str>>();
+            match self.format {
+                InputFormat::Paf => builder.append_paf(&fields)?,
+                _ => builder.append_sam(&fields)?,
+            }
+        }
+        if builder.len == 0 {
+            return Ok(None);
+        }
+        builder.finish(&self.schema).map(Some)
+    }
+}
+
+impl Iterator for AlignmentBatchIterator {
+    type Item = Result<RecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_batch().transpose()
+    }
+}
+
+enum ColumnBuilder {
+    Utf8(StringBuilder),
+    Int64(Int64Builder),
+    Tags(MapBuilder<StringBuilder, StringBuilder>),
+}
+
+impl ColumnBuilder {
+    fn append(&mut self, value: Option<&str>) -> Result<()> {
+        match self {
+            ColumnBuilder::Utf8(builder) => builder.append_option(value),
+            ColumnBuilder::Int64(builder) => match value {
+                Some(v) => builder.append_value(v.parse::<i64>().map_err(|_| {
+                    DataFusionError::Execution(format!("Invalid integer field: {}", v))
+                })?),
+                None => builder.append_null(),
+            },
+            ColumnBuilder::Tags(builder) => {
+                for tag in value.unwrap_or_default().split('\t') {
+                    // TAG:TYPE:VALUE
+                    let mut parts = tag.splitn(3, ':');
+                    if let (Some(key), Some(_), Some(v)) =
+                        (parts.next(), parts.next(), parts.next())
+                    {
+                        builder.keys().append_value(key);
+                        builder.values().append_value(v);
+                    }
+                }
+                builder.append(true)?;
+            },
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            ColumnBuilder::Utf8(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Int64(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Tags(builder) => Arc::new(builder.finish()),
+        }
+    }
+}
+
+struct AlignmentBatchBuilder {
+    columns: Vec<ColumnBuilder>,
+    len: usize,
+}
+
+impl AlignmentBatchBuilder {
+    fn new(schema: &SchemaRef) -> Self {
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|field| match field.data_type() {
+                DataType::Utf8 => ColumnBuilder::Utf8(StringBuilder::new()),
+                DataType::Int64 => ColumnBuilder::Int64(Int64Builder::new()),
+                _ => ColumnBuilder::Tags(tags_builder()),
+            })
+            .collect();
+        AlignmentBatchBuilder { columns, len: 0 }
+    }
+
+    fn append_paf(&mut self, fields: &[&str]) -> Result<()> {
+        if fields.len() < PAF_MANDATORY_FIELDS {
+            return Err(DataFusionError::Execution(format!(
+                "Invalid PAF record, expected at least {} fields: {}",
+                PAF_MANDATORY_FIELDS,
+                fields.join("\t")
+            )));
+        }
+        for (i, field) in fields[..PAF_MANDATORY_FIELDS].iter().enumerate() {
+            let value = match i {
+                PAF_MAPQ_FIELD => missing(field, MAPQ_MISSING_VALUE),
+                _ => Some(*field),
+            };
+            self.columns[i].append(value)?;
+        }
+        let tags = fields[PAF_MANDATORY_FIELDS..].join("\t");
+        self.columns[PAF_MANDATORY_FIELDS].append(Some(&tags))?;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn append_sam(&mut self, fields: &[&str]) -> Result<()> {
+        if fields.len() < SAM_MANDATORY_FIELDS {
+            return Err(DataFusionError::Execution(format!(
+                "Invalid SAM record, expected at least {} fields: {}",
+                SAM_MANDATORY_FIELDS,
+                fields.join("\t")
+            )));
+        }
+        let flag = fields[1]
+            .parse::<i64>()
+            .map_err(|_| DataFusionError::Execution(format!("Invalid SAM flag: {}", fields[1])))?;
+        let chrom = missing(fields[2], SAM_MISSING_VALUE);
+        let start = missing(fields[3], "0").filter(|_| chrom.is_some());
+        let end = match (start, reference_length(fields[5])) {
+            (Some(start), Some(length)) if length > 0 => {
+                Some((start.parse::<i64>().unwrap_or_default() + length - 1).to_string())
+            },
+            (Some(start), _) => Some(start.to_string()),
+            _ => None,
+        };
+        let strand = if flag & SAM_REVERSE_FLAG != 0 {
+            "-"
+        } else {
+            "+"
+        };
+        let mate_chrom = match fields[6] {
+            SAM_SAME_REFERENCE => chrom,
+            v => missing(v, SAM_MISSING_VALUE),
+        };
+        let tags = fields[SAM_MANDATORY_FIELDS..].join("\t");
+        let values = [
+            Some(fields[0]),
+            Some(fields[1]),
+            chrom,
+            start,
+            end.as_deref(),
+            chrom.map(|_| strand),
+            missing(fields[4], MAPQ_MISSING_VALUE),
+            missing(fields[5], SAM_MISSING_VALUE),
+            mate_chrom,
+            missing(fields[7], "0"),
+            Some(fields[8]),
+            missing(fields[9], SAM_MISSING_VALUE),
+            missing(fields[10], SAM_MISSING_VALUE),
+            Some(tags.as_str()),
+        ];
+        for (column, value) in self.columns.iter_mut().zip(values) {
+            column.append(value)?;
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    fn finish(mut self, schema: &SchemaRef) -> Result<RecordBatch> {
+        let columns = self
+            .columns
+            .iter_mut()
+            .map(|column| column.finish())
+            .collect::<Vec<ArrayRef>>();
+        Ok(RecordBatch::try_new(schema.clone(), columns)?)
+    }
+}
+
+fn missing<'a>(value: &'a str, missing_value: &str) -> Option<&'a str> {
+    if value == missing_value {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Number of reference bases consumed by a CIGAR string (M, D, N, = and X operations).
+fn reference_length(cigar: &str) -> Option<i64> {
+    if cigar == SAM_MISSING_VALUE {
+        return None;
+    }
+    let mut length = 0;
+    let mut count = 0i64;
+    for c in cigar.chars() {
+        match c {
+            '0'..='9' => count = count * 10 + c.to_digit(10)? as i64,
+            'M' | 'D' | 'N' | '=' | 'X' => {
+                length += count;
+                count = 0;
+            },
+            _ => count = 0,
+        }
+    }
+    Some(length)
+}
@@ -0,0 +1,1186 @@
+use std::any::Any;
+use std::collections::VecDeque;
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, Float64Builder, Int64Builder, RecordBatch, StringBuilder};
+use arrow::compute::cast;
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use async_trait::async_trait;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use datafusion::catalog::{Session, TableProvider};
+use datafusion::common::{DataFusionError, Result, ScalarValue};
+use datafusion::datasource::TableType;
+use datafusion::execution::{SendableRecordBatchStream, TaskContext};
+use datafusion::logical_expr::{BinaryExpr, Operator, TableProviderFilterPushDown};
+use datafusion::physical_expr::{EquivalenceProperties, Partitioning};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::{
+    DisplayAs, DisplayFormatType, ExecutionMode, ExecutionPlan, PlanProperties,
+};
+use datafusion::prelude::Expr;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use log::debug;
+
+use crate::bed::BED_COLUMNS;
+
+pub(crate) const BIGWIG_MAGIC: u32 = 0x888F_FC26;
+pub(crate) const BIGBED_MAGIC: u32 = 0x8789_F2EB;
+const CHROM_TREE_MAGIC: u32 = 0x78CA_8C91;
+const CIR_TREE_MAGIC: u32 = 0x2468_ACE0;
+const CIR_TREE_HEADER_SIZE: u64 = 48;
+const CHROM_TREE_HEADER_SIZE: u64 = 32;
+const BATCH_SIZE: usize = 8192;
+
+const CHROM_COLUMN: &str = "chrom";
+const START_COLUMN: &str = "start";
+const END_COLUMN: &str = "end";
+const ZOOM_VALUE_COLUMNS: [&str; 5] = ["min", "max", "mean", "sum", "sum_squares"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BbiKind {
+    BigWig,
+    BigBed,
+}
+
+#[derive(Clone, Debug)]
+struct ZoomHeader {
+    reduction_level: u32,
+    index_offset: u64,
+}
+
+#[derive(Clone, Debug)]
+struct BbiHeader {
+    kind: BbiKind,
+    chrom_tree_offset: u64,
+    full_index_offset: u64,
+    field_count: u16,
+    defined_field_count: u16,
+    auto_sql_offset: u64,
+    uncompress_buf_size: u32,
+    zoom_headers: Vec<ZoomHeader>,
+}
+
+/// What the records of the data blocks look like.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BbiRecordType {
+    Wig,
+    Bed,
+    Zoom,
+}
+
+/// A compressed data block referenced by a leaf of the R-tree index.
+#[derive(Clone, Copy, Debug)]
+struct Block {
+    offset: u64,
+    size: u64,
+}
+
+/// Region in the R-tree coordinates: chromosome id and a half-open base range.
+type Region = (u32, u32, u32);
+
+/// Minimal little-endian reader of the bigWig/bigBed (BBI) container: header,
+/// chromosome B+ tree, R-tree (CIR) index and zlib-compressed data blocks.
+struct BbiReader {
+    reader: BufReader<File>,
+    header: BbiHeader,
+}
+
+impl BbiReader {
+    fn open(path: &str) -> Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let header = read_header(&mut reader, path)?;
+        Ok(BbiReader { reader, header })
+    }
+
+    /// Chromosome names indexed by their id.
+    fn read_chroms(&mut self) -> Result<Vec<String>> {
+        self.reader
+            .seek(SeekFrom::Start(self.header.chrom_tree_offset))?;
+        if self.reader.read_u32::<LittleEndian>()? != CHROM_TREE_MAGIC {
+            return Err(invalid("chromosome tree"));
+        }
+        let _block_size = self.reader.read_u32::<LittleEndian>()?;
+        let key_size = self.reader.read_u32::<LittleEndian>()? as usize;
+        let _value_size = self.reader.read_u32::<LittleEndian>()?;
+        let item_count = self.reader.read_u64::<LittleEndian>()? as usize;
+        let mut chroms = Vec::with_capacity(item_count);
+        self.read_chrom_node(
+            self.header.chrom_tree_offset + CHROM_TREE_HEADER_SIZE,
+            key_size,
+            &mut chroms,
+        )?;
+        chroms.sort_by_key(|(id, _)| *id);
+        Ok(chroms.into_iter().map(|(_, name)| name).collect())
+    }
+
+    fn read_chrom_node(
+        &mut self,
+        offset: u64,
+        key_size: usize,
+        chroms: &mut Vec<(u32, String)>,
+    ) -> Result<()> {
+        self.reader.seek(SeekFrom::Start(offset))?;
+        let is_leaf = self.reader.read_u8()? == 1;
+        let _reserved = self.reader.read_u8()?;
+        let count = self.reader.read_u16::<LittleEndian>()?;
+        let mut key = vec![0u8; key_size];
+        let mut children = Vec::new();
+        for _ in 0..count {
+            self.reader.read_exact(&mut key)?;
+            if is_leaf {
+                let id = self.reader.read_u32::<LittleEndian>()?;
+                let _size = self.reader.read_u32::<LittleEndian>()?;
+                let name = key.split(|b| *b == 0).next().unwrap_or_default();
+                chroms.push((id, String::from_utf8_lossy(name).to_string()));
+            } else {
+                children.push(self.reader.read_u64::<LittleEndian>()?);
+            }
+        }
+        for child in children {
+            self.read_chrom_node(child, key_size, chroms)?;
+        }
+        Ok(())
+    }
+
+    /// Data blocks overlapping `region`, or all blocks if there is no region.
+    fn search_index(&mut self, index_offset: u64, region: Option<Region>) -> Result<Vec<Block>> {
+        self.reader.seek(SeekFrom::Start(index_offset))?;
+        if self.reader.read_u32::<LittleEndian>()? != CIR_TREE_MAGIC {
+            return Err(invalid("R-tree index"));
+        }
+        let mut blocks = Vec::new();
+        self.search_index_node(index_offset + CIR_TREE_HEADER_SIZE, region, &mut blocks)?;
+        Ok(blocks)
+    }
+
+    fn search_index_node(
+        &mut self,
+        offset: u64,
+        region: Option<Region>,
+        blocks: &mut Vec<Block>,
+    ) -> Result<()> {
+        self.reader.seek(SeekFrom::Start(offset))?;
+        let is_leaf = self.reader.read_u8()? == 1;
+        let _reserved = self.reader.read_u8()?;
+        let count = self.reader.read_u16::<LittleEndian>()?;
+        let mut children = Vec::new();
+        for _ in 0..count {
+            let start_chrom = self.reader.read_u32::<LittleEndian>()?;
+            let start_base = self.reader.read_u32::<LittleEndian>()?;
+            let end_chrom = self.reader.read_u32::<LittleEndian>()?;
+            let end_base = self.reader.read_u32::<LittleEndian>()?;
+            let data_offset = self.reader.read_u64::<LittleEndian>()?;
+            let overlaps = match region {
+                Some((chrom, start, end)) => {
+                    (chrom, start) < (end_chrom, end_base)
+                        && (start_chrom, start_base) < (chrom, end)
+                },
+                None => true,
+            };
+            if is_leaf {
+                let size = self.reader.read_u64::<LittleEndian>()?;
+                if overlaps {
+                    blocks.push(Block {
+                        offset: data_offset,
+                        size,
+                    });
+                }
+            } else if overlaps {
+                children.push(data_offset);
+            }
+        }
+        for child in children {
+            self.search_index_node(child, region, blocks)?;
+        }
+        Ok(())
+    }
+
+    fn read_block(&mut self, block: &Block) -> Result<Vec<u8>> {
+        self.reader.seek(SeekFrom::Start(block.offset))?;
+        let mut data = vec![0u8; block.size as usize];
+        self.reader.read_exact(&mut data)?;
+        if self.header.uncompress_buf_size == 0 {
+            return Ok(data);
+        }
+        let mut decompressed = Vec::with_capacity(self.header.uncompress_buf_size as usize);
+        ZlibDecoder::new(data.as_slice()).read_to_end(&mut decompressed)?;
+        Ok(decompressed)
+    }
+
+    fn read_auto_sql(&mut self) -> Result<Option<String>> {
+        if self.header.auto_sql_offset == 0 {
+            return Ok(None);
+        }
+        self.reader
+            .seek(SeekFrom::Start(self.header.auto_sql_offset))?;
+        let mut auto_sql = Vec::new();
+        loop {
+            match self.reader.read_u8()? {
+                0 => break,
+                b => auto_sql.push(b),
+            }
+        }
+        Ok(Some(String::from_utf8_lossy(&auto_sql).to_string()))
+    }
+}
+
+fn read_header<R: Read>(reader: &mut R, path: &str) -> Result<BbiHeader> {
+    let kind = match reader.read_u32::<LittleEndian>()? {
+        BIGWIG_MAGIC => BbiKind::BigWig,
+        BIGBED_MAGIC => BbiKind::BigBed,
+        _ => {
+            return Err(DataFusionError::Execution(format!(
+                "Not a little-endian bigWig/bigBed file: {}",
+                path
+            )))
+        },
+    };
+    let _version = reader.read_u16::<LittleEndian>()?;
+    let zoom_levels = reader.read_u16::<LittleEndian>()?;
+    let chrom_tree_offset = reader.read_u64::<LittleEndian>()?;
+    let _full_data_offset = reader.read_u64::<LittleEndian>()?;
+    let full_index_offset = reader.read_u64::<LittleEndian>()?;
+    let field_count = reader.read_u16::<LittleEndian>()?;
+    let defined_field_count = reader.read_u16::<LittleEndian>()?;
+    let auto_sql_offset = reader.read_u64::<LittleEndian>()?;
+    let _total_summary_offset = reader.read_u64::<LittleEndian>()?;
+    let uncompress_buf_size = reader.read_u32::<LittleEndian>()?;
+    let _extension_offset = reader.read_u64::<LittleEndian>()?;
+    let mut zoom_headers = Vec::with_capacity(zoom_levels as usize);
+    for _ in 0..zoom_levels {
+        let reduction_level = reader.read_u32::<LittleEndian>()?;
+        let _reserved = reader.read_u32::<LittleEndian>()?;
+        let _data_offset = reader.read_u64::<LittleEndian>()?;
+        let index_offset = reader.read_u64::<LittleEndian>()?;
+        zoom_headers.push(ZoomHeader {
+            reduction_level,
+            index_offset,
+        });
+    }
+    Ok(BbiHeader {
+        kind,
+        chrom_tree_offset,
+        full_index_offset,
+        field_count,
+        defined_field_count,
+        auto_sql_offset,
+        uncompress_buf_size,
+        zoom_headers,
+    })
+}
+
+fn invalid(structure: &str) -> DataFusionError {
+    DataFusionError::Execution(format!("Invalid bigWig/bigBed {}", structure))
+}
+
+/// Fields declared in an autoSql table definition, e.g. `uint chromStart; "..."`,
+/// with the Arrow type of their values.
+fn parse_auto_sql_fields(auto_sql: &str) -> Vec<(String, DataType)> {
+    auto_sql
+        .lines()
+        .filter(|line| line.contains(';'))
+        .filter_map(|line| {
+            let mut tokens = line.split(';').next()?.split_whitespace();
+            let auto_sql_type = tokens.next()?;
+            let name = tokens.last()?;
+            Some((name.to_string(), auto_sql_data_type(auto_sql_type)))
+        })
+        .collect()
+}
+
+/// Arrow type of an autoSql field type, arrays (e.g. `int[blockCount]`) and
+/// strings are kept as text.
+fn auto_sql_data_type(auto_sql_type: &str) -> DataType {
+    match auto_sql_type {
+        "byte" | "ubyte" | "short" | "ushort" | "int" | "uint" | "bigint" => DataType::Int64,
+        "float" | "double" => DataType::Float64,
+        _ => DataType::Utf8,
+    }
+}
+
+/// Table provider for local bigWig and bigBed files. Filters on `chrom`, `start`
+/// and `end` are pushed down to the R-tree index, so only the overlapping data
+/// blocks are decompressed. With `zoom_level`, precomputed summaries of the chosen
+/// zoom level are returned instead of the base-level records.
+pub struct BbiTableProvider {
+    path: String,
+    header: BbiHeader,
+    zoom_level: Option<usize>,
+    schema: SchemaRef,
+}
+
+impl BbiTableProvider {
+    pub fn new(path: String, zoom_level: Option<usize>) -> Result<Self> {
+        let mut reader = BbiReader::open(&path)?;
+        let header = reader.header.clone();
+        if let Some(level) = zoom_level {
+            if level >= header.zoom_headers.len() {
+                return Err(DataFusionError::Plan(format!(
+                    "Zoom level {} not available, {} has {} zoom levels",
+                    level,
+                    path,
+                    header.zoom_headers.len()
+                )));
+            }
+            debug!(
+                "Using zoom level {} ({} bases) of {}",
+                level, header.zoom_headers[level].reduction_level, path
+            );
+        }
+        let mut fields = vec![
+            Field::new(CHROM_COLUMN, DataType::Utf8, false),
+            Field::new(START_COLUMN, DataType::Int64, false),
+            Field::new(END_COLUMN, DataType::Int64, false),
+        ];
+        match (header.kind, zoom_level) {
+            (_, Some(_)) => {
+                fields.push(Field::new("valid_count", DataType::Int64, false));
+                for name in ZOOM_VALUE_COLUMNS {
+                    fields.push(Field::new(name, DataType::Float64, false));
+                }
+            },
+            (BbiKind::BigWig, None) => {
+                fields.push(Field::new("value", DataType::Float64, false));
+            },
+            (BbiKind::BigBed, None) => {
+                let auto_sql_fields = reader
+                    .read_auto_sql()?
+                    .map(|auto_sql| parse_auto_sql_fields(&auto_sql))
+                    .unwrap_or_default();
+                for i in 3..header.field_count as usize {
+                    // standard BED columns keep the bioframe naming used by `read_bed`
+                    let field = if i < header.defined_field_count as usize && i < BED_COLUMNS.len()
+                    {
+                        Field::new(BED_COLUMNS[i].0, BED_COLUMNS[i].1.clone(), true)
+                    } else {
+                        let (name, data_type) = auto_sql_fields
+                            .get(i)
+                            .cloned()
+                            .unwrap_or((format!("field_{}", i + 1), DataType::Utf8));
+                        Field::new(name, data_type, true)
+                    };
+                    fields.push(field);
+                }
+            },
+        }
+        Ok(BbiTableProvider {
+            path,
+            header,
+            zoom_level,
+            schema: Arc::new(Schema::new(fields)),
+        })
+    }
+
+    fn record_type(&self) -> BbiRecordType {
+        match (self.header.kind, self.zoom_level) {
+            (_, Some(_)) => BbiRecordType::Zoom,
+            (BbiKind::BigWig, None) => BbiRecordType::Wig,
+            (BbiKind::BigBed, None) => BbiRecordType::Bed,
+        }
+    }
+}
+
+impl Debug for BbiTableProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BbiTableProvider {{ path: {} }}", self.path)
+    }
+}
+
+#[async_trait]
+impl TableProvider for BbiTableProvider {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Base
+    }
+
+    fn supports_filters_pushdown(
+        &self,
+        filters: &[&Expr],
+    ) -> Result<Vec<TableProviderFilterPushDown>> {
+        Ok(filters
+            .iter()
+            .map(|f| match parse_region_filter(f) {
+                Some(_) => TableProviderFilterPushDown::Inexact,
+                None => TableProviderFilterPushDown::Unsupported,
+            })
+            .collect())
+    }
+
+    async fn scan(
+        &self,
+        _state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        _limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let mut reader = BbiReader::open(&self.path)?;
+        let chroms = reader.read_chroms()?;
+        let index_offset = match self.zoom_level {
+            Some(level) => self.header.zoom_headers[level].index_offset,
+            None => self.header.full_index_offset,
+        };
+        let blocks = match region_from_filters(filters) {
+            (Some(chrom), start, end) => match chroms.iter().position(|c| *c == chrom) {
+                Some(id) => {
+                    let region = (id as u32, start, end);
+                    debug!("Searching {} for region {:?}", self.path, region);
+                    reader.search_index(index_offset, Some(region))?
+                },
+                None => vec![],
+            },
+            (None, _, _) => reader.search_index(index_offset, None)?,
+        };
+        debug!("{} blocks to read from {}", blocks.len(), self.path);
+        let schema = match projection {
+            Some(p) => Arc::new(self.schema.project(p)?),
+            None => self.schema.clone(),
+        };
+        Ok(Arc::new(BbiExec {
+            path: self.path.clone(),
+            chroms: Arc::new(chroms),
+            blocks: Arc::new(blocks),
+            record_type: self.record_type(),
+            full_schema: self.schema.clone(),
+            projection: projection.cloned(),
+            cache: PlanProperties::new(
+                EquivalenceProperties::new(schema.clone()),
+                Partitioning::UnknownPartitioning(1),
+                ExecutionMode::Bounded,
+            ),
+            schema,
+        }))
+    }
+}
+
+/// Bound derived from a single pushed-down filter.
+#[derive(Clone, Debug, PartialEq)]
+enum RegionFilter {
+    Chrom(String),
+    /// records end after this position
+    MinEnd(u32),
+    /// records start before this position
+    MaxStart(u32),
+}
+
+fn parse_region_filter(expr: &Expr) -> Option<RegionFilter> {
+    let Expr::BinaryExpr(BinaryExpr { left, op, right }) = expr else {
+        return None;
+    };
+    let (column, op, value) = match (left.as_ref(), right.as_ref()) {
+        (Expr::Column(c), Expr::Literal(v)) => (c.name.as_str(), *op, v),
+        (Expr::Literal(v), Expr::Column(c)) => (c.name.as_str(), op.swap()?, v),
+        _ => return None,
+    };
+    match (column, op) {
+        (CHROM_COLUMN, Operator::Eq) => match value {
+            ScalarValue::Utf8(Some(v))
+            | ScalarValue::LargeUtf8(Some(v))
+            | ScalarValue::Utf8View(Some(v)) => Some(RegionFilter::Chrom(v.clone())),
+            _ => None,
+        },
+        (START_COLUMN | END_COLUMN, _) => {
+            let value = match value {
+                ScalarValue::Int64(Some(v)) => *v,
+                ScalarValue::Int32(Some(v)) => *v as i64,
+                ScalarValue::UInt64(Some(v)) => *v as i64,
+                ScalarValue::UInt32(Some(v)) => *v as i64,
+                _ => return None,
+            };
+            let position = |v: i64| v.clamp(0, u32::MAX as i64) as u32;
+            match (column, op) {
+                // start >= v implies end > v
+                (START_COLUMN, Operator::Gt | Operator::GtEq) => {
+                    Some(RegionFilter::MinEnd(position(value)))
+                },
+                (START_COLUMN, Operator::Lt) => Some(RegionFilter::MaxStart(position(value))),
+                (START_COLUMN, Operator::LtEq) => Some(RegionFilter::MaxStart(position(value + 1))),
+                (END_COLUMN, Operator::Gt) => Some(RegionFilter::MinEnd(position(value))),
+                (END_COLUMN, Operator::GtEq) => Some(RegionFilter::MinEnd(position(value - 1))),
+                // end <= v implies start < v
+                (END_COLUMN, Operator::Lt | Operator::LtEq) => {
+                    Some(RegionFilter::MaxStart(position(value)))
+                },
+                _ => None,
+            }
+        },
+        _ => None,
+    }
+}
+
+/// Combines pushed-down filters into a single chromosome and a half-open range.
+/// Coordinates are only usable together with a chromosome equality filter.
+fn region_from_filters(filters: &[Expr]) -> (Option<String>, u32, u32) {
+    let mut chroms = Vec::new();
+    let (mut start, mut end) = (0, u32::MAX);
+    for filter in filters.iter().filter_map(parse_region_filter) {
+        match filter {
+            RegionFilter::Chrom(chrom) => chroms.push(chrom),
+            RegionFilter::MinEnd(v) => start = start.max(v),
+            RegionFilter::MaxStart(v) => end = end.min(v),
+        }
+    }
+    chroms.dedup();
+    match chroms.len() {
+        1 => (chroms.pop(), start, end),
+        _ => (None, 0, u32::MAX),
+    }
+}
+
+struct BbiExec {
+    path: String,
+    chroms: Arc<Vec<String>>,
+    blocks: Arc<Vec<Block>>,
+    record_type: BbiRecordType,
+    full_schema: SchemaRef,
+    projection: Option<Vec<usize>>,
+    schema: SchemaRef,
+    cache: PlanProperties,
+}
+
+impl Debug for BbiExec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BbiExec {{ path: {} }}", self.path)
+    }
+}
+
+impl DisplayAs for BbiExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "BbiExec: path={}, blocks={}",
+            self.path,
+            self.blocks.len()
+        )
+    }
+}
+
+impl ExecutionPlan for BbiExec {
+    fn name(&self) -> &str {
+        "BbiExec"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.cache
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        _children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        Ok(self)
+    }
+
+    fn execute(
+        &self,
+        _partition: usize,
+        _context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        let batches = BbiBatchIterator {
+            reader: BbiReader::open(&self.path)?,
+            chroms: self.chroms.clone(),
+            blocks: self.blocks.iter().copied().collect(),
+            record_type: self.record_type,
+            schema: self.full_schema.clone(),
+            projection: self.projection.clone(),
+        };
+        Ok(Box::pin(RecordBatchStreamAdapter::new(
+            self.schema.clone(),
+            futures::stream::iter(batches),
+        )))
+    }
+}
+
+/// Decodes data blocks into record batches of about `BATCH_SIZE` rows.
+struct BbiBatchIterator {
+    reader: BbiReader,
+    chroms: Arc<Vec<String>>,
+    blocks: VecDeque<Block>,
+    record_type: BbiRecordType,
+    schema: SchemaRef,
+    projection: Option<Vec<usize>>,
+}
+
+impl BbiBatchIterator {
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
+        let mut builder = BbiBatchBuilder::new(self.record_type, &self.schema);
+        while builder.len < BATCH_SIZE {
+            let Some(block) = self.blocks.pop_front() else {
+                break;
+            };
+            let data = self.reader.read_block(&block)?;
+            builder.append_block(&data, &self.chroms)?;
+        }
+        if builder.len == 0 {
+            return Ok(None);
+        }
+        let batch = builder.finish(&self.schema)?;
+        Ok(Some(match &self.projection {
+            Some(projection) => batch.project(projection)?,
+            None => batch,
+        }))
+    }
+}
+
+impl Iterator for BbiBatchIterator {
+    type Item = Result<RecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_batch().transpose()
+    }
+}
+
+struct BbiBatchBuilder {
+    record_type: BbiRecordType,
+    chroms: StringBuilder,
+    starts: Int64Builder,
+    ends: Int64Builder,
+    counts: Int64Builder,
+    values: Vec<Float64Builder>,
+    fields: Vec<StringBuilder>,
+    len: usize,
+}
+
+impl BbiBatchBuilder {
+    fn new(record_type: BbiRecordType, schema: &SchemaRef) -> Self {
+        let (values, fields) = match record_type {
+            BbiRecordType::Wig => (1, 0),
+            BbiRecordType::Zoom => (ZOOM_VALUE_COLUMNS.len(), 0),
+            BbiRecordType::Bed => (0, schema.fields().len() - 3),
+        };
+        BbiBatchBuilder {
+            record_type,
+            chroms: StringBuilder::new(),
+            starts: Int64Builder::new(),
+            ends: Int64Builder::new(),
+            counts: Int64Builder::new(),
+            values: (0..values).map(|_| Float64Builder::new()).collect(),
+            fields: (0..fields).map(|_| StringBuilder::new()).collect(),
+            len: 0,
+        }
+    }
+
+    fn append_interval(
+        &mut self,
+        chroms: &[String],
+        chrom_id: u32,
+        start: u32,
+        end: u32,
+    ) -> Result<()> {
+        let chrom = chroms
+            .get(chrom_id as usize)
+            .ok_or_else(|| invalid("chromosome id"))?;
+        self.chroms.append_value(chrom);
+        self.starts.append_value(start as i64);
+        self.ends.append_value(end as i64);
+        self.len += 1;
+        Ok(())
+    }
+
+    fn append_block(&mut self, mut data: &[u8], chroms: &[String]) -> Result<()> {
+        match self.record_type {
+            BbiRecordType::Wig => {
+                let chrom_id = data.read_u32::<LittleEndian>()?;
+                let section_start = data.read_u32::<LittleEndian>()?;
+                let _section_end = data.read_u32::<LittleEndian>()?;
+                let step = data.read_u32::<LittleEndian>()?;
+                let span = data.read_u32::<LittleEndian>()?;
+                let section_type = data.read_u8()?;
+                let _reserved = data.read_u8()?;
+                let count = data.read_u16::<LittleEndian>()?;
+                for i in 0..count as u32 {
+                    let (start, end) = match section_type {
+                        // bedGraph
+                        1 => (
+                            data.read_u32::<LittleEndian>()?,
+                            data.read_u32::<LittleEndian>()?,
+                        ),
+                        // variableStep
+                        2 => {
+                            let start = data.read_u32::<LittleEndian>()?;
+                            (start, start + span)
+                        },
+                        // fixedStep
+                        3 => (section_start + i * step, section_start + i * step + span),
+                        _ => return Err(invalid("wig section type")),
+                    };
+                    let value = data.read_f32::<LittleEndian>()?;
+                    self.append_interval(chroms, chrom_id, start, end)?;
+                    self.values[0].append_value(value as f64);
+                }
+            },
+            BbiRecordType::Zoom => {
+                while !data.is_empty() {
+                    let chrom_id = data.read_u32::<LittleEndian>()?;
+                    let start = data.read_u32::<LittleEndian>()?;
+                    let end = data.read_u32::<LittleEndian>()?;
+                    let valid_count = data.read_u32::<LittleEndian>()?;
+                    let min = data.read_f32::<LittleEndian>()? as f64;
+                    let max = data.read_f32::<LittleEndian>()? as f64;
+                    let sum = data.read_f32::<LittleEndian>()? as f64;
+                    let sum_squares = data.read_f32::<LittleEndian>()? as f64;
+                    let mean = if valid_count > 0 {
+                        sum / valid_count as f64
+                    } else {
+                        0.0
+                    };
+                    self.append_interval(chroms, chrom_id, start, end)?;
+                    self.counts.append_value(valid_count as i64);
+                    for (builder, value) in
+                        self.values
+                            .iter_mut()
+                            .zip([min, max, mean, sum, sum_squares])
+                    {
+                        builder.append_value(value);
+                    }
+                }
+            },
+            BbiRecordType::Bed => {
+                while !data.is_empty() {
+                    let chrom_id = data.read_u32::<LittleEndian>()?;
+                    let start = data.read_u32::<LittleEndian>()?;
+                    let end = data.read_u32::<LittleEndian>()?;
+                    let length = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+                    let rest = String::from_utf8_lossy(&data[..length]);
+                    data = &data[(length + 1).min(data.len())..];
+                    self.append_interval(chroms, chrom_id, start, end)?;
+                    let mut values = (!rest.is_empty())
+                        .then(|| rest.split('\t'))
+                        .into_iter()
+                        .flatten();
+                    for builder in self.fields.iter_mut() {
+                        builder.append_option(values.next());
+                    }
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Text fields of bigBed records are cast to the types of the schema.
+    fn finish(mut self, schema: &SchemaRef) -> Result<RecordBatch> {
+        let mut columns: Vec<ArrayRef> = vec![
+            Arc::new(self.chroms.finish()),
+            Arc::new(self.starts.finish()),
+            Arc::new(self.ends.finish()),
+        ];
+        if self.record_type == BbiRecordType::Zoom {
+            columns.push(Arc::new(self.counts.finish()));
+        }
+        for builder in self.values.iter_mut() {
+            columns.push(Arc::new(builder.finish()));
+        }
+        for (i, builder) in self.fields.iter_mut().enumerate() {
+            let column = builder.finish();
+            columns.push(cast(&column, schema.field(i + 3).data_type())?);
+        }
+        Ok(RecordBatch::try_new(schema.clone(), columns)?)
+    }
+}
+
+const BBI_VERSION: u16 = 4;
+const BBI_HEADER_SIZE: usize = 64;
+const ZOOM_HEADER_SIZE: usize = 24;
+const TREE_BLOCK_SIZE: usize = 256;
+const ITEMS_PER_SLOT: usize = 1024;
+const MAX_ZOOM_LEVELS: usize = 10;
+const ZOOM_INCREMENT: u32 = 4;
+const BEDGRAPH_SECTION_TYPE: u8 = 1;
+
+/// An interval of constant signal, written as a bedGraph item of a bigWig section.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Run {
+    pub chrom_id: u32,
+    pub start: u32,
+    pub end: u32,
+    pub value: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Summary {
+    valid_count: u64,
+    min: f64,
+    max: f64,
+    sum: f64,
+    sum_squares: f64,
+}
+
+impl Summary {
+    fn new() -> Self {
+        Summary {
+            valid_count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            sum: 0.0,
+            sum_squares: 0.0,
+        }
+    }
+
+    fn add(&mut self, value: f64, bases: u32) {
+        self.valid_count += bases as u64;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value * bases as f64;
+        self.sum_squares += value * value * bases as f64;
+    }
+}
+
+struct ZoomRecord {
+    chrom_id: u32,
+    start: u32,
+    end: u32,
+    summary: Summary,
+}
+
+/// Leaf item of the R-tree index: the genomic span and location of a data block.
+#[derive(Clone, Copy, Debug)]
+struct IndexItem {
+    start: (u32, u32),
+    end: (u32, u32),
+    offset: u64,
+    size: u64,
+}
+
+/// Writes `runs` sorted by chromosome id and start as a bigWig file. Chromosome ids
+/// are positions in `chroms`, which has to be sorted by name. Zoom levels are built
+/// with reductions growing by a factor of 4 as long as they shrink the data.
+pub(crate) fn write_bigwig(path: &str, chroms: &[(String, u32)], runs: &[Run]) -> Result<()> {
+    let zoom_levels = zoom_levels(runs, chroms);
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(&vec![
+        0u8;
+        BBI_HEADER_SIZE + ZOOM_HEADER_SIZE * zoom_levels.len()
+    ])?;
+
+    let total_summary_offset = writer.stream_position()?;
+    let mut total_summary = Summary::new();
+    for run in runs {
+        total_summary.add(run.value as f64, run.end - run.start);
+    }
+    write_summary(&mut writer, &total_summary)?;
+
+    let chrom_tree_offset = writer.stream_position()?;
+    write_chrom_tree(&mut writer, chroms)?;
+
+    let full_data_offset = writer.stream_position()?;
+    let sections = runs
+        .chunk_by(|a, b| a.chrom_id == b.chrom_id)
+        .flat_map(|runs| runs.chunks(ITEMS_PER_SLOT))
+        .collect::<Vec<&[Run]>>();
+    writer.write_u32::<LittleEndian>(sections.len() as u32)?;
+    let mut max_block_size = 0;
+    let mut items = Vec::with_capacity(sections.len());
+    for section in sections {
+        let (first, last) = (section[0], section[section.len() - 1]);
+        let mut data = Vec::with_capacity(24 + 12 * section.len());
+        data.write_u32::<LittleEndian>(first.chrom_id)?;
+        data.write_u32::<LittleEndian>(first.start)?;
+        data.write_u32::<LittleEndian>(last.end)?;
+        data.write_u32::<LittleEndian>(0)?;
+        data.write_u32::<LittleEndian>(0)?;
+        data.write_u8(BEDGRAPH_SECTION_TYPE)?;
+        data.write_u8(0)?;
+        data.write_u16::<LittleEndian>(section.len() as u16)?;
+        for run in section {
+            data.write_u32::<LittleEndian>(run.start)?;
+            data.write_u32::<LittleEndian>(run.end)?;
+            data.write_f32::<LittleEndian>(run.value)?;
+        }
+        max_block_size = max_block_size.max(data.len());
+        let span = ((first.chrom_id, first.start), (last.chrom_id, last.end));
+        items.push(write_block(&mut writer, &data, span)?);
+    }
+    let full_index_offset = writer.stream_position()?;
+    write_cir_tree(&mut writer, &items)?;
+
+    let mut zoom_headers = Vec::with_capacity(zoom_levels.len());
+    for (reduction, records) in zoom_levels {
+        let data_offset = writer.stream_position()?;
+        writer.write_u32::<LittleEndian>(records.len() as u32)?;
+        let mut items = Vec::new();
+        let blocks = records
+            .chunk_by(|a, b| a.chrom_id == b.chrom_id)
+            .flat_map(|records| records.chunks(ITEMS_PER_SLOT));
+        for block in blocks {
+            let mut data = Vec::with_capacity(32 * block.len());
+            for record in block {
+                data.write_u32::<LittleEndian>(record.chrom_id)?;
+                data.write_u32::<LittleEndian>(record.start)?;
+                data.write_u32::<LittleEndian>(record.end)?;
+                data.write_u32::<LittleEndian>(record.summary.valid_count as u32)?;
+                data.write_f32::<LittleEndian>(record.summary.min as f32)?;
+                data.write_f32::<LittleEndian>(record.summary.max as f32)?;
+                data.write_f32::<LittleEndian>(record.summary.sum as f32)?;
+                data.write_f32::<LittleEndian>(record.summary.sum_squares as f32)?;
+            }
+            max_block_size = max_block_size.max(data.len());
+            let (first, last) = (&block[0], &block[block.len() - 1]);
+            let span = ((first.chrom_id, first.start), (last.chrom_id, last.end));
+            items.push(write_block(&mut writer, &data, span)?);
+        }
+        let index_offset = writer.stream_position()?;
+        write_cir_tree(&mut writer, &items)?;
+        zoom_headers.push((reduction, data_offset, index_offset));
+    }
+    // readers use the trailing magic to detect truncated files
+    writer.write_u32::<LittleEndian>(BIGWIG_MAGIC)?;
+
+    writer.seek(SeekFrom::Start(0))?;
+    writer.write_u32::<LittleEndian>(BIGWIG_MAGIC)?;
+    writer.write_u16::<LittleEndian>(BBI_VERSION)?;
+    writer.write_u16::<LittleEndian>(zoom_headers.len() as u16)?;
+    writer.write_u64::<LittleEndian>(chrom_tree_offset)?;
+    writer.write_u64::<LittleEndian>(full_data_offset)?;
+    writer.write_u64::<LittleEndian>(full_index_offset)?;
+    writer.write_u16::<LittleEndian>(0)?;
+    writer.write_u16::<LittleEndian>(0)?;
+    writer.write_u64::<LittleEndian>(0)?;
+    writer.write_u64::<LittleEndian>(total_summary_offset)?;
+    writer.write_u32::<LittleEndian>(max_block_size as u32)?;
+    writer.write_u64::<LittleEndian>(0)?;
+    for (reduction, data_offset, index_offset) in zoom_headers {
+        writer.write_u32::<LittleEndian>(reduction)?;
+        writer.write_u32::<LittleEndian>(0)?;
+        writer.write_u64::<LittleEndian>(data_offset)?;
+        writer.write_u64::<LittleEndian>(index_offset)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_summary<W: Write>(writer: &mut W, summary: &Summary) -> Result<()> {
+    let empty = summary.valid_count == 0;
+    writer.write_u64::<LittleEndian>(summary.valid_count)?;
+    writer.write_f64::<LittleEndian>(if empty { 0.0 } else { summary.min })?;
+    writer.write_f64::<LittleEndian>(if empty { 0.0 } else { summary.max })?;
+    writer.write_f64::<LittleEndian>(summary.sum)?;
+    writer.write_f64::<LittleEndian>(summary.sum_squares)?;
+    Ok(())
+}
+
+fn write_block<W: Write + Seek>(
+    writer: &mut W,
+    data: &[u8],
+    span: ((u32, u32), (u32, u32)),
+) -> Result<IndexItem> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    let compressed = encoder.finish()?;
+    let offset = writer.stream_position()?;
+    writer.write_all(&compressed)?;
+    Ok(IndexItem {
+        start: span.0,
+        end: span.1,
+        offset,
+        size: compressed.len() as u64,
+    })
+}
+
+/// Summaries over fixed-size bins for growing reductions, stopping when a level
+/// no longer reduces the number of records.
+fn zoom_levels(runs: &[Run], chroms: &[(String, u32)]) -> Vec<(u32, Vec<ZoomRecord>)> {
+    let mut levels = Vec::new();
+    if runs.is_empty() {
+        return levels;
+    }
+    let bases: u64 = runs.iter().map(|r| (r.end - r.start) as u64).sum();
+    let average_span = (bases / runs.len() as u64).max(1);
+    let mut reduction = (average_span * ZOOM_INCREMENT as u64).min(u32::MAX as u64) as u32;
+    let mut previous = runs.len();
+    while levels.len() < MAX_ZOOM_LEVELS {
+        let records = zoom_records(runs, chroms, reduction);
+        if records.len() >= previous {
+            break;
+        }
+        previous = records.len();
+        levels.push((reduction, records));
+        match reduction.checked_mul(ZOOM_INCREMENT) {
+            Some(next) => reduction = next,
+            None => break,
+        }
+    }
+    levels
+}
+
+fn zoom_records(runs: &[Run], chroms: &[(String, u32)], reduction: u32) -> Vec<ZoomRecord> {
+    let mut records: Vec<ZoomRecord> = Vec::new();
+    for run in runs {
+        let chrom_size = chroms[run.chrom_id as usize].1;
+        let mut start = run.start;
+        while start < run.end {
+            let bin_start = start / reduction * reduction;
+            let bin_end = bin_start.saturating_add(reduction).min(chrom_size);
+            let end = run.end.min(bin_end);
+            match records.last_mut() {
+                Some(record) if record.chrom_id == run.chrom_id && record.start == bin_start => {
+                    record.summary.add(run.value as f64, end - start)
+                },
+                _ => {
+                    let mut summary = Summary::new();
+                    summary.add(run.value as f64, end - start);
+                    records.push(ZoomRecord {
+                        chrom_id: run.chrom_id,
+                        start: bin_start,
+                        end: bin_end,
+                        summary,
+                    });
+                },
+            }
+            start = end;
+        }
+    }
+    records
+}
+
+/// Number of items on each level of a tree with `count` leaf items, from the leaves
+/// up to the single root node.
+fn tree_level_sizes(count: usize, block_size: usize) -> Vec<usize> {
+    let mut sizes = vec![count];
+    while sizes[sizes.len() - 1] > block_size {
+        sizes.push(sizes[sizes.len() - 1].div_ceil(block_size));
+    }
+    sizes
+}
+
+/// Writes the nodes of a tree level by level, starting from the root. An item `i`
+/// of a non-leaf level points to node `i` of the level below, so `write_item` gets
+/// the level, the item index and the child node offset (`None` for leaves).
+fn write_tree_nodes<W, F>(
+    writer: &mut W,
+    count: usize,
+    block_size: usize,
+    item_size: [u64; 2],
+    mut write_item: F,
+) -> Result<()>
+where
+    W: Write + Seek,
+    F: FnMut(&mut W, usize, usize, Option<u64>) -> Result<()>,
+{
+    let sizes = tree_level_sizes(count, block_size);
+    let top = sizes.len() - 1;
+    let item_size = |level: usize| item_size[(level > 0) as usize];
+    let node_size = |level: usize| 4 + block_size as u64 * item_size(level);
+    let mut level_offsets = vec![0; sizes.len()];
+    let mut offset = writer.stream_position()?;
+    for level in (0..=top).rev() {
+        level_offsets[level] = offset;
+        let nodes = if level == top { 1 } else { sizes[level + 1] };
+        offset += 4 * nodes as u64 + sizes[level] as u64 * item_size(level);
+    }
+    for level in (0..=top).rev() {
+        let indices = (0..sizes[level]).collect::<Vec<usize>>();
+        let mut nodes = indices.chunks(block_size).collect::<Vec<&[usize]>>();
+        if nodes.is_empty() {
+            nodes.push(&[]);
+        }
+        for node in nodes {
+            writer.write_u8((level == 0) as u8)?;
+            writer.write_u8(0)?;
+            writer.write_u16::<LittleEndian>(node.len() as u16)?;
+            for &i in node {
+                let child =
+                    (level > 0).then(|| level_offsets[level - 1] + i as u64 * node_size(level - 1));
+                write_item(writer, level, i, child)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_chrom_tree<W: Write + Seek>(writer: &mut W, chroms: &[(String, u32)]) -> Result<()> {
+    let key_size = chroms
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(1)
+        .max(1);
+    let block_size = chroms.len().clamp(1, TREE_BLOCK_SIZE);
+    writer.write_u32::<LittleEndian>(CHROM_TREE_MAGIC)?;
+    writer.write_u32::<LittleEndian>(block_size as u32)?;
+    writer.write_u32::<LittleEndian>(key_size as u32)?;
+    writer.write_u32::<LittleEndian>(8)?;
+    writer.write_u64::<LittleEndian>(chroms.len() as u64)?;
+    writer.write_u64::<LittleEndian>(0)?;
+    let item_size = (key_size + 8) as u64;
+    write_tree_nodes(
+        writer,
+        chroms.len(),
+        block_size,
+        [item_size, item_size],
+        |writer, level, i, child| {
+            // non-leaf items are keyed by the first chromosome of their subtree
+            let id = i * block_size.pow(level as u32);
+            let mut key = chroms[id].0.as_bytes().to_vec();
+            key.resize(key_size, 0);
+            writer.write_all(&key)?;
+            match child {
+                Some(offset) => writer.write_u64::<LittleEndian>(offset)?,
+                None => {
+                    writer.write_u32::<LittleEndian>(id as u32)?;
+                    writer.write_u32::<LittleEndian>(chroms[id].1)?;
+                },
+            }
+            Ok(())
+        },
+    )
+}
+
+fn write_cir_tree<W: Write + Seek>(writer: &mut W, items: &[IndexItem]) -> Result<()> {
+    let end_file_offset = writer.stream_position()?;
+    let start = items.iter().map(|item| item.start).min().unwrap_or((0, 0));
+    let end = items.iter().map(|item| item.end).max().unwrap_or((0, 0));
+    writer.write_u32::<LittleEndian>(CIR_TREE_MAGIC)?;
+    writer.write_u32::<LittleEndian>(TREE_BLOCK_SIZE as u32)?;
+    writer.write_u64::<LittleEndian>(items.len() as u64)?;
+    writer.write_u32::<LittleEndian>(start.0)?;
+    writer.write_u32::<LittleEndian>(start.1)?;
+    writer.write_u32::<LittleEndian>(end.0)?;
+    writer.write_u32::<LittleEndian>(end.1)?;
+    writer.write_u64::<LittleEndian>(end_file_offset)?;
+    writer.write_u32::<LittleEndian>(ITEMS_PER_SLOT as u32)?;
+    writer.write_u32::<LittleEndian>(0)?;
+    write_tree_nodes(
+        writer,
+        items.len(),
+        TREE_BLOCK_SIZE,
+        [32, 24],
+        |writer, level, i, child| {
+            // non-leaf items span all leaf items of their subtree
+            let leaves = TREE_BLOCK_SIZE.pow(level as u32);
+            let subtree = &items[i * leaves..((i + 1) * leaves).min(items.len())];
+            let start = subtree.iter().map(|item| item.start).min().unwrap();
+            let end = subtree.iter().map(|item| item.end).max().unwrap();
+            writer.write_u32::<LittleEndian>(start.0)?;
+            writer.write_u32::<LittleEndian>(start.1)?;
+            writer.write_u32::<LittleEndian>(end.0)?;
+            writer.write_u32::<LittleEndian>(end.1)?;
+            match child {
+                Some(offset) => writer.write_u64::<LittleEndian>(offset)?,
+                None => {
+                    writer.write_u64::<LittleEndian>(items[i].offset)?;
+                    writer.write_u64::<LittleEndian>(items[i].size)?;
+                },
+            }
+            Ok(())
+        },
+    )
+}
@@ -0,0 +1,201 @@
+use std::io::{BufRead, BufReader};
+
+use arrow_schema::{DataType, Field, Schema};
+use datafusion::prelude::CsvReadOptions;
+use exon::ExonSession;
+use log::debug;
+use pyo3::exceptions::PyValueError;
+use pyo3::PyResult;
+
+use crate::option::{BedReadOptions, InputFormat};
+use crate::scan::{get_compression_type, get_file_extension, open_decompressed};
+
+pub(crate) const BED_COLUMNS: [(&str, DataType); 12] = [
+    ("chrom", DataType::Utf8),
+    ("start", DataType::Int64),
+    ("end", DataType::Int64),
+    ("name", DataType::Utf8),
+    ("score", DataType::Int64),
+    ("strand", DataType::Utf8),
+    ("thickStart", DataType::Int64),
+    ("thickEnd", DataType::Int64),
+    ("itemRgb", DataType::Utf8),
+    ("blockCount", DataType::Int64),
+    ("blockSizes", DataType::Utf8),
+    ("blockStarts", DataType::Utf8),
+];
+// ENCODE peak formats share the first 6 BED columns, naming follows bioframe schemas
+const PEAK_COLUMNS: [(&str, DataType); 4] = [
+    ("fc", DataType::Float64),
+    ("-log10p", DataType::Float64),
+    ("-log10q", DataType::Float64),
+    ("relSummit", DataType::Int64),
+];
+const BEDGRAPH_VALUE_COLUMN: (&str, DataType) = ("value", DataType::Float64);
+const DEFAULT_BED_COLUMNS: usize = 6;
+
+/// BED flavour resolved from the input format and `BedReadOptions::bed_type`.
+#[derive(Clone, Debug, PartialEq)]
+enum BedType {
+    Bed(usize),
+    NarrowPeak,
+    BroadPeak,
+    BedGraph,
+}
+
+impl BedType {
+    fn parse(bed_type: &str) -> Option<Self> {
+        match bed_type.to_lowercase().as_str() {
+            "narrowpeak" => Some(BedType::NarrowPeak),
+            "broadpeak" => Some(BedType::BroadPeak),
+            "bedgraph" => Some(BedType::BedGraph),
+            t => match t.strip_prefix("bed")?.parse::<usize>() {
+                Ok(n) if (3..=12).contains(&n) => Some(BedType::Bed(n)),
+                _ => None,
+            },
+        }
+    }
+
+    fn schema(&self) -> Schema {
+        let columns: Vec<(&str, DataType)> = match self {
+            BedType::Bed(n) => BED_COLUMNS[..*n].to_vec(),
+            BedType::NarrowPeak => [&BED_COLUMNS[..6], &PEAK_COLUMNS[..]].concat(),
+            BedType::BroadPeak => [&BED_COLUMNS[..6], &PEAK_COLUMNS[..3]].concat(),
+            BedType::BedGraph => [&BED_COLUMNS[..3], &[BEDGRAPH_VALUE_COLUMN][..]].concat(),
+        };
+        Schema::new(
+            columns
+                .into_iter()
+                .map(|(name, data_type)| Field::new(name, data_type, true))
+                .collect::<Vec<Field>>(),
+        )
+    }
+}
+
+/// Registers a BED-like file with a typed schema. For BED12, `blockSizes` and
+/// `blockStarts` are parsed as lists, or exploded into one row per block.
+pub(crate) async fn register_bed_table(
+    ctx: &ExonSession,
+    path: &str,
+    table_name: &str,
+    format: &InputFormat,
+    bed_read_options: BedReadOptions,
+) -> PyResult<()> {
+    let bed_type = match (format, &bed_read_options.bed_type) {
+        (InputFormat::NarrowPeak, _) => BedType::NarrowPeak,
+        (InputFormat::BroadPeak, _) => BedType::BroadPeak,
+        (InputFormat::BedGraph, _) => BedType::BedGraph,
+        (_, Some(bed_type)) => BedType::parse(bed_type).ok_or_else(|| {
+            PyValueError::new_err(format!(
+                "Unsupported BED type: {}, expected bed3...bed12, narrowPeak, broadPeak or bedGraph",
+                bed_type
+            ))
+        })?,
+        (_, None) => BedType::Bed(infer_bed_columns(path)),
+    };
+    debug!("Registering {} as {:?}", path, bed_type);
+    let schema = bed_type.schema();
+    // a BED type narrower than the file reads its first columns
+    let raw_schema = match (&bed_type, first_data_line(path)) {
+        (BedType::Bed(n), Some(line)) => {
+            BedType::Bed(line.split('\t').count().clamp(*n, 12)).schema()
+        },
+        _ => schema.clone(),
+    };
+    let file_extension = get_file_extension(path);
+    let options = CsvReadOptions::new()
+        .delimiter(b'\t')
+        .has_header(false)
+        .comment(b'#')
+        .schema(&raw_schema)
+        .file_extension(&file_extension)
+        .file_compression_type(get_compression_type(path));
+    let raw_table = format!("{}_raw", table_name);
+    ctx.session.deregister_table(&raw_table)?;
+    ctx.session.register_csv(&raw_table, path, options).await?;
+    let query = match bed_type {
+        BedType::Bed(12) if bed_read_options.explode_blocks => exploded_blocks_query(&raw_table),
+        _ => columns_query(&schema, &raw_table),
+    };
+    debug!("Query: {}", query);
+    let df = ctx.session.sql(&query).await?;
+    ctx.session.register_table(table_name, df.into_view())?;
+    ctx.session.deregister_table(&raw_table)?;
+    Ok(())
+}
+
+fn parse_list(column: &str) -> String {
+    format!(
+        "CAST(string_to_array(btrim(\"{}\", ','), ',') AS BIGINT[])",
+        column
+    )
+}
+
+/// Selects the `schema` columns, parsing BED12 block lists.
+fn columns_query(schema: &Schema, raw_table: &str) -> String {
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|f| match f.name().as_str() {
+            "blockSizes" | "blockStarts" => format!("{} AS \"{}\"", parse_list(f.name()), f.name()),
+            name => format!("\"{}\"", name),
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+    format!("SELECT {} FROM {}", columns, raw_table)
+}
+
+/// One row per BED12 block with absolute block coordinates, usable directly in range operations.
+fn exploded_blocks_query(raw_table: &str) -> String {
+    format!(
+        r#"
+        SELECT
+            chrom,
+            start + block_start AS start,
+            start + block_start + block_size AS "end",
+            name,
+            score,
+            strand
+        FROM (
+            SELECT
+                chrom,
+                start,
+                name,
+                score,
+                strand,
+                unnest({}) AS block_size,
+                unnest({}) AS block_start
+            FROM {}
+        )
+        "#,
+        parse_list("blockSizes"),
+        parse_list("blockStarts"),
+        raw_table
+    )
+}
+
+/// Counts columns of the first data line of a local file, defaults to BED6 otherwise.
+fn infer_bed_columns(path: &str) -> usize {
+    first_data_line(path)
+        .map(|line| line.split('\t').count().clamp(3, 12))
+        .unwrap_or(DEFAULT_BED_COLUMNS)
+}
+
+/// Opens a local, optionally compressed, text file for line-based sniffing.
+pub(crate) fn open_text_file(path: &str) -> Option<Box<dyn BufRead>> {
+    let reader = open_decompressed(path, 1).ok()?;
+    Some(Box::new(BufReader::new(reader)))
+}
+
+/// First line that is not a comment or a UCSC `track`/`browser` line.
+pub(crate) fn first_data_line(path: &str) -> Option<String> {
+    open_text_file(path)?
+        .lines()
+        .map_while(Result::ok)
+        .find(|line| {
+            !(line.is_empty()
+                || line.starts_with('#')
+                || line.starts_with("track")
+                || line.starts_with("browser"))
+        })
+}
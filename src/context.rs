@@ -1,6 +1,8 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
-use datafusion::config::ConfigOptions;
+use datafusion::common::extensions_options;
+use datafusion::config::{ConfigExtension, ConfigOptions};
 use datafusion::prelude::SessionConfig;
 use exon::config::ExonConfigExtension;
 use exon::ExonSession;
@@ -8,9 +10,41 @@ use log::debug;
 use pyo3::{pyclass, pymethods, PyResult};
 use sequila_core::session_context::SequilaConfig;
 
-use crate::kmers_udaf::create_kmer_count_udaf;
+use crate::faidx::create_getfasta_udf;
+use crate::kmers_sketch::{
+    create_fracminhash_sketch_udaf, create_minhash_sketch_udaf, create_minimizers_udf,
+    create_sketch_distance_udf, create_syncmers_udf,
+};
+use crate::kmers_udaf::{create_kmer_count_udaf, create_kmer_skipped_windows_udaf};
+use crate::kmers_udtf::KmerCountsFunction;
+use crate::quality::create_quality_udfs;
+use crate::sequence::create_sequence_udfs;
+use crate::storage::register_object_store;
+use crate::trim::create_trim_udfs;
 
 
+/// Name of the staging directory, in the session catalog or, if `bio.staging_dir` is
+/// set to an empty string, in the system temporary directory.
+pub(crate) const STAGING_DIR: &str = "staging";
+
+extensions_options! {
+    /// polars-bio options, set with the `bio.` prefix, e.g. `bio.bgzf_threads`.
+    pub struct BioConfig {
+        /// Number of threads used to decompress BGZF text inputs
+        pub bgzf_threads: usize, default = 1
+        /// Whether compressed local inputs a reader can't decompress itself are
+        /// decompressed into `staging_dir`
+        pub stage_inputs: bool, default = false
+        /// Directory for decompressed copies of inputs, by default a directory of the
+        /// session catalog that is removed with the session
+        pub staging_dir: String, default = String::new()
+    }
+}
+
+impl ConfigExtension for BioConfig {
+    const PREFIX: &'static str = "bio";
+}
+
 #[pyclass(name = "BioSessionContext")]
 // #[derive(Clone)]
 pub struct PyBioSessionContext {
@@ -28,6 +62,31 @@ impl PyBioSessionContext {
     pub fn new(seed: String, catalog_dir: String) -> PyResult<Self> {
         let ctx = create_context().unwrap();
         ctx.session.register_udaf(create_kmer_count_udaf());        
+        ctx.session.register_udaf(create_kmer_skipped_windows_udaf());
+        ctx.session.register_udf(create_minimizers_udf());
+        ctx.session.register_udf(create_syncmers_udf());
+        ctx.session.register_udaf(create_minhash_sketch_udaf());
+        ctx.session.register_udaf(create_fracminhash_sketch_udaf());
+        ctx.session.register_udf(create_sketch_distance_udf());
+        ctx.session.register_udf(create_getfasta_udf());
+        for udf in create_quality_udfs() {
+            ctx.session.register_udf(udf);
+        }
+        for udf in create_trim_udfs() {
+            ctx.session.register_udf(udf);
+        }
+        for udf in create_sequence_udfs() {
+            ctx.session.register_udf(udf);
+        }
+        ctx.session.register_udtf(
+            "kmer_counts",
+            Arc::new(KmerCountsFunction::new(Arc::new(ctx.session.clone()))),
+        );
+        set_option_internal(
+            &ctx,
+            "bio.staging_dir",
+            &format!("{}/{}", catalog_dir, STAGING_DIR),
+        );
         let session_config: HashMap<String, String> = HashMap::new();
 
         Ok(PyBioSessionContext {
@@ -46,6 +105,17 @@ impl PyBioSessionContext {
         set_option_internal(&self.ctx, key, value);
     }
 
+    /// Registers an object store for an `s3://`, `gs://` or `http(s)://` bucket/host,
+    /// see `storage::build_object_store` for the supported options.
+    #[pyo3(signature = (url, options=None))]
+    pub fn register_object_store(
+        &mut self,
+        url: &str,
+        options: Option<HashMap<String, String>>,
+    ) -> PyResult<()> {
+        register_object_store(&self.ctx, url, &options.unwrap_or_default())
+    }
+
     #[pyo3(signature = (key))]
     pub fn get_option(&self, key: &str) -> Option<&str> {
         self.session_config.get(key).map(|v| v.as_str())
@@ -87,6 +157,7 @@ fn create_context() -> exon::Result<ExonSession> {
 
     let config = SessionConfig::from(options)
         .with_option_extension(sequila_config)
+        .with_option_extension(BioConfig::default())
         .with_information_schema(true);
 
     ExonSession::with_config_exon(config)
@@ -0,0 +1,278 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use arrow::array::{Array, ArrayRef, AsArray, StringBuilder};
+use arrow::datatypes::Int64Type;
+use arrow_schema::DataType;
+use datafusion::common::{exec_err, DataFusionError, Result, ScalarValue};
+use datafusion::logical_expr::{
+    ColumnarValue, ScalarUDF, ScalarUDFImpl, Signature, TypeSignature, Volatility,
+};
+use log::debug;
+
+use crate::sequence::reverse_complement;
+
+const FAI_EXTENSION: &str = ".fai";
+
+/// A `.fai` index entry, see `samtools faidx`.
+#[derive(Clone, Debug)]
+struct FaiRecord {
+    length: u64,
+    offset: u64,
+    line_bases: u64,
+    line_width: u64,
+}
+
+impl FaiRecord {
+    fn byte_offset(&self, pos: u64) -> u64 {
+        self.offset + pos / self.line_bases * self.line_width + pos % self.line_bases
+    }
+}
+
+/// Uncompressed FASTA file with random access to subsequences. The `.fai` index is
+/// read from `{path}.fai` when present, otherwise it is built in memory.
+#[derive(Debug)]
+pub(crate) struct IndexedFasta {
+    index: HashMap<String, FaiRecord>,
+    file: Mutex<File>,
+}
+
+impl IndexedFasta {
+    pub(crate) fn open(path: &str) -> Result<Self> {
+        let lower = path.to_lowercase();
+        if lower.ends_with(".gz") || lower.ends_with(".bgz") {
+            return exec_err!(
+                "Compressed FASTA is not supported for random access, decompress {} first",
+                path
+            );
+        }
+        let fai_path = format!("{}{}", path, FAI_EXTENSION);
+        let index = if Path::new(&fai_path).is_file() {
+            read_fai(&fai_path)?
+        } else {
+            debug!("No index found for {}, building it in memory", path);
+            build_fai(path)?
+        };
+        Ok(Self {
+            index,
+            file: Mutex::new(File::open(path)?),
+        })
+    }
+
+    /// Sequence of the 0-based, half-open `[start, end)` interval, clipped to the
+    /// contig length. Returns `None` for an unknown contig.
+    pub(crate) fn fetch(&self, chrom: &str, start: i64, end: i64) -> Result<Option<Vec<u8>>> {
+        let record = match self.index.get(chrom) {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        let start = start.clamp(0, record.length as i64) as u64;
+        let end = end.clamp(0, record.length as i64) as u64;
+        if start >= end {
+            return Ok(Some(Vec::new()));
+        }
+        let from = record.byte_offset(start);
+        let to = record.byte_offset(end - 1) + 1;
+        let mut buffer = vec![0u8; (to - from) as usize];
+        let mut file = self.file.lock().unwrap();
+        file.seek(SeekFrom::Start(from))?;
+        file.read_exact(&mut buffer)?;
+        buffer.retain(|b| *b != b'\n' && *b != b'\r');
+        Ok(Some(buffer))
+    }
+}
+
+fn read_fai(path: &str) -> Result<HashMap<String, FaiRecord>> {
+    let mut index = HashMap::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        let fields = line.split('\t').collect::<Vec<&str>>();
+        if fields.len() < 5 {
+            return exec_err!("Invalid FASTA index line in {}: {}", path, line);
+        }
+        let parse = |i: usize| {
+            fields[i].parse::<u64>().map_err(|e| {
+                DataFusionError::Execution(format!("Invalid FASTA index {}: {}", path, e))
+            })
+        };
+        index.insert(
+            fields[0].to_string(),
+            FaiRecord {
+                length: parse(1)?,
+                offset: parse(2)?,
+                line_bases: parse(3)?,
+                line_width: parse(4)?,
+            },
+        );
+    }
+    Ok(index)
+}
+
+/// Builds the index with a single pass over the file. Like `samtools faidx`, it
+/// requires all lines of a record but the last to have the same length.
+fn build_fai(path: &str) -> Result<HashMap<String, FaiRecord>> {
+    let mut index = HashMap::new();
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut line = Vec::new();
+    let mut offset = 0u64;
+    let mut current: Option<(String, FaiRecord)> = None;
+    let mut last_line_short = false;
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)? as u64;
+        if read == 0 {
+            break;
+        }
+        offset += read;
+        if line.starts_with(b">") {
+            if let Some((name, record)) = current.take() {
+                index.insert(name, record);
+            }
+            let name = String::from_utf8_lossy(&line[1..])
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            current = Some((
+                name,
+                FaiRecord {
+                    length: 0,
+                    offset,
+                    line_bases: 0,
+                    line_width: 0,
+                },
+            ));
+            last_line_short = false;
+            continue;
+        }
+        let (name, record) = match current.as_mut() {
+            Some(current) => current,
+            None => continue,
+        };
+        let bases = line.iter().filter(|b| **b != b'\n' && **b != b'\r').count() as u64;
+        if bases == 0 {
+            continue;
+        }
+        if record.line_bases == 0 {
+            record.line_bases = bases;
+            record.line_width = read;
+        } else if last_line_short || bases > record.line_bases {
+            return exec_err!(
+                "Different line lengths in FASTA record {} of {}",
+                name,
+                path
+            );
+        }
+        last_line_short = bases < record.line_bases;
+        record.length += bases;
+    }
+    if let Some((name, record)) = current {
+        index.insert(name, record);
+    }
+    Ok(index)
+}
+
+/// `getfasta(fasta_path, chrom, start, end [, strand])` returns the reference sequence
+/// of a 0-based, half-open interval, reverse complemented when `strand` is `-`.
+/// Unknown contigs give `NULL`. Opened FASTA files are cached per path.
+#[derive(Debug)]
+pub(crate) struct GetFasta {
+    signature: Signature,
+    readers: Mutex<HashMap<String, Arc<IndexedFasta>>>,
+}
+
+impl GetFasta {
+    fn new() -> Self {
+        let interval = vec![
+            DataType::Utf8,
+            DataType::Utf8,
+            DataType::Int64,
+            DataType::Int64,
+        ];
+        let stranded = [interval.clone(), vec![DataType::Utf8]].concat();
+        Self {
+            signature: Signature::one_of(
+                vec![
+                    TypeSignature::Exact(interval),
+                    TypeSignature::Exact(stranded),
+                ],
+                Volatility::Immutable,
+            ),
+            readers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn reader(&self, path: &str) -> Result<Arc<IndexedFasta>> {
+        let mut readers = self.readers.lock().unwrap();
+        if let Some(reader) = readers.get(path) {
+            return Ok(reader.clone());
+        }
+        let reader = Arc::new(IndexedFasta::open(path)?);
+        readers.insert(path.to_string(), reader.clone());
+        Ok(reader)
+    }
+}
+
+impl ScalarUDFImpl for GetFasta {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "getfasta"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Utf8)
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        let scalar = args
+            .iter()
+            .all(|arg| matches!(arg, ColumnarValue::Scalar(_)));
+        let arrays = ColumnarValue::values_to_arrays(args)?;
+        let (paths, chroms) = (arrays[0].as_string::<i32>(), arrays[1].as_string::<i32>());
+        let (starts, ends) = (
+            arrays[2].as_primitive::<Int64Type>(),
+            arrays[3].as_primitive::<Int64Type>(),
+        );
+        let strands = arrays.get(4).map(|a| a.as_string::<i32>());
+        let mut builder = StringBuilder::new();
+        for i in 0..chroms.len() {
+            if paths.is_null(i) || chroms.is_null(i) || starts.is_null(i) || ends.is_null(i) {
+                builder.append_null();
+                continue;
+            }
+            let reader = self.reader(paths.value(i))?;
+            match reader.fetch(chroms.value(i), starts.value(i), ends.value(i))? {
+                Some(mut sequence) => {
+                    if strands.is_some_and(|s| s.is_valid(i) && s.value(i) == "-") {
+                        sequence = reverse_complement(&sequence);
+                    }
+                    builder.append_value(String::from_utf8_lossy(&sequence));
+                },
+                None => builder.append_null(),
+            }
+        }
+        let array: ArrayRef = Arc::new(builder.finish());
+        if scalar {
+            Ok(ColumnarValue::Scalar(ScalarValue::try_from_array(
+                &array, 0,
+            )?))
+        } else {
+            Ok(ColumnarValue::Array(array))
+        }
+    }
+}
+
+pub(crate) fn create_getfasta_udf() -> ScalarUDF {
+    ScalarUDF::from(GetFasta::new())
+}
@@ -0,0 +1,216 @@
+use exon::ExonSession;
+use log::{debug, info};
+
+use crate::gff::{attribute_expr, first_parent_expr};
+
+const GENE_ID_ATTRIBUTES: [&str; 2] = ["gene_id", "gene"];
+const TRANSCRIPT_ID_ATTRIBUTE: &str = "transcript_id";
+const ID_ATTRIBUTE: &str = "ID";
+
+/// Registers interval views derived from a gene model table (GTF/GFF):
+/// `{prefix}_features`, `_exons`, `_transcripts`, `_genes`, `_introns`, `_tss`,
+/// `_promoters`, `_utr5` and `_utr3`. All views share `chrom`, `start`, `end` and
+/// `strand` columns, use the coordinate system of the source table and can be
+/// used on either side of a range operation.
+pub(crate) async fn register_gene_model(
+    ctx: &ExonSession,
+    table_name: &str,
+    prefix: &str,
+    upstream: i64,
+    downstream: i64,
+) -> datafusion::common::Result<Vec<String>> {
+    let schema = ctx.session.table(table_name).await?.schema().clone();
+    let attributes_type = schema
+        .field_with_unqualified_name("attributes")?
+        .data_type()
+        .clone();
+    // prefer columns already extracted with `GffReadOptions`
+    let attribute = |key: &str| {
+        if schema.has_column_with_unqualified_name(key) {
+            format!("t.\"{}\"", key)
+        } else {
+            attribute_expr(&attributes_type, "t", key)
+        }
+    };
+    let gene_id = format!(
+        "coalesce({})",
+        GENE_ID_ATTRIBUTES
+            .iter()
+            .map(|k| attribute(k))
+            .collect::<Vec<String>>()
+            .join(", ")
+    );
+    let transcript_id = format!(
+        "coalesce({}, CASE WHEN t.\"type\" IN ('exon', 'CDS') THEN {} ELSE {} END)",
+        attribute(TRANSCRIPT_ID_ATTRIBUTE),
+        first_parent_expr(&attributes_type, "t"),
+        attribute(ID_ATTRIBUTE),
+    );
+
+    let views = vec![
+        (
+            "features",
+            features_query(table_name, &gene_id, &transcript_id),
+        ),
+        ("transcripts", transcripts_query(prefix)),
+        ("exons", exons_query(prefix)),
+        ("genes", genes_query(prefix)),
+        ("introns", introns_query(prefix)),
+        ("tss", tss_query(prefix)),
+        ("promoters", promoters_query(prefix, upstream, downstream)),
+        ("utr5", utr_query(prefix, true)),
+        ("utr3", utr_query(prefix, false)),
+    ];
+    let mut names = Vec::with_capacity(views.len());
+    for (view, query) in views {
+        let name = format!("{}_{}", prefix, view);
+        debug!("Query: {}", query);
+        let df = ctx.session.sql(&query).await?;
+        ctx.session.deregister_table(&name)?;
+        ctx.session.register_table(&name, df.into_view())?;
+        info!("View: {} registered for table: {}", name, table_name);
+        names.push(name);
+    }
+    Ok(names)
+}
+
+fn features_query(table_name: &str, gene_id: &str, transcript_id: &str) -> String {
+    format!(
+        r#"
+        SELECT
+            CAST(t.seqname AS VARCHAR) AS chrom,
+            t.start AS start,
+            t."end" AS "end",
+            CAST(t.strand AS VARCHAR) AS strand,
+            CAST(t."type" AS VARCHAR) AS feature,
+            {} AS gene_id,
+            {} AS transcript_id
+        FROM {} AS t
+        "#,
+        gene_id, transcript_id, table_name
+    )
+}
+
+fn exons_query(prefix: &str) -> String {
+    format!(
+        r#"
+        SELECT
+            e.chrom, e.start, e."end", tr.strand, e.gene_id, e.transcript_id,
+            CAST(ROW_NUMBER() OVER (
+                PARTITION BY e.transcript_id
+                ORDER BY CASE WHEN tr.strand = '-' THEN -CAST(e.start AS BIGINT) ELSE CAST(e.start AS BIGINT) END
+            ) AS BIGINT) AS exon_number
+        FROM {prefix}_features AS e
+        JOIN {prefix}_transcripts AS tr ON e.transcript_id = tr.transcript_id
+        WHERE e.feature = 'exon'
+        "#,
+        prefix = prefix
+    )
+}
+
+/// Transcript and gene spans are computed from exons, so they don't depend on the
+/// naming of transcript/gene feature types. `min(strand)` prefers `+`/`-` over `?` or `.`.
+fn transcripts_query(prefix: &str) -> String {
+    format!(
+        r#"
+        SELECT
+            chrom, min(start) AS start, max("end") AS "end", min(strand) AS strand,
+            max(gene_id) AS gene_id, transcript_id
+        FROM {}_features
+        WHERE feature = 'exon' AND transcript_id IS NOT NULL
+        GROUP BY chrom, transcript_id
+        "#,
+        prefix
+    )
+}
+
+fn genes_query(prefix: &str) -> String {
+    format!(
+        r#"
+        SELECT chrom, min(start) AS start, max("end") AS "end", min(strand) AS strand, gene_id
+        FROM {}_transcripts
+        WHERE gene_id IS NOT NULL
+        GROUP BY chrom, gene_id
+        "#,
+        prefix
+    )
+}
+
+/// Gaps between consecutive exons of a transcript (closed intervals).
+fn introns_query(prefix: &str) -> String {
+    format!(
+        r#"
+        SELECT
+            chrom, "end" + 1 AS start, next_start - 1 AS "end", strand, gene_id, transcript_id
+        FROM (
+            SELECT
+                chrom, start, "end", strand, gene_id, transcript_id,
+                LEAD(start) OVER (PARTITION BY transcript_id ORDER BY start) AS next_start
+            FROM {}_exons
+        )
+        WHERE next_start IS NOT NULL AND next_start > "end" + 1
+        "#,
+        prefix
+    )
+}
+
+fn tss_query(prefix: &str) -> String {
+    format!(
+        r#"
+        SELECT
+            chrom,
+            CASE WHEN strand = '-' THEN "end" ELSE start END AS start,
+            CASE WHEN strand = '-' THEN "end" ELSE start END AS "end",
+            strand, gene_id, transcript_id
+        FROM {}_transcripts
+        "#,
+        prefix
+    )
+}
+
+/// Strand-aware window of `upstream` bases before and `downstream` bases after the TSS.
+fn promoters_query(prefix: &str, upstream: i64, downstream: i64) -> String {
+    format!(
+        r#"
+        SELECT
+            chrom,
+            greatest(CASE WHEN strand = '-' THEN start - {down} + 1 ELSE start - {up} END, 1) AS start,
+            CASE WHEN strand = '-' THEN start + {up} ELSE start + {down} - 1 END AS "end",
+            strand, gene_id, transcript_id
+        FROM {prefix}_tss
+        "#,
+        up = upstream,
+        down = downstream,
+        prefix = prefix
+    )
+}
+
+/// UTRs are exon parts outside of the CDS span of a transcript. The part before the
+/// CDS is 5' UTR on the forward strand and 3' UTR on the reverse strand.
+fn utr_query(prefix: &str, five_prime: bool) -> String {
+    let (left_strand, right_strand) = if five_prime {
+        ("'+'", "'-'")
+    } else {
+        ("'-'", "'+'")
+    };
+    format!(
+        r#"
+        WITH cds AS (
+            SELECT transcript_id, min(start) AS cds_start, max("end") AS cds_end
+            FROM {prefix}_features
+            WHERE feature = 'CDS'
+            GROUP BY transcript_id
+        )
+        SELECT e.chrom, e.start, least(e."end", c.cds_start - 1) AS "end", e.strand, e.gene_id, e.transcript_id
+        FROM {prefix}_exons AS e JOIN cds AS c ON e.transcript_id = c.transcript_id
+        WHERE e.start < c.cds_start AND e.strand = {left}
+        UNION ALL
+        SELECT e.chrom, greatest(e.start, c.cds_end + 1) AS start, e."end", e.strand, e.gene_id, e.transcript_id
+        FROM {prefix}_exons AS e JOIN cds AS c ON e.transcript_id = c.transcript_id
+        WHERE e."end" > c.cds_end AND e.strand = {right}
+        "#,
+        prefix = prefix,
+        left = left_strand,
+        right = right_strand
+    )
+}
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow::array::{Array, AsArray, RecordBatch, StringBuilder};
+use arrow::compute::cast;
+use arrow_schema::{DataType, Field, Schema};
+use datafusion::common::{DataFusionError, Result};
+use datafusion::datasource::MemTable;
+use exon::ExonSession;
+use log::debug;
+
+use crate::option::{GffReadOptions, InputFormat};
+
+const ATTRIBUTES_COLUMN: &str = "attributes";
+const ID_ATTRIBUTE: &str = "ID";
+const PARENT_ATTRIBUTE: &str = "Parent";
+const GTF_GENE_ID_ATTRIBUTE: &str = "gene_id";
+const GTF_TRANSCRIPT_ID_ATTRIBUTE: &str = "transcript_id";
+/// Integer attributes of GENCODE/Ensembl annotations, extracted as `BIGINT`.
+const INTEGER_ATTRIBUTES: [&str; 3] = ["exon_number", "level", "rank"];
+const MAX_HIERARCHY_DEPTH: usize = 64;
+
+/// Registers a GFF3/GTF file with the requested attributes extracted as top-level
+/// columns, strings except for [`INTEGER_ATTRIBUTES`]. The full `attributes` column is
+/// kept for the remaining keys. With `resolve_parents`, every feature gets the ID and
+/// type of its top-level ancestor following the `Parent`/`ID` hierarchy of GFF3 (e.g.
+/// exon -> mRNA -> gene) or the `transcript_id`/`gene_id` attributes of GTF.
+pub(crate) async fn register_gff_table(
+    ctx: &ExonSession,
+    path: &str,
+    table_name: &str,
+    format: &InputFormat,
+    gff_read_options: GffReadOptions,
+) -> Result<()> {
+    let raw_table = format!("{}_raw", table_name);
+    ctx.session.deregister_table(&raw_table)?;
+    ctx.register_exon_table(&raw_table, path, &format.to_string())
+        .await
+        .map_err(|e| DataFusionError::Execution(e.to_string()))?;
+    let attributes_type = ctx
+        .session
+        .table(&raw_table)
+        .await?
+        .schema()
+        .field_with_unqualified_name(ATTRIBUTES_COLUMN)?
+        .data_type()
+        .clone();
+    let mut columns = vec!["r.*".to_string()];
+    for key in gff_read_options.attr_fields.unwrap_or_default() {
+        let expr = attribute_expr(&attributes_type, "r", &key);
+        let expr = if INTEGER_ATTRIBUTES.contains(&key.as_str()) {
+            format!("TRY_CAST({} AS BIGINT)", expr)
+        } else {
+            expr
+        };
+        columns.push(format!("{} AS \"{}\"", expr, key.replace('"', "\"\"")));
+    }
+    let hierarchy_table = format!("{}_hierarchy", table_name);
+    let query = if gff_read_options.resolve_parents {
+        register_hierarchy_table(ctx, &raw_table, &hierarchy_table, &attributes_type, format)
+            .await?;
+        columns.push("h.root_id".to_string());
+        columns.push("h.root_type".to_string());
+        format!(
+            "SELECT {} FROM {} AS r LEFT JOIN {} AS h ON coalesce({}, {}) = h.id",
+            columns.join(", "),
+            raw_table,
+            hierarchy_table,
+            feature_id_expr(&attributes_type, "r", format),
+            feature_parent_expr(&attributes_type, "r", format),
+        )
+    } else {
+        format!("SELECT {} FROM {} AS r", columns.join(", "), raw_table)
+    };
+    debug!("Query: {}", query);
+    let df = ctx.session.sql(&query).await?;
+    ctx.session.register_table(table_name, df.into_view())?;
+    ctx.session.deregister_table(&raw_table)?;
+    ctx.session.deregister_table(&hierarchy_table)?;
+    Ok(())
+}
+
+/// SQL expression extracting a single attribute value as a string.
+/// Multi-valued attributes are joined with a comma.
+pub(crate) fn attribute_expr(attributes_type: &DataType, alias: &str, key: &str) -> String {
+    let column = format!("{}.{}", alias, ATTRIBUTES_COLUMN);
+    let quoted_key = key.replace('\'', "''");
+    match attributes_type {
+        DataType::Map(entries, _) => match entries.data_type() {
+            DataType::Struct(fields)
+                if matches!(
+                    fields[1].data_type(),
+                    DataType::List(_) | DataType::LargeList(_)
+                ) =>
+            {
+                format!("array_to_string({}['{}'], ',')", column, quoted_key)
+            },
+            _ => format!("{}['{}']", column, quoted_key),
+        },
+        // raw text, either GFF3 `key=value;` or GTF `key "value";`
+        _ => format!(
+            "regexp_match({}, '(?:^|;)\\s*{}[= ]\"?([^\";]*)')[1]",
+            column,
+            regex::escape(key).replace('\'', "''")
+        ),
+    }
+}
+
+pub(crate) fn first_parent_expr(attributes_type: &DataType, alias: &str) -> String {
+    format!(
+        "split_part({}, ',', 1)",
+        attribute_expr(attributes_type, alias, PARENT_ATTRIBUTE)
+    )
+}
+
+/// ID of a feature in the hierarchy: the `ID` attribute of GFF3, the `gene_id` of
+/// GTF genes and the `transcript_id` of GTF transcripts.
+fn feature_id_expr(attributes_type: &DataType, alias: &str, format: &InputFormat) -> String {
+    match format {
+        InputFormat::Gtf => format!(
+            "CASE CAST({alias}.\"type\" AS VARCHAR) WHEN 'gene' THEN {} WHEN 'transcript' THEN {} END",
+            attribute_expr(attributes_type, alias, GTF_GENE_ID_ATTRIBUTE),
+            attribute_expr(attributes_type, alias, GTF_TRANSCRIPT_ID_ATTRIBUTE),
+        ),
+        _ => attribute_expr(attributes_type, alias, ID_ATTRIBUTE),
+    }
+}
+
+/// Parent of a feature in the hierarchy: the first `Parent` of GFF3, the `gene_id` of
+/// GTF transcripts and the `transcript_id` (or `gene_id`) of other GTF features.
+fn feature_parent_expr(attributes_type: &DataType, alias: &str, format: &InputFormat) -> String {
+    match format {
+        InputFormat::Gtf => format!(
+            "CASE CAST({alias}.\"type\" AS VARCHAR) WHEN 'gene' THEN NULL WHEN 'transcript' THEN {gene_id} ELSE coalesce({}, {gene_id}) END",
+            attribute_expr(attributes_type, alias, GTF_TRANSCRIPT_ID_ATTRIBUTE),
+            gene_id = attribute_expr(attributes_type, alias, GTF_GENE_ID_ATTRIBUTE),
+        ),
+        _ => first_parent_expr(attributes_type, alias),
+    }
+}
+
+/// Builds an in-memory `id -> (root_id, root_type)` table by walking parent links.
+async fn register_hierarchy_table(
+    ctx: &ExonSession,
+    raw_table: &str,
+    hierarchy_table: &str,
+    attributes_type: &DataType,
+    format: &InputFormat,
+) -> Result<()> {
+    let id = feature_id_expr(attributes_type, "r", format);
+    let query = format!(
+        "SELECT {} AS id, {} AS parent, CAST(r.\"type\" AS VARCHAR) AS type FROM {} AS r WHERE {} IS NOT NULL",
+        id,
+        feature_parent_expr(attributes_type, "r", format),
+        raw_table,
+        id,
+    );
+    debug!("Query: {}", query);
+    let batches = ctx.session.sql(&query).await?.collect().await?;
+    let mut features: HashMap<String, (Option<String>, String)> = HashMap::new();
+    for batch in batches {
+        let columns = (0..3)
+            .map(|i| cast(batch.column(i), &DataType::Utf8))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let (ids, parents, types) = (
+            columns[0].as_string::<i32>(),
+            columns[1].as_string::<i32>(),
+            columns[2].as_string::<i32>(),
+        );
+        for i in 0..batch.num_rows() {
+            let parent = if parents.is_null(i) || parents.value(i).is_empty() {
+                None
+            } else {
+                Some(parents.value(i).to_string())
+            };
+            features.insert(
+                ids.value(i).to_string(),
+                (parent, types.value(i).to_string()),
+            );
+        }
+    }
+
+    let mut id_builder = StringBuilder::new();
+    let mut root_id_builder = StringBuilder::new();
+    let mut root_type_builder = StringBuilder::new();
+    for (id, (_, feature_type)) in &features {
+        let mut root = (id, feature_type);
+        for _ in 0..MAX_HIERARCHY_DEPTH {
+            match features.get(root.0) {
+                Some((Some(parent), _)) if features.contains_key(parent) => {
+                    root = (parent, &features[parent].1);
+                },
+                _ => break,
+            }
+        }
+        id_builder.append_value(id);
+        root_id_builder.append_value(root.0);
+        root_type_builder.append_value(root.1);
+    }
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Utf8, false),
+        Field::new("root_id", DataType::Utf8, false),
+        Field::new("root_type", DataType::Utf8, false),
+    ]));
+    let batch = RecordBatch::try_new(
+        schema.clone(),
+        vec![
+            Arc::new(id_builder.finish()),
+            Arc::new(root_id_builder.finish()),
+            Arc::new(root_type_builder.finish()),
+        ],
+    )?;
+    let table = MemTable::try_new(schema, vec![vec![batch]])?;
+    ctx.session.deregister_table(hierarchy_table)?;
+    ctx.session
+        .register_table(hierarchy_table, Arc::new(table))?;
+    Ok(())
+}
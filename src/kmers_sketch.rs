@@ -0,0 +1,671 @@
+use std::any::Any;
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::Arc;
+
+use arrow::array::{
+    Array, ArrayRef, AsArray, Float64Builder, Int64Array, ListArray, ListBuilder, StringBuilder,
+    StructArray, UInt64Array,
+};
+use arrow::buffer::OffsetBuffer;
+use arrow::datatypes::{Int64Type, UInt64Type};
+use arrow_schema::{DataType, Field, Fields};
+use datafusion::common::{exec_err, plan_err, Result, ScalarValue};
+use datafusion::logical_expr::function::AccumulatorArgs;
+use datafusion::logical_expr::{
+    Accumulator, AggregateUDF, ColumnarValue, ScalarUDF, ScalarUDFImpl, Signature,
+    SimpleAggregateUDF, TypeSignature, Volatility,
+};
+
+use crate::kmers_udaf::{for_each_kmer, KmerMode, PackedKmer, MAX_K};
+
+const DEFAULT_NUM_HASHES: usize = 1000;
+const DEFAULT_SCALE: u64 = 1000;
+
+/// splitmix64 finalizer, a bijection on `u64`.
+fn mix64(mut x: u64) -> u64 {
+    x ^= x >> 30;
+    x = x.wrapping_mul(0xbf58476d1ce4e5b9);
+    x ^= x >> 27;
+    x = x.wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// 64-bit hash of a packed k-mer, collision free for k up to 32. The hashes are not
+/// compatible with Mash or sourmash sketches.
+fn hash_kmer(kmer: u128) -> u64 {
+    mix64(kmer as u64 ^ mix64((kmer >> 64) as u64))
+}
+
+/// Canonical k-mers of `seq` with their start, windows with non-ACGT symbols are skipped.
+fn canonical_kmers(seq: &[u8], k: usize) -> Vec<(usize, u128)> {
+    let mut kmers = Vec::new();
+    for_each_kmer::<u128>(
+        seq,
+        k,
+        KmerMode::default(),
+        |pos, kmer| kmers.push((pos, kmer)),
+        |_| {},
+    );
+    kmers
+}
+
+/// Calls `f` with the index of the leftmost minimum of every `w` consecutive values,
+/// or of all values when there are fewer than `w`.
+fn window_minima(values: &[u64], w: usize, mut f: impl FnMut(usize)) {
+    if values.is_empty() {
+        return;
+    }
+    let w = w.min(values.len());
+    let mut candidates = VecDeque::new();
+    for (i, value) in values.iter().enumerate() {
+        while candidates
+            .back()
+            .is_some_and(|j: &usize| values[*j] > *value)
+        {
+            candidates.pop_back();
+        }
+        candidates.push_back(i);
+        if i + 1 >= w {
+            while candidates.front().is_some_and(|j| j + w <= i) {
+                candidates.pop_front();
+            }
+            f(candidates[0]);
+        }
+    }
+}
+
+/// `(w, k)`-minimizers: the k-mer with the smallest hash of every `w` consecutive
+/// k-mers. Windows do not span non-ACGT symbols.
+fn minimizers(seq: &[u8], k: usize, w: usize) -> Vec<(usize, u128)> {
+    let kmers = canonical_kmers(seq, k);
+    let hashes = kmers
+        .iter()
+        .map(|(_, kmer)| hash_kmer(*kmer))
+        .collect::<Vec<u64>>();
+    let mut selected: Vec<usize> = Vec::new();
+    let mut run_start = 0;
+    for run_end in 1..=kmers.len() {
+        if run_end < kmers.len() && kmers[run_end].0 == kmers[run_end - 1].0 + 1 {
+            continue;
+        }
+        window_minima(&hashes[run_start..run_end], w, |i| {
+            if selected.last() != Some(&(run_start + i)) {
+                selected.push(run_start + i);
+            }
+        });
+        run_start = run_end;
+    }
+    selected.into_iter().map(|i| kmers[i]).collect()
+}
+
+/// Closed syncmers, k-mers whose smallest s-mer is their first or last one, or open
+/// syncmers with the smallest s-mer at `offset`.
+fn syncmers(seq: &[u8], k: usize, s: usize, offset: Option<usize>) -> Vec<(usize, u128)> {
+    let mut smer_hashes = vec![0u64; seq.len()];
+    for (pos, smer) in canonical_kmers(seq, s) {
+        smer_hashes[pos] = hash_kmer(smer);
+    }
+    canonical_kmers(seq, k)
+        .into_iter()
+        .filter(|(pos, _)| {
+            let smallest = smer_hashes[*pos..=*pos + k - s]
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, hash)| **hash)
+                .map(|(i, _)| i);
+            match offset {
+                Some(offset) => smallest == Some(offset),
+                None => smallest == Some(0) || smallest == Some(k - s),
+            }
+        })
+        .collect()
+}
+
+fn check_k(k: i64) -> Result<usize> {
+    if k <= 0 {
+        return exec_err!("k must be greater than 0");
+    }
+    if k as usize > MAX_K {
+        return exec_err!("k must not exceed {}", MAX_K);
+    }
+    Ok(k as usize)
+}
+
+/// Applies `select` to every sequence and returns the selected k-mers as string lists.
+/// `NULL` inputs give `NULL`.
+fn invoke_kmer_list(
+    args: &[ColumnarValue],
+    select: impl Fn(&[u8], usize, &[i64]) -> Result<Vec<(usize, u128)>>,
+) -> Result<ColumnarValue> {
+    let scalar = args
+        .iter()
+        .all(|arg| matches!(arg, ColumnarValue::Scalar(_)));
+    let arrays = ColumnarValue::values_to_arrays(args)?;
+    let sequences = arrays[0].as_string::<i32>();
+    let params = arrays[1..]
+        .iter()
+        .map(|a| a.as_primitive::<Int64Type>())
+        .collect::<Vec<_>>();
+    let mut builder = ListBuilder::new(StringBuilder::new());
+    let mut values = Vec::with_capacity(params.len());
+    for i in 0..sequences.len() {
+        if sequences.is_null(i) || params.iter().any(|p| p.is_null(i)) {
+            builder.append_null();
+            continue;
+        }
+        values.clear();
+        values.extend(params.iter().map(|p| p.value(i)));
+        let k = check_k(values[0])?;
+        for (_, kmer) in select(sequences.value(i).as_bytes(), k, &values[1..])? {
+            builder.values().append_value(kmer.decode(k));
+        }
+        builder.append(true);
+    }
+    let array: ArrayRef = Arc::new(builder.finish());
+    if scalar {
+        Ok(ColumnarValue::Scalar(ScalarValue::try_from_array(
+            &array, 0,
+        )?))
+    } else {
+        Ok(ColumnarValue::Array(array))
+    }
+}
+
+fn kmer_list_type() -> DataType {
+    DataType::List(Arc::new(Field::new("item", DataType::Utf8, true)))
+}
+
+/// `minimizers(sequence, k, w)` returns the canonical `(w, k)`-minimizers of a sequence
+/// in order of their positions.
+#[derive(Debug)]
+struct Minimizers {
+    signature: Signature,
+}
+
+impl ScalarUDFImpl for Minimizers {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "minimizers"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(kmer_list_type())
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        invoke_kmer_list(args, |seq, k, params| {
+            if params[0] <= 0 {
+                return exec_err!("w must be greater than 0");
+            }
+            Ok(minimizers(seq, k, params[0] as usize))
+        })
+    }
+}
+
+/// `syncmers(sequence, k, s [, offset])` returns the canonical closed syncmers of a
+/// sequence, or the open syncmers with the smallest s-mer at `offset`.
+#[derive(Debug)]
+struct Syncmers {
+    signature: Signature,
+}
+
+impl ScalarUDFImpl for Syncmers {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "syncmers"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(kmer_list_type())
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        invoke_kmer_list(args, |seq, k, params| {
+            let s = params[0];
+            if s <= 0 || s as usize > k {
+                return exec_err!("s must be between 1 and k");
+            }
+            let s = s as usize;
+            let offset = match params.get(1) {
+                Some(t) if *t < 0 || (*t as usize) > k - s => {
+                    return exec_err!("offset must be between 0 and k - s")
+                },
+                t => t.map(|t| *t as usize),
+            };
+            Ok(syncmers(seq, k, s, offset))
+        })
+    }
+}
+
+pub(crate) fn create_minimizers_udf() -> ScalarUDF {
+    ScalarUDF::from(Minimizers {
+        signature: Signature::exact(
+            vec![DataType::Utf8, DataType::Int64, DataType::Int64],
+            Volatility::Immutable,
+        ),
+    })
+}
+
+pub(crate) fn create_syncmers_udf() -> ScalarUDF {
+    let closed = vec![DataType::Utf8, DataType::Int64, DataType::Int64];
+    let open = [closed.clone(), vec![DataType::Int64]].concat();
+    ScalarUDF::from(Syncmers {
+        signature: Signature::one_of(
+            vec![TypeSignature::Exact(closed), TypeSignature::Exact(open)],
+            Volatility::Immutable,
+        ),
+    })
+}
+
+/// A sketch keeps the hashes of canonical k-mers not above `u64::MAX / scale`, at most
+/// the `num_hashes` smallest ones. MinHash sketches have `scale` 1, FracMinHash sketches
+/// have `num_hashes` 0, i.e. unbounded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct SketchParams {
+    k: usize,
+    num_hashes: usize,
+    scale: u64,
+}
+
+impl SketchParams {
+    fn max_hash(&self) -> u64 {
+        u64::MAX / self.scale
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum SketchKind {
+    MinHash,
+    FracMinHash,
+}
+
+impl SketchKind {
+    fn params(&self, k: i64, size: Option<i64>) -> Result<SketchParams> {
+        let k = check_k(k)?;
+        match self {
+            SketchKind::MinHash => match size {
+                Some(n) if n <= 0 => exec_err!("num_hashes must be greater than 0"),
+                n => Ok(SketchParams {
+                    k,
+                    num_hashes: n.map_or(DEFAULT_NUM_HASHES, |n| n as usize),
+                    scale: 1,
+                }),
+            },
+            SketchKind::FracMinHash => match size {
+                Some(scale) if scale <= 0 => exec_err!("scale must be greater than 0"),
+                scale => Ok(SketchParams {
+                    k,
+                    num_hashes: 0,
+                    scale: scale.map_or(DEFAULT_SCALE, |s| s as u64),
+                }),
+            },
+        }
+    }
+}
+
+fn sketch_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("k", DataType::Int64, false),
+        Field::new("num_hashes", DataType::Int64, false),
+        Field::new("scale", DataType::Int64, false),
+        Field::new(
+            "hashes",
+            DataType::List(Arc::new(Field::new("item", DataType::UInt64, true))),
+            false,
+        ),
+    ])
+}
+
+#[derive(Debug)]
+struct SketchAccumulator {
+    kind: SketchKind,
+    params: Option<SketchParams>,
+    hashes: BTreeSet<u64>,
+}
+
+impl SketchAccumulator {
+    fn new(kind: SketchKind) -> Self {
+        Self {
+            kind,
+            params: None,
+            hashes: BTreeSet::new(),
+        }
+    }
+
+    fn set_params(&mut self, params: SketchParams) -> Result<SketchParams> {
+        match self.params {
+            None => {
+                self.params = Some(params);
+                Ok(params)
+            },
+            Some(current) if current != params => {
+                exec_err!("Inconsistent sketch parameters in UDAF batch")
+            },
+            Some(current) => Ok(current),
+        }
+    }
+
+    fn insert(&mut self, hash: u64, params: &SketchParams) {
+        if hash > params.max_hash() {
+            return;
+        }
+        if params.num_hashes > 0 && self.hashes.len() >= params.num_hashes {
+            if self.hashes.last().is_some_and(|last| hash >= *last) {
+                return;
+            }
+            self.hashes.insert(hash);
+            self.hashes.pop_last();
+        } else {
+            self.hashes.insert(hash);
+        }
+    }
+
+    fn hash_values(&self) -> ArrayRef {
+        Arc::new(UInt64Array::from_iter_values(self.hashes.iter().copied()))
+    }
+}
+
+impl Accumulator for SketchAccumulator {
+    fn update_batch(&mut self, values: &[ArrayRef]) -> Result<()> {
+        let sequences = values[0].as_string::<i32>();
+        let ks = values[1].as_primitive::<Int64Type>();
+        let sizes = values.get(2).map(|a| a.as_primitive::<Int64Type>());
+        for i in 0..sequences.len() {
+            if sequences.is_null(i) || ks.is_null(i) {
+                continue;
+            }
+            let size = sizes.filter(|s| s.is_valid(i)).map(|s| s.value(i));
+            let params = self.set_params(self.kind.params(ks.value(i), size)?)?;
+            for (_, kmer) in canonical_kmers(sequences.value(i).as_bytes(), params.k) {
+                self.insert(hash_kmer(kmer), &params);
+            }
+        }
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> Result<()> {
+        let ks = states[0].as_primitive::<Int64Type>();
+        let num_hashes = states[1].as_primitive::<Int64Type>();
+        let scales = states[2].as_primitive::<Int64Type>();
+        let hash_lists = states[3].as_list::<i32>();
+        for row in 0..ks.len() {
+            if ks.is_null(row) || hash_lists.is_null(row) {
+                continue;
+            }
+            let params = self.set_params(SketchParams {
+                k: ks.value(row) as usize,
+                num_hashes: num_hashes.value(row) as usize,
+                scale: scales.value(row) as u64,
+            })?;
+            let hashes = hash_lists.value(row);
+            for hash in hashes.as_primitive::<UInt64Type>().iter().flatten() {
+                self.insert(hash, &params);
+            }
+        }
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        let hashes = ListArray::new(
+            Arc::new(Field::new("item", DataType::UInt64, true)),
+            OffsetBuffer::from_lengths([self.hashes.len()]),
+            self.hash_values(),
+            None,
+        );
+        Ok(match self.params {
+            Some(params) => vec![
+                ScalarValue::Int64(Some(params.k as i64)),
+                ScalarValue::Int64(Some(params.num_hashes as i64)),
+                ScalarValue::Int64(Some(params.scale as i64)),
+                ScalarValue::List(Arc::new(hashes)),
+            ],
+            None => vec![
+                ScalarValue::Int64(None),
+                ScalarValue::Int64(None),
+                ScalarValue::Int64(None),
+                ScalarValue::List(Arc::new(hashes)),
+            ],
+        })
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        let params = match self.params {
+            Some(params) => params,
+            None => return ScalarValue::try_from(&DataType::Struct(sketch_fields())),
+        };
+        let hashes = ListArray::new(
+            Arc::new(Field::new("item", DataType::UInt64, true)),
+            OffsetBuffer::from_lengths([self.hashes.len()]),
+            self.hash_values(),
+            None,
+        );
+        let sketch = StructArray::new(
+            sketch_fields(),
+            vec![
+                Arc::new(Int64Array::from(vec![params.k as i64])),
+                Arc::new(Int64Array::from(vec![params.num_hashes as i64])),
+                Arc::new(Int64Array::from(vec![params.scale as i64])),
+                Arc::new(hashes),
+            ],
+            None,
+        );
+        Ok(ScalarValue::Struct(Arc::new(sketch)))
+    }
+
+    fn size(&self) -> usize {
+        // a BTreeSet entry takes roughly twice the size of its key
+        self.hashes.len() * 2 * std::mem::size_of::<u64>() + std::mem::size_of::<Self>()
+    }
+}
+
+fn create_sketch_udaf(name: &str, kind: SketchKind) -> AggregateUDF {
+    let accumulator_creator = move |_args: AccumulatorArgs<'_>| -> Result<Box<dyn Accumulator>> {
+        Ok(Box::new(SketchAccumulator::new(kind)))
+    };
+    let required = vec![DataType::Utf8, DataType::Int64];
+    let sized = [required.clone(), vec![DataType::Int64]].concat();
+    let state_fields = sketch_fields()
+        .iter()
+        .map(|f| f.as_ref().clone().with_nullable(true))
+        .collect();
+    AggregateUDF::from(SimpleAggregateUDF::new_with_signature(
+        name,
+        Signature::one_of(
+            vec![TypeSignature::Exact(required), TypeSignature::Exact(sized)],
+            Volatility::Immutable,
+        ),
+        DataType::Struct(sketch_fields()),
+        Arc::new(accumulator_creator),
+        state_fields,
+    ))
+}
+
+/// `minhash_sketch(sequence, k [, num_hashes])` keeps the `num_hashes` (default 1000)
+/// smallest k-mer hashes.
+pub(crate) fn create_minhash_sketch_udaf() -> AggregateUDF {
+    create_sketch_udaf("minhash_sketch", SketchKind::MinHash)
+}
+
+/// `fracminhash_sketch(sequence, k [, scale])` keeps the k-mer hashes not above
+/// `u64::MAX / scale` (default 1000).
+pub(crate) fn create_fracminhash_sketch_udaf() -> AggregateUDF {
+    create_sketch_udaf("fracminhash_sketch", SketchKind::FracMinHash)
+}
+
+/// Jaccard index estimate of the k-mer sets of two sketches. Both are downsampled to the
+/// larger scale and, if any is bounded, the smallest hashes of their union are compared
+/// as in Mash.
+fn jaccard(a: &(SketchParams, Vec<u64>), b: &(SketchParams, Vec<u64>)) -> f64 {
+    let max_hash = u64::MAX / a.0.scale.max(b.0.scale);
+    let limit = match (a.0.num_hashes, b.0.num_hashes) {
+        (0, 0) => usize::MAX,
+        (0, n) | (n, 0) => n,
+        (m, n) => m.min(n),
+    };
+    let (mut i, mut j) = (0, 0);
+    let (mut union, mut shared) = (0usize, 0usize);
+    while union < limit {
+        let x = a.1.get(i).filter(|h| **h <= max_hash);
+        let y = b.1.get(j).filter(|h| **h <= max_hash);
+        match (x, y) {
+            (None, None) => break,
+            (Some(x), Some(y)) if x == y => {
+                shared += 1;
+                i += 1;
+                j += 1;
+            },
+            (Some(x), Some(y)) if x < y => i += 1,
+            (Some(_), None) => i += 1,
+            _ => j += 1,
+        }
+        union += 1;
+    }
+    if union == 0 {
+        0.0
+    } else {
+        shared as f64 / union as f64
+    }
+}
+
+/// Mash distance of a Jaccard index estimate, ANI is estimated as `1 - distance`.
+fn mash_distance(jaccard: f64, k: usize) -> f64 {
+    if jaccard <= 0.0 {
+        return 1.0;
+    }
+    (-1.0 / k as f64 * (2.0 * jaccard / (1.0 + jaccard)).ln()).min(1.0)
+}
+
+fn distance_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("jaccard", DataType::Float64, true),
+        Field::new("mash_distance", DataType::Float64, true),
+        Field::new("ani", DataType::Float64, true),
+    ])
+}
+
+/// Sketch parameters and sorted hashes of row `i`, `None` for a `NULL` sketch.
+fn sketch_at(sketches: &StructArray, i: usize) -> Result<Option<(SketchParams, Vec<u64>)>> {
+    let column = |name: &str| match sketches.column_by_name(name) {
+        Some(column) => Ok(column),
+        None => plan_err!("sketch_distance expects sketches, missing field {}", name),
+    };
+    let (ks, num_hashes, scales) = (
+        column("k")?.as_primitive::<Int64Type>(),
+        column("num_hashes")?.as_primitive::<Int64Type>(),
+        column("scale")?.as_primitive::<Int64Type>(),
+    );
+    let hash_lists = column("hashes")?.as_list::<i32>();
+    if sketches.is_null(i) || ks.is_null(i) || hash_lists.is_null(i) {
+        return Ok(None);
+    }
+    let mut hashes = hash_lists
+        .value(i)
+        .as_primitive::<UInt64Type>()
+        .iter()
+        .flatten()
+        .collect::<Vec<u64>>();
+    hashes.sort_unstable();
+    Ok(Some((
+        SketchParams {
+            k: ks.value(i) as usize,
+            num_hashes: num_hashes.value(i) as usize,
+            scale: scales.value(i).max(1) as u64,
+        },
+        hashes,
+    )))
+}
+
+/// `sketch_distance(a, b)` compares two `minhash_sketch` or `fracminhash_sketch`
+/// sketches of the same k and returns their `jaccard` index, `mash_distance` and `ani`.
+#[derive(Debug)]
+struct SketchDistance {
+    signature: Signature,
+}
+
+impl ScalarUDFImpl for SketchDistance {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        "sketch_distance"
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+        if !arg_types.iter().all(|t| matches!(t, DataType::Struct(_))) {
+            return plan_err!("sketch_distance expects two sketches, got {:?}", arg_types);
+        }
+        Ok(DataType::Struct(distance_fields()))
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        let scalar = args
+            .iter()
+            .all(|arg| matches!(arg, ColumnarValue::Scalar(_)));
+        let arrays = ColumnarValue::values_to_arrays(args)?;
+        let (left, right) = (arrays[0].as_struct(), arrays[1].as_struct());
+        let mut builders = [
+            Float64Builder::new(),
+            Float64Builder::new(),
+            Float64Builder::new(),
+        ];
+        for i in 0..left.len() {
+            match (sketch_at(left, i)?, sketch_at(right, i)?) {
+                (Some(a), Some(b)) => {
+                    if a.0.k != b.0.k {
+                        return exec_err!(
+                            "Cannot compare sketches of different k: {} and {}",
+                            a.0.k,
+                            b.0.k
+                        );
+                    }
+                    let jaccard = jaccard(&a, &b);
+                    let distance = mash_distance(jaccard, a.0.k);
+                    builders[0].append_value(jaccard);
+                    builders[1].append_value(distance);
+                    builders[2].append_value(1.0 - distance);
+                },
+                _ => builders.iter_mut().for_each(|b| b.append_null()),
+            }
+        }
+        let array: ArrayRef = Arc::new(StructArray::new(
+            distance_fields(),
+            builders
+                .iter_mut()
+                .map(|b| Arc::new(b.finish()) as ArrayRef)
+                .collect(),
+            None,
+        ));
+        if scalar {
+            Ok(ColumnarValue::Scalar(ScalarValue::try_from_array(
+                &array, 0,
+            )?))
+        } else {
+            Ok(ColumnarValue::Array(array))
+        }
+    }
+}
+
+pub(crate) fn create_sketch_distance_udf() -> ScalarUDF {
+    ScalarUDF::from(SketchDistance {
+        signature: Signature::any(2, Volatility::Immutable),
+    })
+}
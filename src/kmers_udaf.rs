@@ -1,65 +1,523 @@
-use std::collections::HashMap;
+use std::hash::Hash;
 use std::sync::Arc;
 
-use arrow_array::{StringArray, Int64Array, StructArray};
+use arrow_array::{ArrayRef, BooleanArray, StringArray, Int64Array, ListArray, StructArray};
+use arrow::buffer::OffsetBuffer;
 use arrow_array::builder::{StringBuilder, Int64Builder};
 use arrow_schema::{DataType, Field, Fields};
 use datafusion::common::Result;
-use datafusion::logical_expr::{Volatility, AggregateUDF, create_udaf};
+use datafusion::logical_expr::{
+    Volatility, AggregateUDF, Signature, SimpleAggregateUDF, TypeSignature,
+};
 use datafusion::physical_plan::Accumulator;
 use datafusion::logical_expr::function::AccumulatorArgs;
 use datafusion::scalar::ScalarValue;
 use arrow_array::Array;
 use datafusion::common::DataFusionError;
+use rustc_hash::FxHashMap;
 
+use crate::sequence::reverse_complement;
+
+/// `kmer_count(sequence, k [, strand [, keep_n]])`, where `strand` is `'canonical'`
+/// (default) or `'forward'` and `keep_n` also counts k-mers containing `N`.
 pub fn create_kmer_count_udaf() -> AggregateUDF {
     let accumulator_creator = |_args: AccumulatorArgs<'_>| -> Result<Box<dyn Accumulator>> {
         Ok(Box::new(KmerCountAccumulator::new()))
     };
 
-    let return_type = Arc::new(DataType::Struct(Fields::from(vec![
-        Field::new("kmer", DataType::Utf8, false),
-        Field::new("count", DataType::Int64, false),
-    ])));
+    let return_type = DataType::Struct(kmer_count_fields());
 
-    let state_type = vec![DataType::Utf8, DataType::Int64];
+    // partial counts are exchanged between aggregation phases as parallel lists
+    let state_fields = vec![
+        Field::new("kmers", DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))), true),
+        Field::new("counts", DataType::List(Arc::new(Field::new("item", DataType::Int64, true))), true),
+    ];
 
-    create_udaf(
+    AggregateUDF::from(SimpleAggregateUDF::new_with_signature(
         "kmer_count",
-        vec![DataType::Utf8, DataType::Int64],
+        kmer_signature(),
         return_type,
-        Volatility::Immutable,
         Arc::new(accumulator_creator),
-        Arc::new(state_type),
+        state_fields,
+    ))
+}
+
+fn kmer_count_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("kmer", DataType::Utf8, false),
+        Field::new("count", DataType::Int64, false),
+    ])
+}
+
+/// `kmer_skipped_windows(sequence, k [, strand [, keep_n]])` returns the number of windows
+/// that `kmer_count` with the same arguments skips for symbols other than `ACGT`, or
+/// `ACGTN` with `keep_n`.
+pub fn create_kmer_skipped_windows_udaf() -> AggregateUDF {
+    let accumulator_creator = |_args: AccumulatorArgs<'_>| -> Result<Box<dyn Accumulator>> {
+        Ok(Box::new(SkippedWindowsAccumulator::default()))
+    };
+
+    AggregateUDF::from(SimpleAggregateUDF::new_with_signature(
+        "kmer_skipped_windows",
+        kmer_signature(),
+        DataType::Int64,
+        Arc::new(accumulator_creator),
+        vec![Field::new("skipped_windows", DataType::Int64, true)],
+    ))
+}
+
+/// `(sequence, k [, strand [, keep_n]])`
+fn kmer_signature() -> Signature {
+    let required = vec![DataType::Utf8, DataType::Int64];
+    let stranded = [required.clone(), vec![DataType::Utf8]].concat();
+    let with_n = [stranded.clone(), vec![DataType::Boolean]].concat();
+    Signature::one_of(
+        vec![
+            TypeSignature::Exact(required),
+            TypeSignature::Exact(stranded),
+            TypeSignature::Exact(with_n),
+        ],
+        Volatility::Immutable,
     )
 }
 
+/// How sequence windows are turned into k-mers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct KmerMode {
+    /// Count the smaller of a k-mer and its reverse complement, otherwise the forward strand only.
+    pub(crate) canonical: bool,
+    /// Keep `N` as a symbol instead of skipping windows containing it.
+    pub(crate) keep_n: bool,
+}
+
+impl Default for KmerMode {
+    fn default() -> Self {
+        Self {
+            canonical: true,
+            keep_n: false,
+        }
+    }
+}
+
+impl KmerMode {
+    pub(crate) fn new(strand: Option<&str>, keep_n: Option<bool>) -> Result<Self> {
+        let canonical = match strand.map(|s| s.to_lowercase()).as_deref() {
+            None | Some("canonical") => true,
+            Some("forward") => false,
+            Some(other) => {
+                return Err(DataFusionError::Execution(format!(
+                    "Unknown k-mer strand mode {}, expected 'canonical' or 'forward'",
+                    other
+                )))
+            },
+        };
+        Ok(Self {
+            canonical,
+            keep_n: keep_n.unwrap_or(false),
+        })
+    }
+
+    /// Mode of row `i` given the optional `strand` and `keep_n` argument arrays.
+    fn from_args(args: &[ArrayRef], i: usize) -> Result<Self> {
+        let strand = args.get(2).map(|a| a.as_any().downcast_ref::<StringArray>().expect("Expected StringArray"));
+        let keep_n = args.get(3).map(|a| a.as_any().downcast_ref::<BooleanArray>().expect("Expected BooleanArray"));
+        Self::new(
+            strand.filter(|s| s.is_valid(i)).map(|s| s.value(i)),
+            keep_n.filter(|n| n.is_valid(i)).map(|n| n.value(i)),
+        )
+    }
+}
+
+/// Wraps all `values` into a one-element list scalar.
+fn single_list(data_type: DataType, values: ArrayRef) -> ScalarValue {
+    let list = ListArray::new(
+        Arc::new(Field::new("item", data_type, true)),
+        OffsetBuffer::from_lengths([values.len()]),
+        values,
+        None,
+    );
+    ScalarValue::List(Arc::new(list))
+}
+
+/// Maximum k-mer size, k-mers up to 32 bases are packed into `u64`, longer into `u128`.
+pub(crate) const MAX_K: usize = 64;
+
+/// 2-bit code of a nucleotide (A=0, C=1, G=2, T=3), `None` for any other symbol.
+/// The numeric order of packed k-mers matches the lexicographic order of their strings.
+#[inline]
+fn encode_base(base: u8) -> Option<u8> {
+    match base {
+        b'A' | b'a' => Some(0),
+        b'C' | b'c' => Some(1),
+        b'G' | b'g' => Some(2),
+        b'T' | b't' => Some(3),
+        _ => None,
+    }
+}
+
+const DECODE: [u8; 4] = [b'A', b'C', b'G', b'T'];
+
+/// A k-mer packed 2 bits per base, most significant bits first.
+pub(crate) trait PackedKmer: Copy + Eq + Hash + Ord {
+    fn mask(k: usize) -> Self;
+    /// Appends a base to the forward k-mer, dropping the first one.
+    fn push_forward(self, code: u8, mask: Self) -> Self;
+    /// Prepends the complement of a base to the reverse complement k-mer.
+    fn push_reverse(self, code: u8, k: usize) -> Self;
+    fn decode(self, k: usize) -> String;
+}
+
+macro_rules! impl_packed_kmer {
+    ($t:ty) => {
+        impl PackedKmer for $t {
+            #[inline]
+            fn mask(k: usize) -> Self {
+                if 2 * k >= <$t>::BITS as usize {
+                    <$t>::MAX
+                } else {
+                    (1 << (2 * k)) - 1
+                }
+            }
+
+            #[inline]
+            fn push_forward(self, code: u8, mask: Self) -> Self {
+                ((self << 2) | code as $t) & mask
+            }
+
+            #[inline]
+            fn push_reverse(self, code: u8, k: usize) -> Self {
+                (self >> 2) | (((3 - code) as $t) << (2 * (k - 1)))
+            }
+
+            fn decode(self, k: usize) -> String {
+                (0..k)
+                    .rev()
+                    .map(|i| DECODE[((self >> (2 * i)) & 3) as usize] as char)
+                    .collect()
+            }
+        }
+    };
+}
+
+impl_packed_kmer!(u64);
+impl_packed_kmer!(u128);
+
+/// Calls `packed` with the start and the packed k-mer of every ACGT window of `seq`, the canonical
+/// (smaller of forward and reverse complement) one unless counting the forward strand
+/// only. Both strands are updated incrementally. With `keep_n`, windows containing `N`
+/// are passed uppercased to `with_n`. Windows with any other symbol are skipped, their
+/// number is returned.
+#[inline]
+pub(crate) fn for_each_kmer<T: PackedKmer + Default>(
+    seq: &[u8],
+    k: usize,
+    mode: KmerMode,
+    mut packed: impl FnMut(usize, T),
+    mut with_n: impl FnMut(Vec<u8>),
+) -> usize {
+    let mask = T::mask(k);
+    let mut forward = T::default();
+    let mut reverse = T::default();
+    // lengths of the current runs of ACGT and of ACGT or N symbols
+    let mut valid = 0usize;
+    let mut valid_with_n = 0usize;
+    let mut skipped = 0usize;
+    for (i, &base) in seq.iter().enumerate() {
+        match encode_base(base) {
+            Some(code) => {
+                forward = forward.push_forward(code, mask);
+                if mode.canonical {
+                    reverse = reverse.push_reverse(code, k);
+                }
+                valid += 1;
+                valid_with_n += 1;
+            },
+            None if mode.keep_n && matches!(base, b'N' | b'n') => {
+                valid = 0;
+                valid_with_n += 1;
+            },
+            None => {
+                valid = 0;
+                valid_with_n = 0;
+            },
+        }
+        if i + 1 < k {
+            continue;
+        }
+        if valid >= k {
+            packed(i + 1 - k, if mode.canonical { forward.min(reverse) } else { forward });
+        } else if valid_with_n >= k {
+            with_n(kmer_with_n(&seq[i + 1 - k..=i], mode.canonical));
+        } else {
+            skipped += 1;
+        }
+    }
+    skipped
+}
+
+/// Uppercased k-mer of a window containing `N`, which is its own complement.
+fn kmer_with_n(window: &[u8], canonical: bool) -> Vec<u8> {
+    let forward = window.to_ascii_uppercase();
+    if !canonical {
+        return forward;
+    }
+    let reverse = reverse_complement(&forward);
+    forward.min(reverse)
+}
+
+fn encode_kmer<T: PackedKmer + Default>(kmer: &[u8]) -> Option<T> {
+    let mask = T::mask(kmer.len());
+    kmer.iter()
+        .try_fold(T::default(), |packed, &base| Some(packed.push_forward(encode_base(base)?, mask)))
+}
+
+/// K-mer strings and their counts.
+fn kmer_arrays(entries: impl Iterator<Item = (String, i64)>, len: usize, k: usize) -> (ArrayRef, ArrayRef) {
+    let mut kmer_builder = StringBuilder::with_capacity(len, len * k);
+    let mut count_builder = Int64Builder::with_capacity(len);
+    for (kmer, count) in entries {
+        kmer_builder.append_value(kmer);
+        count_builder.append_value(count);
+    }
+    (Arc::new(kmer_builder.finish()), Arc::new(count_builder.finish()))
+}
+
+/// Entries counted at least `min_count` times, see `top_kmers`.
+fn select_kmers<T: Ord + Clone>(counts: &FxHashMap<T, i64>, min_count: i64, top_n: Option<usize>) -> Vec<(T, i64)> {
+    let mut entries = counts
+        .iter()
+        .filter(|(_, count)| **count >= min_count)
+        .map(|(kmer, count)| (kmer.clone(), *count))
+        .collect::<Vec<_>>();
+    top_kmers(&mut entries, top_n);
+    entries
+}
+
+/// With `top_n`, keeps only the `top_n` most frequent entries, ordered by descending
+/// count and then by k-mer.
+fn top_kmers<T: Ord>(entries: &mut Vec<(T, i64)>, top_n: Option<usize>) {
+    if let Some(n) = top_n {
+        let order = |a: &(T, i64), b: &(T, i64)| b.1.cmp(&a.1).then(a.0.cmp(&b.0));
+        if n < entries.len() {
+            entries.select_nth_unstable_by(n, order);
+            entries.truncate(n);
+        }
+        entries.sort_unstable_by(order);
+    }
+}
+
+/// Decodes the selected entries lazily, `batch_size` k-mers at a time.
+fn kmer_batches<T: Send + 'static>(
+    entries: Vec<(T, i64)>,
+    k: usize,
+    batch_size: usize,
+    decode: fn(&T, usize) -> String,
+) -> Box<dyn Iterator<Item = (ArrayRef, ArrayRef)> + Send> {
+    let len = entries.len();
+    Box::new((0..len).step_by(batch_size.max(1)).map(move |start| {
+        let end = (start + batch_size.max(1)).min(len);
+        kmer_arrays(entries[start..end].iter().map(|(kmer, count)| (decode(kmer, k), *count)), end - start, k)
+    }))
+}
+
+/// Packed k-mers decoded to strings, to be merged with k-mers containing `N`.
+fn decoded_kmers<T: PackedKmer>(entries: Vec<(T, i64)>, k: usize) -> impl Iterator<Item = (Vec<u8>, i64)> {
+    entries.into_iter().map(move |(kmer, count)| (kmer.decode(k).into_bytes(), count))
+}
+
+/// Counts keyed by packed k-mers, the key width is chosen once k is known.
+#[derive(Debug)]
+enum PackedCounts {
+    Empty,
+    Short(FxHashMap<u64, i64>),
+    Long(FxHashMap<u128, i64>),
+}
+
+/// K-mer counts, packed for ACGT k-mers and as strings for k-mers containing `N`.
+#[derive(Debug)]
+pub(crate) struct KmerCounts {
+    packed: PackedCounts,
+    with_n: FxHashMap<Vec<u8>, i64>,
+}
+
+impl KmerCounts {
+    fn empty() -> Self {
+        Self {
+            packed: PackedCounts::Empty,
+            with_n: FxHashMap::default(),
+        }
+    }
+
+    pub(crate) fn with_k(k: usize) -> Self {
+        let packed = if k <= 32 {
+            PackedCounts::Short(FxHashMap::default())
+        } else {
+            PackedCounts::Long(FxHashMap::default())
+        };
+        Self {
+            packed,
+            with_n: FxHashMap::default(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        let packed = match &self.packed {
+            PackedCounts::Empty => 0,
+            PackedCounts::Short(counts) => counts.len(),
+            PackedCounts::Long(counts) => counts.len(),
+        };
+        packed + self.with_n.len()
+    }
+
+    pub(crate) fn count_sequence(&mut self, seq: &[u8], k: usize, mode: KmerMode) {
+        let with_n = &mut self.with_n;
+        let count_with_n = |kmer: Vec<u8>| *with_n.entry(kmer).or_insert(0) += 1;
+        match &mut self.packed {
+            PackedCounts::Empty => {},
+            PackedCounts::Short(counts) => {
+                for_each_kmer::<u64>(seq, k, mode, |_, kmer| *counts.entry(kmer).or_insert(0) += 1, count_with_n);
+            },
+            PackedCounts::Long(counts) => {
+                for_each_kmer::<u128>(seq, k, mode, |_, kmer| *counts.entry(kmer).or_insert(0) += 1, count_with_n);
+            },
+        }
+    }
+
+    /// Adds counts of another partition counted with the same k.
+    pub(crate) fn merge(&mut self, other: KmerCounts) {
+        for (kmer, count) in other.with_n {
+            *self.with_n.entry(kmer).or_insert(0) += count;
+        }
+        match (&mut self.packed, other.packed) {
+            (PackedCounts::Short(counts), PackedCounts::Short(other)) => {
+                for (kmer, count) in other {
+                    *counts.entry(kmer).or_insert(0) += count;
+                }
+            },
+            (PackedCounts::Long(counts), PackedCounts::Long(other)) => {
+                for (kmer, count) in other {
+                    *counts.entry(kmer).or_insert(0) += count;
+                }
+            },
+            (packed @ PackedCounts::Empty, other) => *packed = other,
+            _ => {},
+        }
+    }
+
+    fn add(&mut self, kmer: &str, count: i64) -> Result<()> {
+        let invalid = || DataFusionError::Execution(format!("Invalid k-mer in partial state: {}", kmer));
+        if kmer.contains('N') {
+            if !kmer.bytes().all(|b| b == b'N' || encode_base(b).is_some()) {
+                return Err(invalid());
+            }
+            *self.with_n.entry(kmer.as_bytes().to_vec()).or_insert(0) += count;
+            return Ok(());
+        }
+        match &mut self.packed {
+            PackedCounts::Empty => {},
+            PackedCounts::Short(counts) => {
+                *counts.entry(encode_kmer(kmer.as_bytes()).ok_or_else(invalid)?).or_insert(0) += count
+            },
+            PackedCounts::Long(counts) => {
+                *counts.entry(encode_kmer(kmer.as_bytes()).ok_or_else(invalid)?).or_insert(0) += count
+            },
+        }
+        Ok(())
+    }
+
+    /// Decoded k-mer strings and their counts.
+    fn to_arrays(&self, k: usize) -> (ArrayRef, ArrayRef) {
+        let with_n = self
+            .with_n
+            .iter()
+            .map(|(kmer, count)| (String::from_utf8_lossy(kmer).into_owned(), *count));
+        match &self.packed {
+            PackedCounts::Empty => kmer_arrays(with_n, self.len(), k),
+            PackedCounts::Short(counts) => {
+                let packed = counts.iter().map(|(kmer, count)| (kmer.decode(k), *count));
+                kmer_arrays(packed.chain(with_n), self.len(), k)
+            },
+            PackedCounts::Long(counts) => {
+                let packed = counts.iter().map(|(kmer, count)| (kmer.decode(k), *count));
+                kmer_arrays(packed.chain(with_n), self.len(), k)
+            },
+        }
+    }
+
+    /// Batches of the k-mers counted at least `min_count` times, see `select_kmers`.
+    /// The counts are released once the entries are selected. K-mers containing `N`
+    /// are decoded together with the packed ones, which are otherwise decoded lazily.
+    pub(crate) fn into_batches(
+        self,
+        k: usize,
+        min_count: i64,
+        top_n: Option<usize>,
+        batch_size: usize,
+    ) -> Box<dyn Iterator<Item = (ArrayRef, ArrayRef)> + Send> {
+        let mut with_n = select_kmers(&self.with_n, min_count, top_n);
+        match self.packed {
+            PackedCounts::Short(counts) if with_n.is_empty() => {
+                kmer_batches(select_kmers(&counts, min_count, top_n), k, batch_size, |kmer, k| kmer.decode(k))
+            },
+            PackedCounts::Long(counts) if with_n.is_empty() => {
+                kmer_batches(select_kmers(&counts, min_count, top_n), k, batch_size, |kmer, k| kmer.decode(k))
+            },
+            packed => {
+                match packed {
+                    PackedCounts::Empty => {},
+                    PackedCounts::Short(counts) => with_n.extend(decoded_kmers(select_kmers(&counts, min_count, top_n), k)),
+                    PackedCounts::Long(counts) => with_n.extend(decoded_kmers(select_kmers(&counts, min_count, top_n), k)),
+                }
+                top_kmers(&mut with_n, top_n);
+                kmer_batches(with_n, k, batch_size, |kmer, _| String::from_utf8_lossy(kmer).into_owned())
+            },
+        }
+    }
+}
+
 #[derive(Debug)]
 struct KmerCountAccumulator {
     k: Option<usize>,
-    counts: HashMap<String, i64>,
+    mode: Option<KmerMode>,
+    counts: KmerCounts,
 }
 
 impl KmerCountAccumulator {
     fn new() -> Self {
         Self {
             k: None,
-            counts: HashMap::new(),
+            mode: None,
+            counts: KmerCounts::empty(),
         }
     }
 
-    fn canonical_kmer(kmer: &[u8]) -> Vec<u8> {
-        let rc = kmer.iter().rev().map(|b| match b {
-            b'A' | b'a' => b'T',
-            b'T' | b't' => b'A',
-            b'C' | b'c' => b'G',
-            b'G' | b'g' => b'C',
-            _ => b'N',
-        }).collect::<Vec<u8>>();
-        if rc.as_slice() < kmer {
-            rc
-        } else {
-            kmer.to_vec()
+    fn set_mode(&mut self, mode: KmerMode) -> Result<()> {
+        match self.mode {
+            None => {
+                self.mode = Some(mode);
+                Ok(())
+            },
+            Some(current) if current != mode => {
+                Err(DataFusionError::Execution("Inconsistent k-mer counting mode in UDAF batch".into()))
+            },
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn set_k(&mut self, k: usize) -> Result<()> {
+        match self.k {
+            None => {
+                if k > MAX_K {
+                    return Err(DataFusionError::Execution(format!("k must not exceed {}", MAX_K)));
+                }
+                self.k = Some(k);
+                self.counts = KmerCounts::with_k(k);
+                Ok(())
+            },
+            Some(current) if current != k => {
+                Err(DataFusionError::Execution("Inconsistent k-mer size in UDAF batch".into()))
+            },
+            Some(_) => Ok(()),
         }
     }
 }
@@ -80,83 +538,72 @@ impl Accumulator for KmerCountAccumulator {
                 continue;
             }
 
-            let seq = seq_array.value(i).as_bytes();
             let k_val = k_array.value(i);
             if k_val <= 0 {
                 return Err(DataFusionError::Execution("k must be greater than 0".into()));
             }
-
             let k_val = k_val as usize;
+            self.set_k(k_val)?;
+            let mode = KmerMode::from_args(values, i)?;
+            self.set_mode(mode)?;
 
-            if self.k.is_none() {
-                self.k = Some(k_val);
-            }
-
-            if self.k.unwrap() != k_val {
-                return Err(DataFusionError::Execution("Inconsistent k-mer size in UDAF batch".into()));
-            }
-
-            if seq.len() < k_val {
-                continue;
-            }
-
-            for j in 0..=(seq.len() - k_val) {
-                let window = &seq[j..j + k_val];
-                if window.iter().all(|&b| matches!(b, b'A' | b'C' | b'G' | b'T' | b'a' | b'c' | b'g' | b't')) {
-                    let canonical = Self::canonical_kmer(window);
-                    let kmer_str = String::from_utf8_lossy(&canonical).to_string();
-                    *self.counts.entry(kmer_str).or_insert(0) += 1;
-                }
-            }
+            self.counts.count_sequence(seq_array.value(i).as_bytes(), k_val, mode);
         }
 
         Ok(())
     }
 
     fn merge_batch(&mut self, states: &[Arc<dyn Array>]) -> Result<()> {
-        let kmers = states[0]
+        let kmer_lists = states[0]
             .as_any()
-            .downcast_ref::<StringArray>()
-            .expect("Expected StringArray for kmer");
+            .downcast_ref::<ListArray>()
+            .expect("Expected ListArray for kmer");
 
-        let counts = states[1]
+        let count_lists = states[1]
             .as_any()
-            .downcast_ref::<Int64Array>()
-            .expect("Expected Int64Array for count");
+            .downcast_ref::<ListArray>()
+            .expect("Expected ListArray for count");
 
-        for i in 0..kmers.len() {
-            if kmers.is_null(i) || counts.is_null(i) {
+        for row in 0..kmer_lists.len() {
+            if kmer_lists.is_null(row) || count_lists.is_null(row) {
                 continue;
             }
-            let kmer = kmers.value(i);
-            let count = counts.value(i);
-            *self.counts.entry(kmer.to_string()).or_insert(0) += count;
-        }
+            let kmer_values = kmer_lists.value(row);
+            let count_values = count_lists.value(row);
+            let kmers = kmer_values
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .expect("Expected StringArray for kmer");
+            let counts = count_values
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .expect("Expected Int64Array for count");
 
+            for i in 0..kmers.len() {
+                if kmers.is_null(i) || counts.is_null(i) {
+                    continue;
+                }
+                let kmer = kmers.value(i);
+                self.set_k(kmer.len())?;
+                self.counts.add(kmer, counts.value(i))?;
+            }
+        }
         Ok(())
     }
 
     fn state(&mut self) -> Result<Vec<ScalarValue>> {
-        Ok(vec![])
+        let (kmers, counts) = self.counts.to_arrays(self.k.unwrap_or(0));
+        Ok(vec![
+            single_list(DataType::Utf8, kmers),
+            single_list(DataType::Int64, counts),
+        ])
     }
 
     fn evaluate(&mut self) -> Result<ScalarValue> {
-        let mut kmer_builder = StringBuilder::new();
-        let mut count_builder = Int64Builder::new();
-
-        for (kmer, count) in &self.counts {
-            kmer_builder.append_value(kmer);
-            count_builder.append_value(*count);
-        }
-
-        let kmer_array = Arc::new(kmer_builder.finish());
-        let count_array = Arc::new(count_builder.finish());
+        let (kmer_array, count_array) = self.counts.to_arrays(self.k.unwrap_or(0));
 
         let struct_array = StructArray::new(
-            Fields::from(vec![
-                Field::new("kmer", DataType::Utf8, false),
-                Field::new("count", DataType::Int64, false),
-            ]),
+            kmer_count_fields(),
             vec![kmer_array, count_array],
             None,
         );
@@ -165,6 +612,77 @@ impl Accumulator for KmerCountAccumulator {
     }
 
     fn size(&self) -> usize {
-        self.counts.len() * (std::mem::size_of::<String>() + std::mem::size_of::<u64>()) + std::mem::size_of::<Self>()
+        let entry_size = match self.counts.packed {
+            PackedCounts::Long(_) => std::mem::size_of::<(u128, i64)>(),
+            _ => std::mem::size_of::<(u64, i64)>(),
+        };
+        let with_n_size = self.counts.with_n.len() * self.k.unwrap_or(0);
+        self.counts.len() * entry_size + with_n_size + std::mem::size_of::<Self>()
+    }
+}
+
+#[derive(Debug, Default)]
+struct SkippedWindowsAccumulator {
+    skipped_windows: i64,
+}
+
+impl Accumulator for SkippedWindowsAccumulator {
+    fn update_batch(&mut self, values: &[Arc<dyn Array>]) -> Result<()> {
+        let seq_array = values[0]
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .expect("Expected StringArray");
+        let k_array = values[1]
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .expect("Expected Int64Array");
+
+        for i in 0..seq_array.len() {
+            if seq_array.is_null(i) || k_array.is_null(i) {
+                continue;
+            }
+
+            let k_val = k_array.value(i);
+            if k_val <= 0 {
+                return Err(DataFusionError::Execution("k must be greater than 0".into()));
+            }
+            let k_val = k_val as usize;
+            if k_val > MAX_K {
+                return Err(DataFusionError::Execution(format!("k must not exceed {}", MAX_K)));
+            }
+            let mode = KmerMode::from_args(values, i)?;
+
+            // the same pass as `kmer_count`, without keeping the k-mers
+            let seq = seq_array.value(i).as_bytes();
+            let skipped = if k_val <= 32 {
+                for_each_kmer::<u64>(seq, k_val, mode, |_, _| {}, |_| {})
+            } else {
+                for_each_kmer::<u128>(seq, k_val, mode, |_, _| {}, |_| {})
+            };
+            self.skipped_windows += skipped as i64;
+        }
+
+        Ok(())
+    }
+
+    fn merge_batch(&mut self, states: &[Arc<dyn Array>]) -> Result<()> {
+        let skipped_windows = states[0]
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .expect("Expected Int64Array for skipped_windows");
+        self.skipped_windows += skipped_windows.iter().flatten().sum::<i64>();
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<ScalarValue>> {
+        Ok(vec![ScalarValue::Int64(Some(self.skipped_windows))])
+    }
+
+    fn evaluate(&mut self) -> Result<ScalarValue> {
+        Ok(ScalarValue::Int64(Some(self.skipped_windows)))
+    }
+
+    fn size(&self) -> usize {
+        std::mem::size_of::<Self>()
     }
 }
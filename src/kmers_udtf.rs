@@ -0,0 +1,318 @@
+use std::any::Any;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use arrow::compute::cast;
+use arrow_array::{Array, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use async_trait::async_trait;
+use datafusion::catalog::{Session, TableFunctionImpl, TableProvider};
+use datafusion::common::{plan_err, DataFusionError, Result, ScalarValue};
+use datafusion::datasource::TableType;
+use datafusion::execution::{SendableRecordBatchStream, TaskContext};
+use datafusion::physical_expr::{EquivalenceProperties, Partitioning};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::{
+    DisplayAs, DisplayFormatType, ExecutionMode, ExecutionPlan, PlanProperties,
+};
+use datafusion::prelude::{ident, Expr, SessionContext};
+use futures_util::future::try_join_all;
+use futures_util::TryStreamExt;
+use log::debug;
+
+use crate::kmers_udaf::{KmerCounts, KmerMode, MAX_K};
+
+/// `kmer_counts(table, column, k [, min_count [, top_n [, strand [, keep_n]]]])` returns
+/// one `kmer, count` row per k-mer of the `column` sequences of `table`, counted as by
+/// `kmer_count` with the `strand` and `keep_n` arguments. k-mers counted fewer than
+/// `min_count` times are skipped, and with `top_n` only the most frequent are returned,
+/// ordered by descending count.
+#[derive(Debug)]
+pub(crate) struct KmerCountsFunction {
+    session: Arc<SessionContext>,
+}
+
+impl KmerCountsFunction {
+    pub(crate) fn new(session: Arc<SessionContext>) -> Self {
+        Self { session }
+    }
+}
+
+fn kmer_counts_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("kmer", DataType::Utf8, false),
+        Field::new("count", DataType::Int64, false),
+    ]))
+}
+
+fn string_arg(args: &[Expr], i: usize, name: &str) -> Result<String> {
+    match args.get(i) {
+        Some(Expr::Literal(ScalarValue::Utf8(Some(value))))
+        | Some(Expr::Literal(ScalarValue::LargeUtf8(Some(value))))
+        | Some(Expr::Literal(ScalarValue::Utf8View(Some(value)))) => Ok(value.clone()),
+        _ => plan_err!("kmer_counts: {} must be a string literal", name),
+    }
+}
+
+/// Missing and `NULL` optional arguments give `None`.
+fn optional_string_arg(args: &[Expr], i: usize, name: &str) -> Result<Option<String>> {
+    match args.get(i) {
+        None => Ok(None),
+        Some(Expr::Literal(value)) if value.is_null() => Ok(None),
+        _ => string_arg(args, i, name).map(Some),
+    }
+}
+
+fn bool_arg(args: &[Expr], i: usize, name: &str) -> Result<Option<bool>> {
+    match args.get(i) {
+        None => Ok(None),
+        Some(Expr::Literal(value)) if value.is_null() => Ok(None),
+        Some(Expr::Literal(ScalarValue::Boolean(Some(value)))) => Ok(Some(*value)),
+        _ => plan_err!("kmer_counts: {} must be a boolean literal", name),
+    }
+}
+
+fn int_arg(args: &[Expr], i: usize, name: &str) -> Result<Option<i64>> {
+    match args.get(i) {
+        None => Ok(None),
+        Some(Expr::Literal(value)) if value.is_null() => Ok(None),
+        Some(Expr::Literal(ScalarValue::Int64(Some(value)))) => Ok(Some(*value)),
+        _ => plan_err!("kmer_counts: {} must be an integer literal", name),
+    }
+}
+
+impl TableFunctionImpl for KmerCountsFunction {
+    fn call(&self, args: &[Expr]) -> Result<Arc<dyn TableProvider>> {
+        if args.len() < 3 || args.len() > 7 {
+            return plan_err!(
+                "kmer_counts expects (table, column, k [, min_count [, top_n [, strand [, keep_n]]]]), got {} arguments",
+                args.len()
+            );
+        }
+        let k = match int_arg(args, 2, "k")? {
+            Some(k) if k > 0 && k as usize <= MAX_K => k as usize,
+            Some(k) if k > 0 => return plan_err!("k must not exceed {}", MAX_K),
+            _ => return plan_err!("k must be greater than 0"),
+        };
+        let top_n = match int_arg(args, 4, "top_n")? {
+            Some(n) if n < 0 => return plan_err!("top_n must not be negative"),
+            n => n.map(|n| n as usize),
+        };
+        let mode = KmerMode::new(
+            optional_string_arg(args, 5, "strand")?.as_deref(),
+            bool_arg(args, 6, "keep_n")?,
+        )?;
+        Ok(Arc::new(KmerCountsProvider {
+            session: Arc::clone(&self.session),
+            table: string_arg(args, 0, "table")?,
+            column: string_arg(args, 1, "column")?,
+            k,
+            min_count: int_arg(args, 3, "min_count")?.unwrap_or(1),
+            top_n,
+            mode,
+            schema: kmer_counts_schema(),
+        }))
+    }
+}
+
+#[derive(Debug)]
+struct KmerCountsProvider {
+    session: Arc<SessionContext>,
+    table: String,
+    column: String,
+    k: usize,
+    min_count: i64,
+    top_n: Option<usize>,
+    mode: KmerMode,
+    schema: SchemaRef,
+}
+
+#[async_trait]
+impl TableProvider for KmerCountsProvider {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        TableType::Temporary
+    }
+
+    async fn scan(
+        &self,
+        _state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        _filters: &[Expr],
+        _limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        let projection = projection
+            .cloned()
+            .unwrap_or_else(|| (0..self.schema.fields().len()).collect());
+        let schema = Arc::new(self.schema.project(&projection)?);
+        Ok(Arc::new(KmerCountsExec {
+            session: Arc::clone(&self.session),
+            table: self.table.clone(),
+            column: self.column.clone(),
+            k: self.k,
+            min_count: self.min_count,
+            top_n: self.top_n,
+            mode: self.mode,
+            projection,
+            cache: PlanProperties::new(
+                EquivalenceProperties::new(schema.clone()),
+                Partitioning::UnknownPartitioning(1),
+                ExecutionMode::Bounded,
+            ),
+            schema,
+        }))
+    }
+}
+
+/// Counts the k-mers of every input partition in parallel and merges the counts into
+/// a single output partition, then emits them in batches.
+struct KmerCountsExec {
+    session: Arc<SessionContext>,
+    table: String,
+    column: String,
+    k: usize,
+    min_count: i64,
+    top_n: Option<usize>,
+    mode: KmerMode,
+    projection: Vec<usize>,
+    schema: SchemaRef,
+    cache: PlanProperties,
+}
+
+impl Debug for KmerCountsExec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "KmerCountsExec: table={}, column={}, k={}",
+            self.table, self.column, self.k
+        )
+    }
+}
+
+impl DisplayAs for KmerCountsExec {
+    fn fmt_as(&self, _t: DisplayFormatType, f: &mut Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "KmerCountsExec: table={}, column={}, k={}, min_count={}, top_n={:?}, mode={:?}",
+            self.table, self.column, self.k, self.min_count, self.top_n, self.mode
+        )
+    }
+}
+
+impl ExecutionPlan for KmerCountsExec {
+    fn name(&self) -> &str {
+        "KmerCountsExec"
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn properties(&self) -> &PlanProperties {
+        &self.cache
+    }
+
+    fn children(&self) -> Vec<&Arc<dyn ExecutionPlan>> {
+        vec![]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        _children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        Ok(self)
+    }
+
+    fn execute(
+        &self,
+        _partition: usize,
+        context: Arc<TaskContext>,
+    ) -> Result<SendableRecordBatchStream> {
+        let fut = get_stream(
+            Arc::clone(&self.session),
+            self.table.clone(),
+            self.column.clone(),
+            self.k,
+            self.min_count,
+            self.top_n,
+            self.mode,
+            self.projection.clone(),
+            self.schema.clone(),
+            context.session_config().batch_size(),
+        );
+        let stream = futures::stream::once(fut).try_flatten();
+        let schema = self.schema.clone();
+        Ok(Box::pin(RecordBatchStreamAdapter::new(schema, stream)))
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn get_stream(
+    session: Arc<SessionContext>,
+    table: String,
+    column: String,
+    k: usize,
+    min_count: i64,
+    top_n: Option<usize>,
+    mode: KmerMode,
+    projection: Vec<usize>,
+    schema: SchemaRef,
+    batch_size: usize,
+) -> Result<SendableRecordBatchStream> {
+    let streams = session
+        .table(&table)
+        .await?
+        .select(vec![ident(&column)])?
+        .execute_stream_partitioned()
+        .await?;
+    debug!(
+        "Counting {}-mers of {} in {} partitions",
+        k,
+        table,
+        streams.len()
+    );
+    let tasks = streams.into_iter().map(|mut stream| {
+        tokio::spawn(async move {
+            let mut counts = KmerCounts::with_k(k);
+            while let Some(batch) = stream.try_next().await? {
+                let sequences = cast(batch.column(0), &DataType::Utf8)?;
+                let sequences = sequences
+                    .as_any()
+                    .downcast_ref::<StringArray>()
+                    .expect("Expected StringArray");
+                for i in 0..sequences.len() {
+                    if sequences.is_valid(i) {
+                        counts.count_sequence(sequences.value(i).as_bytes(), k, mode);
+                    }
+                }
+            }
+            Ok::<KmerCounts, DataFusionError>(counts)
+        })
+    });
+    let mut counts = KmerCounts::with_k(k);
+    for partition in try_join_all(tasks)
+        .await
+        .map_err(|e| DataFusionError::External(Box::new(e)))?
+    {
+        counts.merge(partition?);
+    }
+    let batches = counts.into_batches(k, min_count, top_n, batch_size).map(
+        move |(kmers, counts)| -> Result<RecordBatch> {
+            Ok(
+                RecordBatch::try_new(kmer_counts_schema(), vec![kmers, counts])?
+                    .project(&projection)?,
+            )
+        },
+    );
+    Ok(Box::pin(RecordBatchStreamAdapter::new(
+        schema,
+        futures::stream::iter(batches),
+    )))
+}
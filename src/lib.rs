@@ -1,20 +1,40 @@
+mod alignment;
+mod bbi;
+mod bed;
 mod context;
+mod faidx;
+mod gene_model;
+mod gff;
+mod kmers_sketch;
+mod kmers_udtf;
 mod operation;
 mod option;
+mod paired;
+mod pairs;
+mod qc;
+mod quality;
 mod query;
 mod scan;
+mod sequence;
+mod sink;
+mod storage;
 mod streaming;
+mod trim;
 mod udtf;
 mod utils;
+mod vcf;
 pub mod kmers_udaf;
 
 use std::string::ToString;
 use std::sync::{Arc, Mutex};
 use datafusion::arrow::ffi_stream::ArrowArrayStreamReader;
 use datafusion::arrow::pyarrow::PyArrowType;
+use datafusion::arrow::record_batch::RecordBatchReader;
+use datafusion::common::DataFusionError;
 use datafusion::datasource::MemTable;
 use datafusion_python::dataframe::PyDataFrame;
 use datafusion_vcf::storage::VcfReader;
+use futures_util::StreamExt;
 use log::{debug, error, info};
 use polars_lazy::prelude::{LazyFrame, ScanArgsAnonymous};
 use polars_python::error::PyPolarsErr;
@@ -22,11 +42,22 @@ use polars_python::lazyframe::PyLazyFrame;
 use pyo3::prelude::*;
 use tokio::runtime::Runtime;
 use crate::context::PyBioSessionContext;
+use crate::gene_model::register_gene_model;
 use crate::operation::do_range_operation;
 use crate::option::{
-    BioTable, FilterOp, InputFormat, RangeOp, RangeOptions, ReadOptions, VcfReadOptions,
+    BedReadOptions, BigWigReadOptions, BioTable, CsvReadOptions, FilterOp, GffReadOptions, InputFormat, OutputFormat, RangeOp, RangeOptions,
+    ReadOptions, VcfReadOptions,
+};
+use crate::paired::register_paired_fastq;
+use crate::qc::fastq_qc;
+use crate::scan::{
+    detect_input_format, expand_paths, get_input_format, get_sample_name, maybe_register_table,
+    register_frame, register_multi_table, register_table,
+};
+use crate::sink::{
+    coordinate_sorted_query, distinct_contigs, needs_vcf_contigs, read_bam_header, track_query,
+    write_bam, write_batches, write_track,
 };
-use crate::scan::{maybe_register_table, register_frame, register_table};
 use crate::streaming::RangeOperationScan;
 use crate::utils::convert_arrow_rb_schema_to_polars_df_schema;
 
@@ -93,14 +124,14 @@ fn range_operation_scan(
         read_options1,
         ctx,
         &rt,
-    );
+    )?;
     let right_table = maybe_register_table(
         df_path_or_table2,
         &RIGHT_TABLE.to_string(),
         read_options2,
         ctx,
         &rt,
-    );
+    )?;
     match limit {
         Some(l) => Ok(PyDataFrame::new(
             do_range_operation(ctx, &rt, range_options, left_table, right_table)
@@ -139,14 +170,14 @@ fn stream_range_operation_scan(
             read_options1,
             ctx,
             &rt,
-        );
+        )?;
         let right_table = maybe_register_table(
             df_path_or_table2,
             &RIGHT_TABLE.to_string(),
             read_options2,
             ctx,
             &rt,
-        );
+        )?;
 
         let df = do_range_operation(ctx, &rt, range_options, left_table, right_table);
         let schema = df.schema().as_arrow();
@@ -210,7 +241,7 @@ fn py_register_table(
             &table_name,
             input_format.clone(),
             read_options,
-        ));
+        ))?;
         match rt.block_on(ctx.session.table(&table_name)) {
             Ok(table) => {
                 let schema = table.schema().as_arrow();
@@ -231,6 +262,19 @@ fn py_register_table(
     })
 }
 
+#[pyfunction]
+#[pyo3(signature = (path, py_ctx=None))]
+fn py_get_input_format(
+    py: Python<'_>,
+    path: String,
+    py_ctx: Option<&PyBioSessionContext>,
+) -> PyResult<InputFormat> {
+    py.allow_threads(|| match py_ctx {
+        Some(py_ctx) => Runtime::new()?.block_on(detect_input_format(&py_ctx.ctx, &path)),
+        None => get_input_format(&path),
+    })
+}
+
 #[pyfunction]
 #[pyo3(signature = (py_ctx, sql_text))]
 fn py_read_sql(
@@ -402,6 +446,242 @@ fn py_from_polars(
     })
 }
 
+#[pyfunction]
+#[pyo3(signature = (py_ctx, name))]
+fn py_deregister_table(py_ctx: &PyBioSessionContext, name: String) -> PyResult<()> {
+    py_ctx.ctx.session.deregister_table(&name)?;
+    Ok(())
+}
+
+#[pyfunction]
+#[pyo3(signature = (py_ctx, table_name, path, output_format))]
+fn py_write_table(
+    py: Python<'_>,
+    py_ctx: &PyBioSessionContext,
+    table_name: String,
+    path: String,
+    output_format: OutputFormat,
+) -> PyResult<usize> {
+    py.allow_threads(|| {
+        let rt = Runtime::new()?;
+        let ctx = &py_ctx.ctx;
+        let df = rt.block_on(ctx.session.table(&table_name))?;
+        let schema = Arc::new(df.schema().as_arrow().clone());
+        let contigs = if needs_vcf_contigs(&schema, &output_format) {
+            let mut stream = rt.block_on(df.clone().select_columns(&["chrom"])?.execute_stream())?;
+            distinct_contigs(std::iter::from_fn(|| rt.block_on(stream.next())))?
+        } else {
+            vec![]
+        };
+        let mut stream = rt.block_on(df.execute_stream())?;
+        let batches = std::iter::from_fn(|| rt.block_on(stream.next()));
+        let rows = write_batches(schema, batches, &path, output_format, &contigs)?;
+        info!("Table: {} written to: {}", table_name, path);
+        Ok(rows)
+    })
+}
+
+#[pyfunction]
+#[pyo3(signature = (df, path, output_format, contigs=vec![]))]
+fn py_write_frame(
+    py: Python<'_>,
+    df: PyArrowType<ArrowArrayStreamReader>,
+    path: String,
+    output_format: OutputFormat,
+    contigs: Vec<String>,
+) -> PyResult<usize> {
+    py.allow_threads(|| {
+        let reader = df.0;
+        let schema = reader.schema();
+        let batches = reader.map(|rb| rb.map_err(DataFusionError::from));
+        Ok(write_batches(
+            schema,
+            batches,
+            &path,
+            output_format,
+            &contigs,
+        )?)
+    })
+}
+
+#[pyfunction]
+#[pyo3(signature = (py_ctx, table_name, path, header_path, sort=false, index=false))]
+fn py_write_bam(
+    py: Python<'_>,
+    py_ctx: &PyBioSessionContext,
+    table_name: String,
+    path: String,
+    header_path: String,
+    sort: bool,
+    index: bool,
+) -> PyResult<usize> {
+    py.allow_threads(|| {
+        let rt = Runtime::new()?;
+        let ctx = &py_ctx.ctx;
+        let header = read_bam_header(&header_path)?;
+        // BAI indexing requires coordinate-sorted records
+        let index_table = format!("{}_reference_index", table_name);
+        let query = if sort || index {
+            coordinate_sorted_query(ctx, &table_name, &index_table, &header)?
+        } else {
+            format!("SELECT * FROM {}", table_name)
+        };
+        debug!("Query: {}", query);
+        let df = rt.block_on(ctx.session.sql(&query))?;
+        let mut stream = rt.block_on(df.execute_stream())?;
+        let batches = std::iter::from_fn(|| rt.block_on(stream.next()));
+        let rows = write_bam(batches, &path, &header, index);
+        ctx.session.deregister_table(&index_table)?;
+        let rows = rows?;
+        info!("Table: {} written to: {}", table_name, path);
+        Ok(rows)
+    })
+}
+
+#[pyfunction]
+#[pyo3(signature = (py_ctx, table_name, path, chrom_sizes, columns, value_column=None, output_format=OutputFormat::BigWig))]
+fn py_write_track(
+    py: Python<'_>,
+    py_ctx: &PyBioSessionContext,
+    table_name: String,
+    path: String,
+    chrom_sizes: Vec<(String, u32)>,
+    columns: Vec<String>,
+    value_column: Option<String>,
+    output_format: OutputFormat,
+) -> PyResult<usize> {
+    py.allow_threads(|| {
+        let rt = Runtime::new()?;
+        let ctx = &py_ctx.ctx;
+        // coverage and count results append the value as the last column
+        let value_column = match value_column {
+            Some(value_column) => value_column,
+            None => {
+                let df = rt.block_on(ctx.session.table(&table_name))?;
+                let fields = df.schema().fields();
+                fields[fields.len() - 1].name().clone()
+            },
+        };
+        let query = track_query(&table_name, &columns, &value_column);
+        debug!("Query: {}", query);
+        let df = rt.block_on(ctx.session.sql(&query))?;
+        let mut stream = rt.block_on(df.execute_stream())?;
+        let batches = std::iter::from_fn(|| rt.block_on(stream.next()));
+        let rows = write_track(batches, &path, chrom_sizes, output_format)?;
+        info!("Table: {} written to: {}", table_name, path);
+        Ok(rows)
+    })
+}
+
+#[pyfunction]
+#[pyo3(signature = (py_ctx, paths, name, input_format=None, read_options=None))]
+fn py_register_tables(
+    py: Python<'_>,
+    py_ctx: &PyBioSessionContext,
+    paths: Vec<String>,
+    name: String,
+    input_format: Option<InputFormat>,
+    read_options: Option<ReadOptions>,
+) -> PyResult<BioTable> {
+    py.allow_threads(|| {
+        let rt = Runtime::new()?;
+        let ctx = &py_ctx.ctx;
+        let paths = expand_paths(paths)?;
+        let input_format = match input_format {
+            Some(input_format) => input_format,
+            None => rt.block_on(detect_input_format(ctx, &paths[0]))?,
+        };
+        rt.block_on(register_multi_table(
+            ctx,
+            &paths,
+            &name,
+            input_format.clone(),
+            read_options,
+        ))?;
+        info!("Table: {} registered for {} files", name, paths.len());
+        Ok(BioTable {
+            name,
+            format: input_format,
+            path: paths.join(","),
+        })
+    })
+}
+
+#[pyfunction]
+#[pyo3(signature = (py_ctx, r1, r2=None, name=None))]
+fn py_register_paired_fastq(
+    py: Python<'_>,
+    py_ctx: &PyBioSessionContext,
+    r1: String,
+    r2: Option<String>,
+    name: Option<String>,
+) -> PyResult<String> {
+    py.allow_threads(|| {
+        let ctx = &py_ctx.ctx;
+        let table_name = match name {
+            Some(name) => name,
+            None => get_sample_name(&r1)
+                .to_lowercase()
+                .replace(".", "_")
+                .replace("-", "_"),
+        };
+        register_paired_fastq(ctx, &r1, r2.as_deref(), &table_name)?;
+        info!("Paired FASTQ table: {} registered for path: {}", table_name, r1);
+        Ok(table_name)
+    })
+}
+
+#[pyfunction]
+#[pyo3(signature = (py_ctx, table_name, prefix=None, upstream=2000, downstream=200))]
+fn py_register_gene_model(
+    py: Python<'_>,
+    py_ctx: &PyBioSessionContext,
+    table_name: String,
+    prefix: Option<String>,
+    upstream: i64,
+    downstream: i64,
+) -> PyResult<Vec<String>> {
+    py.allow_threads(|| {
+        let rt = Runtime::new()?;
+        let ctx = &py_ctx.ctx;
+        let prefix = prefix.unwrap_or(table_name.clone());
+        Ok(rt.block_on(register_gene_model(
+            ctx,
+            &table_name,
+            &prefix,
+            upstream,
+            downstream,
+        ))?)
+    })
+}
+
+#[pyfunction]
+#[pyo3(signature = (py_ctx, table_name, sequence_column="sequence".to_string(), quality_column="quality_scores".to_string(), phred_offset=33))]
+fn py_fastq_qc(
+    py: Python<'_>,
+    py_ctx: &PyBioSessionContext,
+    table_name: String,
+    sequence_column: String,
+    quality_column: String,
+    phred_offset: u8,
+) -> PyResult<Vec<(String, PyDataFrame)>> {
+    py.allow_threads(|| {
+        let rt = Runtime::new()?;
+        let ctx = &py_ctx.ctx;
+        let modules = rt.block_on(fastq_qc(
+            ctx,
+            &table_name,
+            &sequence_column,
+            &quality_column,
+            phred_offset,
+        ))?;
+        modules
+            .into_iter()
+            .map(|(name, batch)| Ok((name, PyDataFrame::new(ctx.session.read_batch(batch)?))))
+            .collect()
+    })
+}
+
 #[pymodule]
 fn polars_bio(_py: Python, m: &Bound<PyModule>) -> PyResult<()> {
     pyo3_log::init();
@@ -409,6 +689,8 @@ fn polars_bio(_py: Python, m: &Bound<PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(range_operation_scan, m)?)?;
     m.add_function(wrap_pyfunction!(stream_range_operation_scan, m)?)?;
     m.add_function(wrap_pyfunction!(py_register_table, m)?)?;
+    m.add_function(wrap_pyfunction!(py_register_tables, m)?)?;
+    m.add_function(wrap_pyfunction!(py_get_input_format, m)?)?;
     m.add_function(wrap_pyfunction!(py_read_table, m)?)?;
     m.add_function(wrap_pyfunction!(py_read_sql, m)?)?;
     m.add_function(wrap_pyfunction!(py_scan_sql, m)?)?;
@@ -416,13 +698,26 @@ fn polars_bio(_py: Python, m: &Bound<PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(py_describe_vcf, m)?)?;
     m.add_function(wrap_pyfunction!(py_register_view, m)?)?;
     m.add_function(wrap_pyfunction!(py_from_polars, m)?)?;
+    m.add_function(wrap_pyfunction!(py_deregister_table, m)?)?;
+    m.add_function(wrap_pyfunction!(py_write_table, m)?)?;
+    m.add_function(wrap_pyfunction!(py_write_frame, m)?)?;
+    m.add_function(wrap_pyfunction!(py_write_bam, m)?)?;
+    m.add_function(wrap_pyfunction!(py_write_track, m)?)?;
+    m.add_function(wrap_pyfunction!(py_register_gene_model, m)?)?;
+    m.add_function(wrap_pyfunction!(py_register_paired_fastq, m)?)?;
+    m.add_function(wrap_pyfunction!(py_fastq_qc, m)?)?;
     // m.add_function(wrap_pyfunction!(unary_operation_scan, m)?)?;
     m.add_class::<PyBioSessionContext>()?;
     m.add_class::<FilterOp>()?;
     m.add_class::<RangeOp>()?;
     m.add_class::<RangeOptions>()?;
     m.add_class::<InputFormat>()?;
+    m.add_class::<OutputFormat>()?;
     m.add_class::<ReadOptions>()?;
     m.add_class::<VcfReadOptions>()?;
+    m.add_class::<CsvReadOptions>()?;
+    m.add_class::<BedReadOptions>()?;
+    m.add_class::<GffReadOptions>()?;
+    m.add_class::<BigWigReadOptions>()?;
     Ok(())
 }
@@ -8,7 +8,7 @@ use tokio::runtime::Runtime;
 
 use crate::context::set_option_internal;
 use crate::option::{FilterOp, RangeOp, RangeOptions};
-use crate::query::{count_overlaps_query, nearest_query, overlap_query};
+use crate::query::{count_overlaps_query, nearest_query, overlap_query, paired_overlap_query};
 use crate::udtf::CountOverlapsProvider;
 use crate::utils::default_cols_to_string;
 use crate::DEFAULT_COLUMN_NAMES;
@@ -70,6 +70,12 @@ pub(crate) fn do_range_operation(
     );
     match range_options.range_op {
         RangeOp::Overlap => rt.block_on(do_overlap(ctx, range_options, left_table, right_table)),
+        RangeOp::PairedOverlap => rt.block_on(do_paired_overlap(
+            ctx,
+            range_options,
+            left_table,
+            right_table,
+        )),
         RangeOp::Nearest => {
             set_option_internal(ctx, "sequila.interval_join_algorithm", "coitreesnearest");
             rt.block_on(do_nearest(ctx, range_options, left_table, right_table))
@@ -134,6 +140,25 @@ async fn do_overlap(
     ctx.sql(&query).await.unwrap()
 }
 
+async fn do_paired_overlap(
+    ctx: &ExonSession,
+    range_opts: RangeOptions,
+    left_table: String,
+    right_table: String,
+) -> datafusion::dataframe::DataFrame {
+    let query = prepare_query(
+        paired_overlap_query,
+        range_opts,
+        ctx,
+        left_table,
+        right_table,
+    )
+    .await
+    .to_string();
+    debug!("Query: {}", query);
+    ctx.sql(&query).await.unwrap()
+}
+
 async fn do_count_overlaps(
     ctx: &ExonSession,
     range_opts: RangeOptions,
@@ -1,6 +1,10 @@
 use std::fmt;
+use std::sync::Arc;
 
-use pyo3::{pyclass, pymethods};
+use arrow::pyarrow::PyArrowType;
+use arrow_schema::Schema;
+use pyo3::exceptions::PyValueError;
+use pyo3::{pyclass, pymethods, PyResult};
 
 #[pyclass(name = "RangeOptions")]
 #[derive(Clone, Debug)]
@@ -37,8 +41,17 @@ impl RangeOptions {
         on_cols: Option<Vec<String>>,
         overlap_alg: Option<String>,
         streaming: Option<bool>,
-    ) -> Self {
-        RangeOptions {
+    ) -> PyResult<Self> {
+        if range_op == RangeOp::PairedOverlap {
+            for columns in [&columns_1, &columns_2] {
+                if !columns.as_ref().is_some_and(|c| c.len() == 6) {
+                    return Err(PyValueError::new_err(
+                        "Paired overlap requires 6 interval columns per table: chrom, start and end of both ends",
+                    ));
+                }
+            }
+        }
+        Ok(RangeOptions {
             range_op,
             filter_op,
             suffixes,
@@ -47,7 +60,7 @@ impl RangeOptions {
             on_cols,
             overlap_alg,
             streaming,
-        }
+        })
     }
 }
 impl std::fmt::Display for RangeOptions {
@@ -73,6 +86,7 @@ pub enum RangeOp {
     Coverage = 4,
     CountOverlaps = 5,
     CountOverlapsNaive = 6,
+    PairedOverlap = 7,
 }
 
 impl fmt::Display for RangeOp {
@@ -85,6 +99,7 @@ impl fmt::Display for RangeOp {
             RangeOp::Coverage => write!(f, "Coverage"),
             RangeOp::CountOverlaps => write!(f, "Count overlaps"),
             RangeOp::CountOverlapsNaive => write!(f, "Count overlaps naive"),
+            RangeOp::PairedOverlap => write!(f, "Paired overlap"),
         }
     }
 }
@@ -104,6 +119,40 @@ pub enum InputFormat {
     Bed,
     Gff,
     Gtf,
+    NarrowPeak,
+    BroadPeak,
+    BedGraph,
+    BigWig,
+    BigBed,
+    Paf,
+    Sam,
+    Pairs,
+    Bedpe,
+}
+
+#[pyclass(eq, eq_int)]
+#[derive(Clone, PartialEq, Debug)]
+pub enum OutputFormat {
+    Vcf,
+    Bed,
+    BedGraph,
+    Gff,
+    Fastq,
+    BigWig,
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            OutputFormat::Vcf => "VCF",
+            OutputFormat::Bed => "BED",
+            OutputFormat::BedGraph => "BEDGRAPH",
+            OutputFormat::Gff => "GFF",
+            OutputFormat::Fastq => "FASTQ",
+            OutputFormat::BigWig => "BIGWIG",
+        };
+        write!(f, "{}", text)
+    }
 }
 
 #[pyclass(eq, get_all)]
@@ -129,6 +178,15 @@ impl fmt::Display for InputFormat {
             InputFormat::IndexedBam => "INDEXED_BAM",
             InputFormat::IndexedVcf => "INDEXED_VCF",
             InputFormat::Cram => "CRAM",
+            InputFormat::NarrowPeak => "NARROWPEAK",
+            InputFormat::BroadPeak => "BROADPEAK",
+            InputFormat::BedGraph => "BEDGRAPH",
+            InputFormat::BigWig => "BIGWIG",
+            InputFormat::BigBed => "BIGBED",
+            InputFormat::Paf => "PAF",
+            InputFormat::Sam => "SAM",
+            InputFormat::Pairs => "PAIRS",
+            InputFormat::Bedpe => "BEDPE",
         };
         write!(f, "{}", text)
     }
@@ -138,14 +196,34 @@ impl fmt::Display for InputFormat {
 pub struct ReadOptions {
     #[pyo3(get, set)]
     pub vcf_read_options: Option<VcfReadOptions>,
+    #[pyo3(get, set)]
+    pub csv_read_options: Option<CsvReadOptions>,
+    #[pyo3(get, set)]
+    pub bed_read_options: Option<BedReadOptions>,
+    #[pyo3(get, set)]
+    pub gff_read_options: Option<GffReadOptions>,
+    #[pyo3(get, set)]
+    pub bigwig_read_options: Option<BigWigReadOptions>,
 }
 
 #[pymethods]
 impl ReadOptions {
     #[new]
-    #[pyo3(signature = (vcf_read_options=None))]
-    pub fn new(vcf_read_options: Option<VcfReadOptions>) -> Self {
-        ReadOptions { vcf_read_options }
+    #[pyo3(signature = (vcf_read_options=None, csv_read_options=None, bed_read_options=None, gff_read_options=None, bigwig_read_options=None))]
+    pub fn new(
+        vcf_read_options: Option<VcfReadOptions>,
+        csv_read_options: Option<CsvReadOptions>,
+        bed_read_options: Option<BedReadOptions>,
+        gff_read_options: Option<GffReadOptions>,
+        bigwig_read_options: Option<BigWigReadOptions>,
+    ) -> Self {
+        ReadOptions {
+            vcf_read_options,
+            csv_read_options,
+            bed_read_options,
+            gff_read_options,
+            bigwig_read_options,
+        }
     }
 }
 
@@ -186,9 +264,134 @@ impl VcfReadOptions {
         VcfReadOptions {
             info_fields: None,
             format_fields: None,
-            thread_num: Some(1),
+            thread_num: None,
             chunk_size: Some(64),
             concurrent_fetches: Some(8),
         }
     }
 }
+
+#[pyclass(name = "CsvReadOptions")]
+#[derive(Clone, Debug)]
+pub struct CsvReadOptions {
+    #[pyo3(get, set)]
+    pub delimiter: Option<String>,
+    #[pyo3(get, set)]
+    pub has_header: bool,
+    #[pyo3(get, set)]
+    pub comment: Option<String>,
+    #[pyo3(get, set)]
+    pub null_values: Option<Vec<String>>,
+    #[pyo3(get, set)]
+    pub compression: Option<String>,
+    pub schema: Option<Arc<Schema>>,
+}
+
+#[pymethods]
+impl CsvReadOptions {
+    #[new]
+    #[pyo3(signature = (delimiter=None, has_header=true, comment=None, schema=None, null_values=None, compression=None))]
+    pub fn new(
+        delimiter: Option<String>,
+        has_header: bool,
+        comment: Option<String>,
+        schema: Option<PyArrowType<Schema>>,
+        null_values: Option<Vec<String>>,
+        compression: Option<String>,
+    ) -> PyResult<Self> {
+        for (name, value) in [("delimiter", &delimiter), ("comment", &comment)] {
+            if let Some(v) = value {
+                if v.len() != 1 {
+                    return Err(PyValueError::new_err(format!(
+                        "{} must be a single character, got: {:?}",
+                        name, v
+                    )));
+                }
+            }
+        }
+        Ok(CsvReadOptions {
+            delimiter,
+            has_header,
+            comment,
+            null_values,
+            compression,
+            schema: schema.map(|s| Arc::new(s.0)),
+        })
+    }
+    #[staticmethod]
+    pub fn default() -> Self {
+        CsvReadOptions {
+            delimiter: None,
+            has_header: true,
+            comment: None,
+            null_values: None,
+            compression: None,
+            schema: None,
+        }
+    }
+}
+
+#[pyclass(name = "BedReadOptions")]
+#[derive(Clone, Debug)]
+pub struct BedReadOptions {
+    #[pyo3(get, set)]
+    pub bed_type: Option<String>,
+    #[pyo3(get, set)]
+    pub explode_blocks: bool,
+}
+
+#[pymethods]
+impl BedReadOptions {
+    #[new]
+    #[pyo3(signature = (bed_type=None, explode_blocks=false))]
+    pub fn new(bed_type: Option<String>, explode_blocks: bool) -> Self {
+        BedReadOptions {
+            bed_type,
+            explode_blocks,
+        }
+    }
+    #[staticmethod]
+    pub fn default() -> Self {
+        BedReadOptions {
+            bed_type: None,
+            explode_blocks: false,
+        }
+    }
+}
+
+#[pyclass(name = "GffReadOptions")]
+#[derive(Clone, Debug)]
+pub struct GffReadOptions {
+    #[pyo3(get, set)]
+    pub attr_fields: Option<Vec<String>>,
+    #[pyo3(get, set)]
+    pub resolve_parents: bool,
+}
+
+#[pymethods]
+impl GffReadOptions {
+    #[new]
+    #[pyo3(signature = (attr_fields=None, resolve_parents=false))]
+    pub fn new(attr_fields: Option<Vec<String>>, resolve_parents: bool) -> Self {
+        GffReadOptions {
+            attr_fields,
+            resolve_parents,
+        }
+    }
+}
+
+#[pyclass(name = "BigWigReadOptions")]
+#[derive(Clone, Debug)]
+pub struct BigWigReadOptions {
+    #[pyo3(get, set)]
+    pub zoom_level: Option<usize>,
+}
+
+#[pymethods]
+impl BigWigReadOptions {
+    #[new]
+    #[pyo3(signature = (zoom_level=None))]
+    pub fn new(zoom_level: Option<usize>) -> Self {
+        BigWigReadOptions { zoom_level }
+    }
+}
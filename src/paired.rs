@@ -0,0 +1,276 @@
+use std::fmt::{Debug, Formatter};
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+
+use arrow::array::{ArrayRef, RecordBatch, StringBuilder};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use datafusion::common::{DataFusionError, Result};
+use datafusion::datasource::streaming::StreamingTable;
+use datafusion::execution::{SendableRecordBatchStream, TaskContext};
+use datafusion::physical_plan::stream::RecordBatchStreamAdapter;
+use datafusion::physical_plan::streaming::PartitionStream;
+use exon::ExonSession;
+
+use crate::scan::open_decompressed;
+
+const BATCH_SIZE: usize = 8192;
+const BATCH_CHANNEL_CAPACITY: usize = 2;
+const PAIRED_FASTQ_COLUMNS: [&str; 5] = ["name", "seq1", "qual1", "seq2", "qual2"];
+const MATE_SUFFIXES: [&str; 2] = ["/1", "/2"];
+
+/// Registers R1/R2 FASTQ files, or a single interleaved FASTQ file if `r2` is `None`,
+/// as one table with a row per read pair. Read names are compared without their
+/// `/1` and `/2` suffixes, which must be on the first and second mate respectively,
+/// and reading fails if they do not pair up.
+pub(crate) fn register_paired_fastq(
+    ctx: &ExonSession,
+    r1: &str,
+    r2: Option<&str>,
+    table_name: &str,
+) -> Result<()> {
+    let schema = Arc::new(Schema::new(
+        PAIRED_FASTQ_COLUMNS
+            .iter()
+            .map(|name| Field::new(*name, DataType::Utf8, true))
+            .collect::<Vec<Field>>(),
+    ));
+    let partition = PairedFastqPartition {
+        r1: r1.to_string(),
+        r2: r2.map(str::to_string),
+        schema: schema.clone(),
+    };
+    let table = StreamingTable::try_new(schema, vec![Arc::new(partition)])?;
+    ctx.session.deregister_table(table_name)?;
+    ctx.session.register_table(table_name, Arc::new(table))?;
+    Ok(())
+}
+
+/// Common name of a read pair, `None` if the mates do not pair up. Names must be equal,
+/// or end in `/1` on the first and `/2` on the second mate.
+fn pair_name<'a>(first: &'a str, mate: &str) -> Option<&'a str> {
+    let [suffix1, suffix2] = MATE_SUFFIXES;
+    match (first.strip_suffix(suffix1), mate.strip_suffix(suffix2)) {
+        (Some(name), Some(mate)) if name == mate => Some(name),
+        _ if first == mate && !MATE_SUFFIXES.iter().any(|s| first.ends_with(s)) => Some(first),
+        _ => None,
+    }
+}
+
+struct PairedFastqPartition {
+    r1: String,
+    r2: Option<String>,
+    schema: SchemaRef,
+}
+
+impl Debug for PairedFastqPartition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "PairedFastqPartition {{ r1: {}, r2: {:?} }}",
+            self.r1, self.r2
+        )
+    }
+}
+
+impl PartitionStream for PairedFastqPartition {
+    fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    /// Reading and decompression are blocking, so batches are read on a separate thread
+    /// and received through a bounded channel. The thread stops once the stream is dropped.
+    fn execute(&self, _ctx: Arc<TaskContext>) -> SendableRecordBatchStream {
+        let (tx, rx) = tokio::sync::mpsc::channel(BATCH_CHANNEL_CAPACITY);
+        let (r1, r2, schema) = (self.r1.clone(), self.r2.clone(), self.schema.clone());
+        std::thread::spawn(move || {
+            let readers = FastqReader::open(&r1).and_then(|r1| {
+                let r2 = r2.as_deref().map(FastqReader::open).transpose()?;
+                Ok((r1, r2))
+            });
+            let batches: Box<dyn Iterator<Item = Result<RecordBatch>>> = match readers {
+                Ok((r1, r2)) => Box::new(PairedFastqBatchIterator {
+                    r1,
+                    r2,
+                    pairs: 0,
+                    done: false,
+                    schema,
+                }),
+                Err(e) => Box::new(std::iter::once(Err(e))),
+            };
+            for batch in batches {
+                if tx.blocking_send(batch).is_err() {
+                    break;
+                }
+            }
+        });
+        let batches = futures::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|batch| (batch, rx))
+        });
+        Box::pin(RecordBatchStreamAdapter::new(self.schema.clone(), batches))
+    }
+}
+
+#[derive(Default)]
+struct FastqRecord {
+    /// The first word of the header line, without the leading `@`
+    name: String,
+    sequence: String,
+    quality: String,
+}
+
+struct FastqReader {
+    path: String,
+    reader: Box<dyn BufRead + Send>,
+    line: String,
+}
+
+impl FastqReader {
+    fn open(path: &str) -> Result<Self> {
+        Ok(FastqReader {
+            path: path.to_string(),
+            reader: Box::new(BufReader::new(open_decompressed(path, 1)?)),
+            line: String::new(),
+        })
+    }
+
+    /// Reads the next line into `self.line` without the line terminator, `false` at EOF.
+    fn read_line(&mut self) -> Result<bool> {
+        self.line.clear();
+        if self.reader.read_line(&mut self.line)? == 0 {
+            return Ok(false);
+        }
+        let len = self.line.trim_end_matches(['\n', '\r']).len();
+        self.line.truncate(len);
+        Ok(true)
+    }
+
+    fn invalid(&self, message: &str) -> DataFusionError {
+        DataFusionError::Execution(format!(
+            "Invalid FASTQ record in {}: {} ({})",
+            self.path, message, self.line
+        ))
+    }
+
+    /// Reads the next 4-line record, `false` at EOF.
+    fn read_record(&mut self, record: &mut FastqRecord) -> Result<bool> {
+        loop {
+            if !self.read_line()? {
+                return Ok(false);
+            }
+            if !self.line.is_empty() {
+                break;
+            }
+        }
+        let Some(header) = self.line.strip_prefix('@') else {
+            return Err(self.invalid("expected a header line starting with '@'"));
+        };
+        record.name.clear();
+        record
+            .name
+            .push_str(header.split_whitespace().next().unwrap_or_default());
+        if !self.read_line()? {
+            return Err(self.invalid("missing sequence line"));
+        }
+        std::mem::swap(&mut record.sequence, &mut self.line);
+        if !self.read_line()? || !self.line.starts_with('+') {
+            return Err(self.invalid("expected a separator line starting with '+'"));
+        }
+        if !self.read_line()? {
+            return Err(self.invalid("missing quality line"));
+        }
+        std::mem::swap(&mut record.quality, &mut self.line);
+        if record.sequence.len() != record.quality.len() {
+            return Err(DataFusionError::Execution(format!(
+                "Invalid FASTQ record in {}: sequence and quality lengths differ for read {}",
+                self.path, record.name
+            )));
+        }
+        Ok(true)
+    }
+}
+
+struct PairedFastqBatchIterator {
+    r1: FastqReader,
+    /// `None` for interleaved input, mates are then read from `r1`
+    r2: Option<FastqReader>,
+    pairs: usize,
+    done: bool,
+    schema: SchemaRef,
+}
+
+impl PairedFastqBatchIterator {
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
+        let mut columns = (0..PAIRED_FASTQ_COLUMNS.len())
+            .map(|_| StringBuilder::new())
+            .collect::<Vec<StringBuilder>>();
+        let (mut first, mut mate) = (FastqRecord::default(), FastqRecord::default());
+        let mut len = 0;
+        while len < BATCH_SIZE {
+            let has_first = self.r1.read_record(&mut first)?;
+            let has_mate = match self.r2.as_mut() {
+                Some(r2) => r2.read_record(&mut mate)?,
+                None if has_first => self.r1.read_record(&mut mate)?,
+                None => false,
+            };
+            match (has_first, has_mate, &self.r2) {
+                (false, false, _) => break,
+                (true, false, None) => {
+                    return Err(DataFusionError::Execution(format!(
+                        "Interleaved FASTQ file {} has an unpaired last read {}",
+                        self.r1.path, first.name
+                    )))
+                },
+                (true, false, Some(r2)) | (false, true, Some(r2)) => {
+                    let (longer, shorter) = if has_first {
+                        (&self.r1.path, &r2.path)
+                    } else {
+                        (&r2.path, &self.r1.path)
+                    };
+                    return Err(DataFusionError::Execution(format!(
+                        "Paired FASTQ files are desynchronized: {} has more reads than {} after {} pairs",
+                        longer, shorter, self.pairs
+                    )));
+                },
+                _ => {},
+            }
+            let Some(name) = pair_name(&first.name, &mate.name) else {
+                return Err(DataFusionError::Execution(format!(
+                    "Paired FASTQ files are desynchronized: read names {} and {} of pair {} do not match",
+                    first.name,
+                    mate.name,
+                    self.pairs + 1
+                )));
+            };
+            columns[0].append_value(name);
+            columns[1].append_value(&first.sequence);
+            columns[2].append_value(&first.quality);
+            columns[3].append_value(&mate.sequence);
+            columns[4].append_value(&mate.quality);
+            self.pairs += 1;
+            len += 1;
+        }
+        if len == 0 {
+            return Ok(None);
+        }
+        let arrays = columns
+            .iter_mut()
+            .map(|builder| Arc::new(builder.finish()) as ArrayRef)
+            .collect::<Vec<ArrayRef>>();
+        Ok(Some(RecordBatch::try_new(self.schema.clone(), arrays)?))
+    }
+}
+
+impl Iterator for PairedFastqBatchIterator {
+    type Item = Result<RecordBatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let batch = self.next_batch();
+        if !matches!(batch, Ok(Some(_))) {
+            self.done = true;
+        }
+        batch.transpose()
+    }
+}
@@ -0,0 +1,137 @@
+use std::io::BufRead;
+
+use arrow_schema::{DataType, Field, Schema};
+use datafusion::common::Result;
+use datafusion::prelude::CsvReadOptions;
+use exon::ExonSession;
+use log::debug;
+
+use crate::bed::{first_data_line, open_text_file};
+use crate::option::InputFormat;
+use crate::scan::{get_compression_type, get_file_extension};
+
+/// Mandatory 4DN pairs columns, used when the file has no `#columns:` header line.
+const PAIRS_COLUMNS: [&str; 7] = [
+    "readID", "chrom1", "pos1", "chrom2", "pos2", "strand1", "strand2",
+];
+const PAIRS_COLUMNS_HEADER: &str = "#columns:";
+const PAIRS_POSITION_COLUMNS: [&str; 2] = ["pos1", "pos2"];
+// score is often `.` in BEDPE files, so it is kept as a string
+const BEDPE_COLUMNS: [(&str, DataType); 10] = [
+    ("chrom1", DataType::Utf8),
+    ("start1", DataType::Int64),
+    ("end1", DataType::Int64),
+    ("chrom2", DataType::Utf8),
+    ("start2", DataType::Int64),
+    ("end2", DataType::Int64),
+    ("name", DataType::Utf8),
+    ("score", DataType::Utf8),
+    ("strand1", DataType::Utf8),
+    ("strand2", DataType::Utf8),
+];
+const MIN_BEDPE_COLUMNS: usize = 6;
+
+/// Registers a 4DN `.pairs` or a BEDPE file. Both expose the two ends of a contact
+/// as `chrom1`, `start1`, `end1`, `chrom2`, `start2`, `end2` (0-based, half-open)
+/// followed by the remaining columns, so they can be used in paired range operations.
+/// Pairs positions are 1-based points, kept in `pos1`/`pos2`.
+pub(crate) async fn register_pairs_table(
+    ctx: &ExonSession,
+    path: &str,
+    table_name: &str,
+    format: &InputFormat,
+) -> Result<()> {
+    let schema = match format {
+        InputFormat::Pairs => pairs_schema(path),
+        InputFormat::Bedpe => bedpe_schema(path),
+        _ => unreachable!("Not a paired-interval format: {}", format),
+    };
+    debug!("Registering {} with schema {:?}", path, schema);
+    let file_extension = get_file_extension(path);
+    let options = CsvReadOptions::new()
+        .delimiter(b'\t')
+        .has_header(false)
+        .comment(b'#')
+        .schema(&schema)
+        .file_extension(&file_extension)
+        .file_compression_type(get_compression_type(path));
+    let raw_table = format!("{}_raw", table_name);
+    ctx.session.deregister_table(&raw_table)?;
+    ctx.session.register_csv(&raw_table, path, options).await?;
+    let query = match format {
+        InputFormat::Pairs => pairs_query(&schema, &raw_table),
+        _ => format!("SELECT * FROM {}", raw_table),
+    };
+    debug!("Query: {}", query);
+    let df = ctx.session.sql(&query).await?;
+    ctx.session.register_table(table_name, df.into_view())?;
+    ctx.session.deregister_table(&raw_table)?;
+    Ok(())
+}
+
+/// Column names are taken from the `#columns:` header line of a local file.
+fn pairs_schema(path: &str) -> Schema {
+    let columns = open_text_file(path)
+        .and_then(|reader| {
+            reader
+                .lines()
+                .map_while(Result::ok)
+                .take_while(|line| line.starts_with('#'))
+                .find_map(|line| {
+                    line.strip_prefix(PAIRS_COLUMNS_HEADER)
+                        .map(|c| c.split_whitespace().map(String::from).collect::<Vec<_>>())
+                })
+        })
+        .filter(|columns| columns.len() >= PAIRS_COLUMNS.len())
+        .unwrap_or_else(|| PAIRS_COLUMNS.iter().map(|c| c.to_string()).collect());
+    Schema::new(
+        columns
+            .iter()
+            .map(|name| {
+                let data_type = if PAIRS_POSITION_COLUMNS.contains(&name.as_str()) {
+                    DataType::Int64
+                } else {
+                    DataType::Utf8
+                };
+                Field::new(name, data_type, true)
+            })
+            .collect::<Vec<Field>>(),
+    )
+}
+
+/// Extra columns past the 10 standard ones are named `field_{n}` (1-based).
+fn bedpe_schema(path: &str) -> Schema {
+    let n = first_data_line(path)
+        .map(|line| line.split('\t').count())
+        .unwrap_or(BEDPE_COLUMNS.len())
+        .max(MIN_BEDPE_COLUMNS);
+    Schema::new(
+        (0..n)
+            .map(|i| match BEDPE_COLUMNS.get(i) {
+                Some((name, data_type)) => Field::new(*name, data_type.clone(), true),
+                None => Field::new(format!("field_{}", i + 1), DataType::Utf8, true),
+            })
+            .collect::<Vec<Field>>(),
+    )
+}
+
+fn pairs_query(schema: &Schema, raw_table: &str) -> String {
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|f| f.name())
+        .filter(|name| !["chrom1", "chrom2"].contains(&name.as_str()))
+        .map(|name| format!("\"{}\"", name))
+        .collect::<Vec<String>>()
+        .join(", ");
+    format!(
+        r#"
+        SELECT
+            chrom1, pos1 - 1 AS start1, pos1 AS end1,
+            chrom2, pos2 - 1 AS start2, pos2 AS end2,
+            {}
+        FROM {}
+        "#,
+        columns, raw_table
+    )
+}
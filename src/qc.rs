@@ -0,0 +1,564 @@
+use std::collections::BTreeMap;
+use std::hash::Hasher;
+use std::sync::Arc;
+
+use arrow::array::{Array, ArrayRef, AsArray, Float64Array, Int64Array, RecordBatch, StringArray};
+use arrow::compute::cast;
+use arrow_schema::{DataType, Field, Schema};
+use datafusion::common::{exec_err, DataFusionError, Result};
+use datafusion::prelude::ident;
+use exon::ExonSession;
+use futures_util::future::try_join_all;
+use futures_util::StreamExt;
+use log::debug;
+use rustc_hash::{FxHashMap, FxHasher};
+
+/// Phred scores 0..=93, the range of printable quality characters.
+const QUALITY_BINS: usize = 94;
+/// At most 100,000 distinct sequences are tracked for duplication and overrepresentation,
+/// reads longer than 75 bases by their first 50. Past the limit, only sequences whose hash
+/// falls below a halving threshold are kept, so the sample does not depend on partitioning.
+const DUPLICATION_TRACKING_LIMIT: usize = 100_000;
+const DUPLICATION_LONG_READ: usize = 75;
+const DUPLICATION_PREFIX_LENGTH: usize = 50;
+const OVERREPRESENTED_FRACTION: f64 = 0.001;
+/// FastQC default adapters, named after the `adapter_content` columns.
+const ADAPTERS: [(&str, &str); 6] = [
+    ("illumina_universal", "AGATCGGAAGAG"),
+    ("illumina_small_rna_3", "TGGAATTCTCGG"),
+    ("illumina_small_rna_5", "GATCGTCGGACT"),
+    ("nextera", "CTGTCTCTTATA"),
+    ("poly_a", "AAAAAAAAAAAA"),
+    ("poly_g", "GGGGGGGGGGGG"),
+];
+const DUPLICATION_LEVELS: [(&str, u64); 16] = [
+    ("1", 1),
+    ("2", 2),
+    ("3", 3),
+    ("4", 4),
+    ("5", 5),
+    ("6", 6),
+    ("7", 7),
+    ("8", 8),
+    ("9", 9),
+    (">10", 10),
+    (">50", 50),
+    (">100", 100),
+    (">500", 500),
+    (">1k", 1000),
+    (">5k", 5000),
+    (">10k", 10000),
+];
+const QC_MODULES: [&str; 8] = [
+    "per_base_quality",
+    "per_sequence_quality",
+    "per_base_content",
+    "per_sequence_gc",
+    "length_distribution",
+    "duplication_levels",
+    "overrepresented_sequences",
+    "adapter_content",
+];
+
+/// FastQC metrics of a set of reads, computed in a single pass and merged across
+/// partitions.
+#[derive(Debug)]
+pub(crate) struct QcStats {
+    phred_offset: u8,
+    reads: u64,
+    base_quality: Vec<[u64; QUALITY_BINS]>,
+    /// A, C, G, T and N counts per position, other symbols are counted as N
+    base_content: Vec<[u64; 5]>,
+    sequence_quality: [u64; QUALITY_BINS],
+    sequence_gc: [u64; 101],
+    lengths: BTreeMap<usize, u64>,
+    duplicates: FxHashMap<Vec<u8>, u64>,
+    /// number of leading hash bits that must be zero for a sequence to be tracked
+    duplicate_sampling: u32,
+    /// reads with the first occurrence of each adapter at a position
+    adapters: Vec<[u64; ADAPTERS.len()]>,
+}
+
+impl QcStats {
+    pub(crate) fn new(phred_offset: u8) -> Self {
+        Self {
+            phred_offset,
+            reads: 0,
+            base_quality: Vec::new(),
+            base_content: Vec::new(),
+            sequence_quality: [0; QUALITY_BINS],
+            sequence_gc: [0; 101],
+            lengths: BTreeMap::new(),
+            duplicates: FxHashMap::default(),
+            duplicate_sampling: 0,
+            adapters: Vec::new(),
+        }
+    }
+
+    fn reserve_positions(&mut self, len: usize) {
+        if self.base_content.len() < len {
+            self.base_quality.resize(len, [0; QUALITY_BINS]);
+            self.base_content.resize(len, [0; 5]);
+            self.adapters.resize(len, [0; ADAPTERS.len()]);
+        }
+    }
+
+    pub(crate) fn update_read(&mut self, sequence: &[u8], quality: Option<&[u8]>) {
+        let len = sequence.len();
+        self.reads += 1;
+        self.reserve_positions(len);
+        *self.lengths.entry(len).or_insert(0) += 1;
+
+        let sequence = sequence.to_ascii_uppercase();
+        let (mut gc, mut acgt) = (0usize, 0usize);
+        for (i, base) in sequence.iter().enumerate() {
+            let index = match base {
+                b'A' => 0,
+                b'C' => 1,
+                b'G' => 2,
+                b'T' => 3,
+                _ => 4,
+            };
+            self.base_content[i][index] += 1;
+            if index < 4 {
+                acgt += 1;
+                if index == 1 || index == 2 {
+                    gc += 1;
+                }
+            }
+        }
+        if acgt > 0 {
+            self.sequence_gc[(gc * 100 + acgt / 2) / acgt] += 1;
+        }
+
+        if let Some(quality) = quality.filter(|q| !q.is_empty() && len > 0) {
+            let mut total = 0usize;
+            let scored = quality.len().min(len);
+            for (i, q) in quality[..scored].iter().enumerate() {
+                let q = (q.saturating_sub(self.phred_offset) as usize).min(QUALITY_BINS - 1);
+                self.base_quality[i][q] += 1;
+                total += q;
+            }
+            self.sequence_quality[total / scored] += 1;
+        }
+
+        let key = if len > DUPLICATION_LONG_READ {
+            &sequence[..DUPLICATION_PREFIX_LENGTH]
+        } else {
+            &sequence[..]
+        };
+        if let Some(count) = self.duplicates.get_mut(key) {
+            *count += 1;
+        } else if is_sampled(key, self.duplicate_sampling) {
+            self.duplicates.insert(key.to_vec(), 1);
+            self.limit_duplicates();
+        }
+
+        for (j, (_, adapter)) in ADAPTERS.iter().enumerate() {
+            if let Some(pos) = sequence
+                .windows(adapter.len())
+                .position(|w| w == adapter.as_bytes())
+            {
+                self.adapters[pos][j] += 1;
+            }
+        }
+    }
+
+    /// Halves the sampling threshold until the tracked sequences fit the limit. Counts of
+    /// the remaining sequences stay exact, as they were sampled on their first occurrence.
+    fn limit_duplicates(&mut self) {
+        while self.duplicates.len() > DUPLICATION_TRACKING_LIMIT {
+            self.duplicate_sampling += 1;
+            let sampling = self.duplicate_sampling;
+            self.duplicates
+                .retain(|sequence, _| is_sampled(sequence, sampling));
+        }
+    }
+
+    pub(crate) fn update_batch(
+        &mut self,
+        sequences: &ArrayRef,
+        qualities: &ArrayRef,
+    ) -> Result<()> {
+        let sequences = cast(sequences, &DataType::Utf8)?;
+        let qualities = cast(qualities, &DataType::Utf8)?;
+        let (sequences, qualities) = (sequences.as_string::<i32>(), qualities.as_string::<i32>());
+        for i in 0..sequences.len() {
+            if sequences.is_null(i) {
+                continue;
+            }
+            let quality = qualities.is_valid(i).then(|| qualities.value(i).as_bytes());
+            self.update_read(sequences.value(i).as_bytes(), quality);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn merge(&mut self, other: QcStats) {
+        self.reads += other.reads;
+        self.reserve_positions(other.base_content.len());
+        for (i, counts) in other.base_quality.iter().enumerate() {
+            add(&mut self.base_quality[i], counts);
+        }
+        for (i, counts) in other.base_content.iter().enumerate() {
+            add(&mut self.base_content[i], counts);
+        }
+        for (i, counts) in other.adapters.iter().enumerate() {
+            add(&mut self.adapters[i], counts);
+        }
+        add(&mut self.sequence_quality, &other.sequence_quality);
+        add(&mut self.sequence_gc, &other.sequence_gc);
+        for (len, count) in other.lengths {
+            *self.lengths.entry(len).or_insert(0) += count;
+        }
+        let sampling = self.duplicate_sampling.max(other.duplicate_sampling);
+        if sampling > self.duplicate_sampling {
+            self.duplicate_sampling = sampling;
+            self.duplicates
+                .retain(|sequence, _| is_sampled(sequence, sampling));
+        }
+        for (sequence, count) in other.duplicates {
+            if is_sampled(&sequence, sampling) {
+                *self.duplicates.entry(sequence).or_insert(0) += count;
+            }
+        }
+        self.limit_duplicates();
+    }
+
+    /// One record batch per module, in the order of `QC_MODULES`.
+    pub(crate) fn to_batches(&self) -> Result<Vec<(String, RecordBatch)>> {
+        let batches = vec![
+            self.per_base_quality()?,
+            self.per_sequence_quality()?,
+            self.per_base_content()?,
+            self.per_sequence_gc()?,
+            self.length_distribution()?,
+            self.duplication_levels()?,
+            self.overrepresented_sequences()?,
+            self.adapter_content()?,
+        ];
+        Ok(QC_MODULES
+            .iter()
+            .map(|name| name.to_string())
+            .zip(batches)
+            .collect())
+    }
+
+    fn per_base_quality(&self) -> Result<RecordBatch> {
+        let mut columns: Vec<Vec<f64>> = vec![Vec::new(); 6];
+        let mut positions = Vec::new();
+        for (i, histogram) in self.base_quality.iter().enumerate() {
+            let total = histogram.iter().sum::<u64>();
+            if total == 0 {
+                continue;
+            }
+            let sum = histogram
+                .iter()
+                .enumerate()
+                .map(|(q, count)| q as u64 * count)
+                .sum::<u64>();
+            positions.push(i as i64 + 1);
+            columns[0].push(sum as f64 / total as f64);
+            for (column, p) in columns[1..].iter_mut().zip([0.5, 0.25, 0.75, 0.1, 0.9]) {
+                column.push(percentile(histogram, total, p));
+            }
+        }
+        let names = [
+            "mean",
+            "median",
+            "lower_quartile",
+            "upper_quartile",
+            "percentile_10",
+            "percentile_90",
+        ];
+        let mut fields = vec![("position", int_array(positions))];
+        fields.extend(
+            names
+                .iter()
+                .zip(columns)
+                .map(|(name, values)| (*name, float_array(values))),
+        );
+        record_batch(fields)
+    }
+
+    fn per_sequence_quality(&self) -> Result<RecordBatch> {
+        let (qualities, counts) = nonzero(&self.sequence_quality);
+        record_batch(vec![
+            ("quality", int_array(qualities)),
+            ("count", int_array(counts)),
+        ])
+    }
+
+    /// A, C, G and T percentages of the non-N bases, N and GC percentages of all bases.
+    fn per_base_content(&self) -> Result<RecordBatch> {
+        let mut columns: Vec<Vec<f64>> = vec![Vec::new(); 6];
+        let mut positions = Vec::new();
+        for (i, counts) in self.base_content.iter().enumerate() {
+            let total = counts.iter().sum::<u64>();
+            if total == 0 {
+                continue;
+            }
+            let acgt = total - counts[4];
+            let percent = |count: u64, of: u64| {
+                if of == 0 {
+                    0.0
+                } else {
+                    count as f64 * 100.0 / of as f64
+                }
+            };
+            positions.push(i as i64 + 1);
+            for base in 0..4 {
+                columns[base].push(percent(counts[base], acgt));
+            }
+            columns[4].push(percent(counts[4], total));
+            columns[5].push(percent(counts[1] + counts[2], acgt));
+        }
+        let mut fields = vec![("position", int_array(positions))];
+        fields.extend(
+            ["a", "c", "g", "t", "n", "gc"]
+                .iter()
+                .zip(columns)
+                .map(|(name, values)| (*name, float_array(values))),
+        );
+        record_batch(fields)
+    }
+
+    fn per_sequence_gc(&self) -> Result<RecordBatch> {
+        record_batch(vec![
+            ("gc", int_array((0..=100).collect())),
+            (
+                "count",
+                int_array(self.sequence_gc.iter().map(|c| *c as i64).collect()),
+            ),
+        ])
+    }
+
+    fn length_distribution(&self) -> Result<RecordBatch> {
+        record_batch(vec![
+            (
+                "length",
+                int_array(self.lengths.keys().map(|l| *l as i64).collect()),
+            ),
+            (
+                "count",
+                int_array(self.lengths.values().map(|c| *c as i64).collect()),
+            ),
+        ])
+    }
+
+    /// Shares of the distinct and of all tracked sequences per duplication level,
+    /// without FastQC's extrapolation past the tracking limit.
+    fn duplication_levels(&self) -> Result<RecordBatch> {
+        let mut distinct = [0u64; DUPLICATION_LEVELS.len()];
+        let mut reads = [0u64; DUPLICATION_LEVELS.len()];
+        for count in self.duplicates.values() {
+            let level = DUPLICATION_LEVELS
+                .iter()
+                .rposition(|(_, min)| count >= min)
+                .unwrap_or(0);
+            distinct[level] += 1;
+            reads[level] += count;
+        }
+        let total_distinct = distinct.iter().sum::<u64>().max(1) as f64;
+        let total_reads = reads.iter().sum::<u64>().max(1) as f64;
+        record_batch(vec![
+            (
+                "duplication_level",
+                Arc::new(StringArray::from_iter_values(
+                    DUPLICATION_LEVELS.iter().map(|(level, _)| *level),
+                )),
+            ),
+            (
+                "percent_deduplicated",
+                float_array(
+                    distinct
+                        .iter()
+                        .map(|c| *c as f64 * 100.0 / total_distinct)
+                        .collect(),
+                ),
+            ),
+            (
+                "percent_total",
+                float_array(
+                    reads
+                        .iter()
+                        .map(|c| *c as f64 * 100.0 / total_reads)
+                        .collect(),
+                ),
+            ),
+        ])
+    }
+
+    /// Tracked sequences making up more than 0.1% of all reads, most frequent first.
+    fn overrepresented_sequences(&self) -> Result<RecordBatch> {
+        let threshold = self.reads as f64 * OVERREPRESENTED_FRACTION;
+        let mut sequences = self
+            .duplicates
+            .iter()
+            .filter(|(_, count)| **count as f64 > threshold)
+            .collect::<Vec<_>>();
+        sequences.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let possible_source = |sequence: &[u8]| {
+            ADAPTERS
+                .iter()
+                .find(|(_, adapter)| {
+                    let adapter = adapter.as_bytes();
+                    sequence.windows(adapter.len()).any(|w| w == adapter)
+                        || adapter
+                            .windows(sequence.len().max(1))
+                            .any(|w| w == sequence)
+                })
+                .map_or("No Hit", |(name, _)| *name)
+        };
+        record_batch(vec![
+            (
+                "sequence",
+                Arc::new(StringArray::from_iter_values(
+                    sequences
+                        .iter()
+                        .map(|(s, _)| String::from_utf8_lossy(s).into_owned()),
+                )),
+            ),
+            (
+                "count",
+                int_array(sequences.iter().map(|(_, c)| **c as i64).collect()),
+            ),
+            (
+                "percentage",
+                float_array(
+                    sequences
+                        .iter()
+                        .map(|(_, c)| **c as f64 * 100.0 / self.reads.max(1) as f64)
+                        .collect(),
+                ),
+            ),
+            (
+                "possible_source",
+                Arc::new(StringArray::from_iter_values(
+                    sequences.iter().map(|(s, _)| possible_source(s)),
+                )),
+            ),
+        ])
+    }
+
+    /// Cumulative percentage of reads with each adapter starting at or before a position.
+    fn adapter_content(&self) -> Result<RecordBatch> {
+        let mut columns: Vec<Vec<f64>> = vec![Vec::new(); ADAPTERS.len()];
+        let mut cumulative = [0u64; ADAPTERS.len()];
+        for counts in &self.adapters {
+            for (j, count) in counts.iter().enumerate() {
+                cumulative[j] += count;
+                columns[j].push(cumulative[j] as f64 * 100.0 / self.reads.max(1) as f64);
+            }
+        }
+        let mut fields = vec![(
+            "position",
+            int_array((1..=self.adapters.len() as i64).collect()),
+        )];
+        fields.extend(
+            ADAPTERS
+                .iter()
+                .zip(columns)
+                .map(|((name, _), values)| (*name, float_array(values))),
+        );
+        record_batch(fields)
+    }
+}
+
+fn is_sampled(sequence: &[u8], sampling: u32) -> bool {
+    let mut hasher = FxHasher::default();
+    hasher.write(sequence);
+    hasher.finish().leading_zeros() >= sampling
+}
+
+fn add<const N: usize>(into: &mut [u64; N], counts: &[u64; N]) {
+    into.iter_mut().zip(counts).for_each(|(a, b)| *a += b);
+}
+
+/// Smallest score with at least a fraction `p` of the `total` scores at or below it.
+fn percentile(histogram: &[u64], total: u64, p: f64) -> f64 {
+    let target = (p * total as f64).ceil().max(1.0) as u64;
+    let mut cumulative = 0;
+    for (q, count) in histogram.iter().enumerate() {
+        cumulative += count;
+        if cumulative >= target {
+            return q as f64;
+        }
+    }
+    (histogram.len() - 1) as f64
+}
+
+fn nonzero(histogram: &[u64]) -> (Vec<i64>, Vec<i64>) {
+    histogram
+        .iter()
+        .enumerate()
+        .filter(|(_, count)| **count > 0)
+        .map(|(value, count)| (value as i64, *count as i64))
+        .unzip()
+}
+
+fn int_array(values: Vec<i64>) -> ArrayRef {
+    Arc::new(Int64Array::from(values))
+}
+
+fn float_array(values: Vec<f64>) -> ArrayRef {
+    Arc::new(Float64Array::from(values))
+}
+
+fn record_batch(columns: Vec<(&str, ArrayRef)>) -> Result<RecordBatch> {
+    let schema = Schema::new(
+        columns
+            .iter()
+            .map(|(name, array)| Field::new(*name, array.data_type().clone(), false))
+            .collect::<Vec<Field>>(),
+    );
+    Ok(RecordBatch::try_new(
+        Arc::new(schema),
+        columns.into_iter().map(|(_, array)| array).collect(),
+    )?)
+}
+
+/// Computes the QC modules of the `sequence_column` and `quality_column` of a table,
+/// with every partition processed in parallel.
+pub(crate) async fn fastq_qc(
+    ctx: &ExonSession,
+    table_name: &str,
+    sequence_column: &str,
+    quality_column: &str,
+    phred_offset: u8,
+) -> Result<Vec<(String, RecordBatch)>> {
+    if !(33..=64).contains(&phred_offset) {
+        return exec_err!(
+            "Phred offset must be between 33 and 64, got {}",
+            phred_offset
+        );
+    }
+    let df = ctx
+        .session
+        .table(table_name)
+        .await?
+        .select(vec![ident(sequence_column), ident(quality_column)])?;
+    let streams = df.execute_stream_partitioned().await?;
+    debug!(
+        "Computing QC of {} in {} partitions",
+        table_name,
+        streams.len()
+    );
+    let tasks = streams.into_iter().map(|mut stream| {
+        tokio::spawn(async move {
+            let mut stats = QcStats::new(phred_offset);
+            while let Some(batch) = stream.next().await {
+                let batch = batch?;
+                stats.update_batch(batch.column(0), batch.column(1))?;
+            }
+            Ok::<QcStats, DataFusionError>(stats)
+        })
+    });
+    let mut stats = QcStats::new(phred_offset);
+    for partition in try_join_all(tasks)
+        .await
+        .map_err(|e| DataFusionError::External(Box::new(e)))?
+    {
+        stats.merge(partition?);
+    }
+    stats.to_batches()
+}
@@ -0,0 +1,215 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use arrow::array::{
+    Array, ArrayRef, AsArray, Float64Builder, Int64Builder, ListBuilder, UInt8Builder,
+};
+use arrow::datatypes::Int64Type;
+use arrow_schema::{DataType, Field};
+use datafusion::common::{exec_err, Result, ScalarValue};
+use datafusion::logical_expr::{
+    ColumnarValue, ScalarUDF, ScalarUDFImpl, Signature, TypeSignature, Volatility,
+};
+
+/// Sanger / Illumina 1.8+ quality encoding, Illumina 1.3-1.7 uses 64.
+pub(crate) const DEFAULT_PHRED_OFFSET: i64 = 33;
+const PHRED_OFFSETS: [i64; 2] = [33, 64];
+
+/// Per-read functions of a FASTQ quality string. All take an optional Phred `offset`
+/// as the last argument.
+#[derive(Clone, Copy, Debug)]
+enum QualityFunction {
+    /// `phred_decode(quality [, offset])`, the list of Phred scores
+    Decode,
+    /// `mean_quality(quality [, offset])`
+    Mean,
+    /// `median_quality(quality [, offset])`
+    Median,
+    /// `expected_errors(quality [, offset])`, the sum of the error probabilities
+    ExpectedErrors,
+    /// `bases_at_or_above_q(quality, q [, offset])`
+    AtOrAboveQ,
+}
+
+impl QualityFunction {
+    fn name(&self) -> &'static str {
+        match self {
+            QualityFunction::Decode => "phred_decode",
+            QualityFunction::Mean => "mean_quality",
+            QualityFunction::Median => "median_quality",
+            QualityFunction::ExpectedErrors => "expected_errors",
+            QualityFunction::AtOrAboveQ => "bases_at_or_above_q",
+        }
+    }
+
+    fn return_type(&self) -> DataType {
+        match self {
+            QualityFunction::Decode => {
+                DataType::List(Arc::new(Field::new("item", DataType::UInt8, true)))
+            },
+            QualityFunction::AtOrAboveQ => DataType::Int64,
+            _ => DataType::Float64,
+        }
+    }
+
+    fn signature(&self) -> Signature {
+        let required = match self {
+            QualityFunction::AtOrAboveQ => vec![DataType::Utf8, DataType::Int64],
+            _ => vec![DataType::Utf8],
+        };
+        let with_offset = [required.clone(), vec![DataType::Int64]].concat();
+        Signature::one_of(
+            vec![
+                TypeSignature::Exact(required),
+                TypeSignature::Exact(with_offset),
+            ],
+            Volatility::Immutable,
+        )
+    }
+}
+
+/// Decodes a quality string into `scores`.
+pub(crate) fn decode(quality: &[u8], offset: i64, scores: &mut Vec<u8>) -> Result<()> {
+    if !PHRED_OFFSETS.contains(&offset) {
+        return exec_err!("Phred offset must be 33 or 64, got {}", offset);
+    }
+    scores.clear();
+    for q in quality {
+        if (*q as i64) < offset || *q > b'~' {
+            return exec_err!(
+                "Invalid quality character '{}' for Phred offset {}",
+                *q as char,
+                offset
+            );
+        }
+        scores.push(*q - offset as u8);
+    }
+    Ok(())
+}
+
+fn median(scores: &mut [u8]) -> Option<f64> {
+    if scores.is_empty() {
+        return None;
+    }
+    scores.sort_unstable();
+    let mid = scores.len() / 2;
+    if scores.len() % 2 == 0 {
+        Some((scores[mid - 1] as f64 + scores[mid] as f64) / 2.0)
+    } else {
+        Some(scores[mid] as f64)
+    }
+}
+
+#[derive(Debug)]
+struct QualityUdf {
+    function: QualityFunction,
+    signature: Signature,
+}
+
+impl QualityUdf {
+    fn new(function: QualityFunction) -> Self {
+        Self {
+            function,
+            signature: function.signature(),
+        }
+    }
+}
+
+impl ScalarUDFImpl for QualityUdf {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        self.function.name()
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(self.function.return_type())
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        let scalar = args
+            .iter()
+            .all(|arg| matches!(arg, ColumnarValue::Scalar(_)));
+        let arrays = ColumnarValue::values_to_arrays(args)?;
+        let qualities = arrays[0].as_string::<i32>();
+        let params = arrays[1..]
+            .iter()
+            .map(|a| a.as_primitive::<Int64Type>())
+            .collect::<Vec<_>>();
+        let (thresholds, offsets) = match self.function {
+            QualityFunction::AtOrAboveQ => (params.first(), params.get(1)),
+            _ => (None, params.first()),
+        };
+
+        let mut lists = ListBuilder::new(UInt8Builder::new());
+        let mut floats = Float64Builder::with_capacity(qualities.len());
+        let mut counts = Int64Builder::with_capacity(qualities.len());
+        let mut scores = Vec::new();
+        for i in 0..qualities.len() {
+            let valid = qualities.is_valid(i)
+                && thresholds.map_or(true, |t| t.is_valid(i))
+                && offsets.map_or(true, |o| o.is_valid(i));
+            if !valid {
+                match self.function {
+                    QualityFunction::Decode => lists.append_null(),
+                    QualityFunction::AtOrAboveQ => counts.append_null(),
+                    _ => floats.append_null(),
+                }
+                continue;
+            }
+            let offset = offsets.map_or(DEFAULT_PHRED_OFFSET, |o| o.value(i));
+            decode(qualities.value(i).as_bytes(), offset, &mut scores)?;
+            match self.function {
+                QualityFunction::Decode => {
+                    lists.values().append_slice(&scores);
+                    lists.append(true);
+                },
+                QualityFunction::Mean => {
+                    floats.append_option((!scores.is_empty()).then(|| {
+                        scores.iter().map(|q| *q as f64).sum::<f64>() / scores.len() as f64
+                    }))
+                },
+                QualityFunction::Median => floats.append_option(median(&mut scores)),
+                QualityFunction::ExpectedErrors => floats
+                    .append_value(scores.iter().map(|q| 10f64.powf(-(*q as f64) / 10.0)).sum()),
+                QualityFunction::AtOrAboveQ => {
+                    let q = thresholds.map_or(0, |t| t.value(i));
+                    counts.append_value(scores.iter().filter(|s| **s as i64 >= q).count() as i64)
+                },
+            }
+        }
+        let array: ArrayRef = match self.function {
+            QualityFunction::Decode => Arc::new(lists.finish()),
+            QualityFunction::AtOrAboveQ => Arc::new(counts.finish()),
+            _ => Arc::new(floats.finish()),
+        };
+        if scalar {
+            Ok(ColumnarValue::Scalar(ScalarValue::try_from_array(
+                &array, 0,
+            )?))
+        } else {
+            Ok(ColumnarValue::Array(array))
+        }
+    }
+}
+
+/// Phred quality UDFs: `phred_decode`, `mean_quality`, `median_quality`,
+/// `expected_errors` and `bases_at_or_above_q`.
+pub(crate) fn create_quality_udfs() -> Vec<ScalarUDF> {
+    [
+        QualityFunction::Decode,
+        QualityFunction::Mean,
+        QualityFunction::Median,
+        QualityFunction::ExpectedErrors,
+        QualityFunction::AtOrAboveQ,
+    ]
+    .into_iter()
+    .map(|function| ScalarUDF::from(QualityUdf::new(function)))
+    .collect()
+}
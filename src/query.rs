@@ -226,3 +226,77 @@ pub(crate) fn count_overlaps_query(query_params: QueryParams) -> String {
     );
     query
 }
+
+/// Pairs of paired-interval rows (e.g. BEDPE, Hi-C contacts) where both ends overlap
+/// their counterparts. `columns_1`/`columns_2` hold contig, start and end of the first
+/// end followed by contig, start and end of the second end. Columns of each table are kept
+/// together (interval columns first) to match the schema of the inputs.
+pub(crate) fn paired_overlap_query(query_params: QueryParams) -> String {
+    let join_columns = |columns: &Vec<String>, alias: &str, suffix: &str| {
+        columns
+            .iter()
+            .map(|c| format!("{}.{} AS {}{}", alias, c, c, suffix))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+    let end_condition = |i: usize| {
+        format!(
+            r#"
+                a.{contig_1} = b.{contig_2}
+            AND
+                cast(a.{end_1} AS INT) >{sign} cast(b.{start_2} AS INT)
+            AND
+                cast(a.{start_1} AS INT) <{sign} cast(b.{end_2} AS INT)
+            "#,
+            contig_1 = query_params.columns_1[i],
+            start_1 = query_params.columns_1[i + 1],
+            end_1 = query_params.columns_1[i + 2],
+            contig_2 = query_params.columns_2[i],
+            start_2 = query_params.columns_2[i + 1],
+            end_2 = query_params.columns_2[i + 2],
+            sign = query_params.sign,
+        )
+    };
+    let query = format!(
+        r#"
+            SELECT
+                {}
+                {},
+                {}
+                {}
+            FROM
+                {} AS b, {} AS a
+            WHERE
+                {}
+            AND
+                {}
+        "#,
+        join_columns(&query_params.columns_1, "a", &query_params.suffixes.0),
+        if !query_params.other_columns_1.is_empty() {
+            ",".to_string()
+                + &format_non_join_tables(
+                    query_params.other_columns_1.clone(),
+                    "a".to_string(),
+                    query_params.suffixes.0.clone(),
+                )
+        } else {
+            "".to_string()
+        },
+        join_columns(&query_params.columns_2, "b", &query_params.suffixes.1),
+        if !query_params.other_columns_2.is_empty() {
+            ",".to_string()
+                + &format_non_join_tables(
+                    query_params.other_columns_2.clone(),
+                    "b".to_string(),
+                    query_params.suffixes.1.clone(),
+                )
+        } else {
+            "".to_string()
+        },
+        query_params.right_table,
+        query_params.left_table,
+        end_condition(0), // first end
+        end_condition(3), // second end
+    );
+    query
+}
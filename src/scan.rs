@@ -1,21 +1,53 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use std::num::NonZeroUsize;
+use std::path::Path;
 use std::sync::Arc;
 
-use arrow::array::RecordBatch;
+use arrow::array::{AsArray, RecordBatch};
+use arrow::datatypes::Int64Type;
 use arrow::error::ArrowError;
 use arrow::ffi_stream::ArrowArrayStreamReader;
 use arrow::pyarrow::PyArrowType;
-use datafusion::dataframe::DataFrameWriteOptions;
+use arrow_schema::{DataType, Field, Schema};
+use datafusion::catalog::TableProvider;
+use datafusion::common::ScalarValue;
+use datafusion::dataframe::{DataFrame, DataFrameWriteOptions};
+use datafusion::datasource::file_format::file_compression_type::FileCompressionType;
 use datafusion::datasource::MemTable;
-use datafusion::prelude::{CsvReadOptions, ParquetReadOptions};
+use datafusion::execution::object_store::ObjectStoreUrl;
+use datafusion::functions_aggregate::expr_fn::count;
+use datafusion::prelude::{
+    cast, ident, lit, try_cast, when, CsvReadOptions as DataFusionCsvReadOptions,
+    ParquetReadOptions,
+};
 use datafusion_vcf::table_provider::VcfTableProvider;
 use exon::ExonSession;
+use flate2::read::MultiGzDecoder;
+use glob::glob;
+use noodles_bgzf as bgzf;
+use object_store::path::Path as ObjectPath;
+use object_store::ObjectStore;
+use pyo3::exceptions::PyValueError;
+use pyo3::{PyErr, PyResult};
 use tokio::runtime::Runtime;
 use tracing::debug;
+use url::Url;
 
-use crate::context::PyBioSessionContext;
-use crate::option::{InputFormat, ReadOptions, VcfReadOptions};
+use crate::alignment::alignment_table;
+use crate::bbi::{BbiTableProvider, BIGBED_MAGIC, BIGWIG_MAGIC};
+use crate::bed::register_bed_table;
+use crate::context::{BioConfig, PyBioSessionContext, STAGING_DIR};
+use crate::gff::register_gff_table;
+use crate::option::{BedReadOptions, CsvReadOptions, InputFormat, ReadOptions, VcfReadOptions};
+use crate::pairs::register_pairs_table;
+use crate::vcf::{read_vcf_header, VcfHeaderTableProvider};
 
 const MAX_IN_MEMORY_ROWS: usize = 1024 * 1024;
+const SAMPLE_COLUMN: &str = "sample";
+const FILE_COLUMN: &str = "file";
 
 pub(crate) fn register_frame(
     py_ctx: &PyBioSessionContext,
@@ -47,22 +79,193 @@ pub(crate) fn register_frame(
             &table_name,
             InputFormat::Parquet,
             None,
-        ));
+        ))
+        .unwrap();
     }
 }
 
-pub(crate) fn get_input_format(path: &str) -> InputFormat {
-    let path = path.to_lowercase();
-    if path.ends_with(".parquet") {
-        InputFormat::Parquet
-    } else if path.ends_with(".csv") {
-        InputFormat::Csv
-    } else if path.ends_with(".bed") {
-        InputFormat::Bed
-    } else if path.ends_with(".vcf") || path.ends_with(".vcf.gz") || path.ends_with(".vcf.bgz") {
-        InputFormat::Vcf
+const COMPRESSION_EXTENSIONS: [&str; 5] = [".gz", ".bgz", ".bz2", ".xz", ".zst"];
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const BZIP2_MAGIC: &[u8] = b"BZh";
+const XZ_MAGIC: [u8; 6] = [0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const COMPRESSION_MAGIC_SIZE: usize = 16;
+const BAM_MAGIC: &[u8] = b"BAM\x01";
+const CRAM_MAGIC: &[u8] = b"CRAM";
+const PARQUET_MAGIC: &[u8] = b"PAR1";
+const SNIFF_SIZE: usize = 64 * 1024;
+const SAM_HEADER_TAGS: [&[u8]; 5] = [b"@HD\t", b"@SQ\t", b"@RG\t", b"@PG\t", b"@CO\t"];
+const VCF_INDEX_EXTENSIONS: [&str; 2] = [".tbi", ".csi"];
+const BAM_INDEX_EXTENSIONS: [&str; 2] = [".bai", ".csi"];
+
+/// Detects the input format from the file extension (ignoring compression suffixes)
+/// and, for local files with an unknown extension, from the leading magic bytes.
+/// Local VCF and BAM files with an index next to them are detected as indexed formats.
+pub(crate) fn get_input_format(path: &str) -> PyResult<InputFormat> {
+    if let Some(format) = get_input_format_from_extension(path) {
+        return Ok(with_local_index(path, format));
+    }
+    if Path::new(path).is_file() {
+        if let Some(format) = get_input_format_from_content(path)? {
+            debug!("Detected format {} from content of {}", format, path);
+            return Ok(with_local_index(path, format));
+        }
+    }
+    Err(PyValueError::new_err(format!(
+        "Unsupported or unrecognized input format: {}",
+        path
+    )))
+}
+
+/// Like `get_input_format`, but also looks up the index of VCF and BAM files stored
+/// in an object store registered in the session.
+pub(crate) async fn detect_input_format(ctx: &ExonSession, path: &str) -> PyResult<InputFormat> {
+    let format = get_input_format(path)?;
+    if !is_remote(path) {
+        return Ok(format);
+    }
+    for ext in index_extensions(&format) {
+        if object_exists(ctx, &format!("{}{}", path, ext)).await {
+            debug!("Detected index {}{}", path, ext);
+            return Ok(indexed_format(format));
+        }
+    }
+    Ok(format)
+}
+
+fn index_extensions(format: &InputFormat) -> &'static [&'static str] {
+    match format {
+        InputFormat::Vcf => &VCF_INDEX_EXTENSIONS,
+        InputFormat::Bam => &BAM_INDEX_EXTENSIONS,
+        _ => &[],
+    }
+}
+
+fn indexed_format(format: InputFormat) -> InputFormat {
+    match format {
+        InputFormat::Vcf => InputFormat::IndexedVcf,
+        InputFormat::Bam => InputFormat::IndexedBam,
+        format => format,
+    }
+}
+
+fn with_local_index(path: &str, format: InputFormat) -> InputFormat {
+    let indexed = index_extensions(&format)
+        .iter()
+        .any(|ext| Path::new(&format!("{}{}", path, ext)).is_file());
+    if indexed {
+        indexed_format(format)
+    } else {
+        format
+    }
+}
+
+fn is_remote(path: &str) -> bool {
+    path.contains("://") && !path.starts_with("file://")
+}
+
+/// Whether `url` exists in the object store registered for its bucket or host.
+async fn object_exists(ctx: &ExonSession, url: &str) -> bool {
+    let Ok(url) = Url::parse(url) else {
+        return false;
+    };
+    let Some(store) = registered_object_store(ctx, &url) else {
+        return false;
+    };
+    match ObjectPath::from_url_path(url.path()) {
+        Ok(location) => store.head(&location).await.is_ok(),
+        Err(_) => false,
+    }
+}
+
+fn has_object_store(ctx: &ExonSession, path: &str) -> bool {
+    Url::parse(path).is_ok_and(|url| registered_object_store(ctx, &url).is_some())
+}
+
+fn registered_object_store(ctx: &ExonSession, url: &Url) -> Option<Arc<dyn ObjectStore>> {
+    let store_url = ObjectStoreUrl::parse(&url[..url::Position::BeforePath]).ok()?;
+    ctx.session.runtime_env().object_store(&store_url).ok()
+}
+
+fn get_input_format_from_extension(path: &str) -> Option<InputFormat> {
+    let mut path = path.to_lowercase();
+    for ext in COMPRESSION_EXTENSIONS {
+        if let Some(stripped) = path.strip_suffix(ext) {
+            path = stripped.to_string();
+            break;
+        }
+    }
+    let ext = path.rsplit_once('.')?.1;
+    match ext {
+        "parquet" => Some(InputFormat::Parquet),
+        "csv" | "tsv" | "txt" => Some(InputFormat::Csv),
+        "bed" => Some(InputFormat::Bed),
+        "vcf" => Some(InputFormat::Vcf),
+        "bam" => Some(InputFormat::Bam),
+        "cram" => Some(InputFormat::Cram),
+        "fastq" | "fq" => Some(InputFormat::Fastq),
+        "fasta" | "fa" | "fna" | "fas" => Some(InputFormat::Fasta),
+        "gff" | "gff3" => Some(InputFormat::Gff),
+        "gtf" => Some(InputFormat::Gtf),
+        "narrowpeak" => Some(InputFormat::NarrowPeak),
+        "broadpeak" => Some(InputFormat::BroadPeak),
+        "bedgraph" | "bdg" => Some(InputFormat::BedGraph),
+        "bw" | "bigwig" => Some(InputFormat::BigWig),
+        "bb" | "bigbed" => Some(InputFormat::BigBed),
+        "paf" => Some(InputFormat::Paf),
+        "sam" => Some(InputFormat::Sam),
+        "pairs" => Some(InputFormat::Pairs),
+        "bedpe" => Some(InputFormat::Bedpe),
+        _ => None,
+    }
+}
+
+fn get_input_format_from_content(path: &str) -> PyResult<Option<InputFormat>> {
+    let mut header = Vec::with_capacity(SNIFF_SIZE);
+    File::open(path)?
+        .take(SNIFF_SIZE as u64)
+        .read_to_end(&mut header)?;
+    if header.starts_with(CRAM_MAGIC) {
+        return Ok(Some(InputFormat::Cram));
+    }
+    if header.starts_with(PARQUET_MAGIC) {
+        return Ok(Some(InputFormat::Parquet));
+    }
+    if header.starts_with(&BIGWIG_MAGIC.to_le_bytes()) {
+        return Ok(Some(InputFormat::BigWig));
+    }
+    if header.starts_with(&BIGBED_MAGIC.to_le_bytes()) {
+        return Ok(Some(InputFormat::BigBed));
+    }
+    if Compression::from_magic(&header) != Compression::Uncompressed {
+        // BGZF can hold BAM or bgzipped text
+        let mut content = Vec::with_capacity(SNIFF_SIZE);
+        open_decompressed(path, 1)?
+            .take(SNIFF_SIZE as u64)
+            .read_to_end(&mut content)?;
+        if content.starts_with(BAM_MAGIC) {
+            return Ok(Some(InputFormat::Bam));
+        }
+        return Ok(get_text_format(&content));
+    }
+    Ok(get_text_format(&header))
+}
+
+fn get_text_format(content: &[u8]) -> Option<InputFormat> {
+    if content.starts_with(b"##fileformat=VCF") {
+        Some(InputFormat::Vcf)
+    } else if content.starts_with(b"## pairs format") {
+        Some(InputFormat::Pairs)
+    } else if content.starts_with(b"##gff-version") {
+        Some(InputFormat::Gff)
+    } else if SAM_HEADER_TAGS.iter().any(|tag| content.starts_with(tag)) {
+        Some(InputFormat::Sam)
+    } else if content.starts_with(b"@") {
+        Some(InputFormat::Fastq)
+    } else if content.starts_with(b">") {
+        Some(InputFormat::Fasta)
     } else {
-        panic!("Unsupported format")
+        None
     }
 }
 
@@ -72,23 +275,37 @@ pub(crate) async fn register_table(
     table_name: &str,
     format: InputFormat,
     read_options: Option<ReadOptions>,
-) -> String {
-    ctx.session.deregister_table(table_name).unwrap();
+) -> PyResult<String> {
+    ctx.session.deregister_table(table_name)?;
+    let staged_path = stage_input(ctx, path, &format)?;
+    let path = staged_path.as_str();
+    let bed_read_options = read_options
+        .as_ref()
+        .and_then(|options| options.bed_read_options.clone());
+    let gff_read_options = read_options
+        .as_ref()
+        .and_then(|options| options.gff_read_options.clone());
     match format {
         InputFormat::Parquet => ctx
             .session
             .register_parquet(table_name, path, ParquetReadOptions::new())
             .await
-            .unwrap(),
+            .map_err(value_error)?,
         InputFormat::Csv => {
-            let csv_read_options = CsvReadOptions::new() //FIXME: expose
-                .delimiter(b',')
-                .has_header(true);
-            ctx.session
-                .register_csv(table_name, path, csv_read_options)
-                .await
-                .unwrap()
+            let csv_read_options = match &read_options {
+                Some(options) => match options.clone().csv_read_options {
+                    Some(csv_read_options) => csv_read_options,
+                    _ => CsvReadOptions::default(),
+                },
+                _ => CsvReadOptions::default(),
+            };
+            register_csv_table(ctx, path, table_name, csv_read_options).await?
         },
+        // VCF files in a bucket with a registered object store are read through it
+        InputFormat::Vcf if is_remote(path) && has_object_store(ctx, path) => ctx
+            .register_exon_table(table_name, path, &format.to_string())
+            .await
+            .map_err(value_error)?,
         InputFormat::Vcf => {
             let vcf_read_options = match &read_options {
                 Some(options) => match options.clone().vcf_read_options {
@@ -101,56 +318,621 @@ pub(crate) async fn register_table(
                 path.to_string(),
                 vcf_read_options.info_fields,
                 vcf_read_options.format_fields,
-                vcf_read_options.thread_num,
+                // a per-read `thread_num` takes precedence over `bio.bgzf_threads`
+                vcf_read_options
+                    .thread_num
+                    .or(Some(bio_config(ctx).bgzf_threads)),
                 vcf_read_options.chunk_size,
                 vcf_read_options.concurrent_fetches,
             )
-            .unwrap();
+            .map_err(value_error)?;
+            let table_provider: Arc<dyn TableProvider> = match read_vcf_header(path) {
+                Ok(header) => Arc::new(VcfHeaderTableProvider::new(
+                    Arc::new(table_provider),
+                    header,
+                )),
+                Err(e) => {
+                    debug!("VCF header of {} not attached: {}", path, e);
+                    Arc::new(table_provider)
+                },
+            };
+            ctx.session
+                .register_table(table_name, table_provider)
+                .map_err(value_error)?;
+        },
+        InputFormat::BigWig | InputFormat::BigBed => {
+            let zoom_level = read_options
+                .and_then(|options| options.bigwig_read_options)
+                .and_then(|options| options.zoom_level);
+            let table_provider =
+                BbiTableProvider::new(path.to_string(), zoom_level).map_err(value_error)?;
+            ctx.session
+                .register_table(table_name, Arc::new(table_provider))
+                .map_err(value_error)?;
+        },
+        InputFormat::Paf | InputFormat::Sam => {
+            let table_provider = alignment_table(path, &format).map_err(value_error)?;
             ctx.session
                 .register_table(table_name, Arc::new(table_provider))
-                .expect("Failed to register VCF table");
+                .map_err(value_error)?;
+        },
+        // without read options BED and GFF/GTF files are read with the exon defaults
+        InputFormat::Bed => match bed_read_options {
+            Some(bed_read_options) => {
+                register_bed_table(ctx, path, table_name, &format, bed_read_options).await?
+            },
+            None => ctx
+                .register_exon_table(table_name, path, &format.to_string())
+                .await
+                .map_err(value_error)?,
+        },
+        InputFormat::Gff | InputFormat::Gtf => match gff_read_options {
+            Some(gff_read_options) => {
+                register_gff_table(ctx, path, table_name, &format, gff_read_options)
+                    .await
+                    .map_err(value_error)?
+            },
+            None => ctx
+                .register_exon_table(table_name, path, &format.to_string())
+                .await
+                .map_err(value_error)?,
+        },
+        InputFormat::Pairs | InputFormat::Bedpe => {
+            register_pairs_table(ctx, path, table_name, &format)
+                .await
+                .map_err(value_error)?
         },
-        InputFormat::Bam
-        | InputFormat::Cram
-        | InputFormat::Fastq
+        InputFormat::NarrowPeak | InputFormat::BroadPeak | InputFormat::BedGraph => {
+            register_bed_table(ctx, path, table_name, &format, BedReadOptions::default()).await?
+        },
+        InputFormat::Bam | InputFormat::Cram | InputFormat::Fastq | InputFormat::Fasta => ctx
+            .register_exon_table(table_name, path, &format.to_string())
+            .await
+            .map_err(value_error)?,
+        // index-based reads use range requests of the registered object store
+        InputFormat::IndexedVcf | InputFormat::IndexedBam => ctx
+            .register_exon_table(table_name, path, &format.to_string())
+            .await
+            .map_err(value_error)?,
+    };
+    Ok(table_name.to_string())
+}
+
+/// Errors of registering a table are caused by its path or read options.
+fn value_error(e: impl std::fmt::Display) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+/// Returns the full (multi-part) extension of the file name, e.g. `.tsv.gz`.
+/// Listing tables filter files by extension, so it has to match the actual one.
+pub(crate) fn get_file_extension(path: &str) -> String {
+    let file_name = path.split('/').last().unwrap();
+    match file_name.find('.') {
+        Some(i) => file_name[i..].to_string(),
+        None => "".to_string(),
+    }
+}
+
+/// Compression of text inputs, detected from the leading magic bytes of local files
+/// and from the file extension otherwise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Compression {
+    Uncompressed,
+    Gzip,
+    Bgzf,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+impl Compression {
+    pub(crate) fn detect(path: &str) -> Self {
+        let mut header = Vec::with_capacity(COMPRESSION_MAGIC_SIZE);
+        match File::open(path) {
+            Ok(file) if !path.contains("://") => {
+                match file
+                    .take(COMPRESSION_MAGIC_SIZE as u64)
+                    .read_to_end(&mut header)
+                {
+                    Ok(_) => Self::from_magic(&header),
+                    Err(_) => Self::from_extension(path),
+                }
+            },
+            _ => Self::from_extension(path),
+        }
+    }
+
+    fn from_magic(header: &[u8]) -> Self {
+        if header.starts_with(&GZIP_MAGIC) {
+            // BGZF members have the FEXTRA flag set and a "BC" extra subfield
+            if header.len() >= 14 && header[3] & 0x04 != 0 && &header[12..14] == b"BC" {
+                Compression::Bgzf
+            } else {
+                Compression::Gzip
+            }
+        } else if header.starts_with(&ZSTD_MAGIC) {
+            Compression::Zstd
+        } else if header.starts_with(BZIP2_MAGIC) {
+            Compression::Bzip2
+        } else if header.starts_with(&XZ_MAGIC) {
+            Compression::Xz
+        } else {
+            Compression::Uncompressed
+        }
+    }
+
+    fn from_extension(path: &str) -> Self {
+        let path = path.to_lowercase();
+        if path.ends_with(".bgz") {
+            Compression::Bgzf
+        } else if path.ends_with(".gz") {
+            Compression::Gzip
+        } else if path.ends_with(".bz2") {
+            Compression::Bzip2
+        } else if path.ends_with(".xz") {
+            Compression::Xz
+        } else if path.ends_with(".zst") {
+            Compression::Zstd
+        } else {
+            Compression::Uncompressed
+        }
+    }
+
+    fn file_compression_type(&self) -> FileCompressionType {
+        match self {
+            Compression::Gzip | Compression::Bgzf => FileCompressionType::GZIP,
+            Compression::Zstd => FileCompressionType::ZSTD,
+            Compression::Bzip2 => FileCompressionType::BZIP2,
+            Compression::Xz => FileCompressionType::XZ,
+            Compression::Uncompressed => FileCompressionType::UNCOMPRESSED,
+        }
+    }
+}
+
+pub(crate) fn get_compression_type(path: &str) -> FileCompressionType {
+    Compression::detect(path).file_compression_type()
+}
+
+/// Opens a local file with transparent decompression. BGZF is decompressed with
+/// `threads` workers, other formats are single-threaded.
+pub(crate) fn open_decompressed(
+    path: &str,
+    threads: usize,
+) -> std::io::Result<Box<dyn Read + Send>> {
+    let file = File::open(path)?;
+    Ok(match Compression::detect(path) {
+        Compression::Bgzf if threads > 1 => Box::new(bgzf::MultithreadedReader::with_worker_count(
+            NonZeroUsize::new(threads).unwrap(),
+            file,
+        )),
+        Compression::Gzip | Compression::Bgzf => Box::new(MultiGzDecoder::new(file)),
+        Compression::Zstd => Box::new(zstd::Decoder::new(file)?),
+        Compression::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(file)),
+        Compression::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(file)),
+        Compression::Uncompressed => Box::new(file),
+    })
+}
+
+/// Returns a path readable by the reader of `format`. Compressed local text files the
+/// reader can't decompress itself (compression not reflected by the file extension, or
+/// bz2/xz/zstd for exon and VCF readers) are decompressed into `bio.staging_dir` if
+/// `bio.stage_inputs` is set, otherwise an error is returned.
+fn stage_input(ctx: &ExonSession, path: &str, format: &InputFormat) -> PyResult<String> {
+    let native = match format {
+        InputFormat::Csv
+        | InputFormat::NarrowPeak
+        | InputFormat::BroadPeak
+        | InputFormat::BedGraph
+        | InputFormat::Pairs
+        | InputFormat::Bedpe
+        | InputFormat::Paf
+        | InputFormat::Sam => NativeDecompression::Any,
+        InputFormat::Vcf => NativeDecompression::Bgzf,
+        InputFormat::Fastq
         | InputFormat::Fasta
         | InputFormat::Bed
         | InputFormat::Gff
-        | InputFormat::Gtf => ctx
-            .register_exon_table(table_name, path, &format.to_string())
-            .await
-            .unwrap(),
-        InputFormat::IndexedVcf | InputFormat::IndexedBam => {
-            todo!("Indexed formats are not supported")
+        | InputFormat::Gtf => NativeDecompression::Extension,
+        _ => return Ok(path.to_string()),
+    };
+    if path.contains("://") || !Path::new(path).is_file() {
+        return Ok(path.to_string());
+    }
+    let compression = Compression::detect(path);
+    let by_extension = Compression::from_extension(path);
+    let supported = match (compression, native) {
+        (Compression::Uncompressed, _) => true,
+        // the VCF reader decompresses BGZF with `bio.bgzf_threads` threads
+        (Compression::Bgzf, NativeDecompression::Bgzf) => by_extension != Compression::Uncompressed,
+        (_, NativeDecompression::Any) => true,
+        (
+            Compression::Gzip | Compression::Bgzf | Compression::Zstd,
+            NativeDecompression::Extension,
+        ) => compression.file_compression_type() == by_extension.file_compression_type(),
+        _ => false,
+    };
+    if supported {
+        return Ok(path.to_string());
+    }
+    let config = bio_config(ctx);
+    if !config.stage_inputs {
+        return Err(PyValueError::new_err(format!(
+            "The {} reader can't decompress {:?}-compressed {}, decompress it first or set \
+             the bio.stage_inputs option to decompress it into bio.staging_dir",
+            format, compression, path
+        )));
+    }
+    let staging_dir = if config.staging_dir.is_empty() {
+        std::env::temp_dir().join(STAGING_DIR)
+    } else {
+        Path::new(&config.staging_dir).to_path_buf()
+    };
+    std::fs::create_dir_all(&staging_dir)?;
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    let file_name = path.split('/').last().unwrap();
+    let file_name = match by_extension {
+        Compression::Uncompressed => file_name,
+        _ => file_name
+            .rsplit_once('.')
+            .map(|(stem, _)| stem)
+            .unwrap_or(file_name),
+    };
+    let staged = staging_dir.join(format!("{:016x}_{}", hasher.finish(), file_name));
+    let source_modified = std::fs::metadata(path)?.modified()?;
+    let is_fresh = std::fs::metadata(&staged)
+        .and_then(|m| m.modified())
+        .is_ok_and(|staged_modified| staged_modified >= source_modified);
+    if !is_fresh {
+        debug!(
+            "Decompressing {:?} {} into {:?} with {} thread(s)",
+            compression, path, staged, config.bgzf_threads
+        );
+        let tmp = staged.with_extension("partial");
+        let copied = open_decompressed(path, config.bgzf_threads)
+            .and_then(|mut reader| std::io::copy(&mut reader, &mut File::create(&tmp)?));
+        if let Err(e) = copied {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(e.into());
+        }
+        std::fs::rename(&tmp, &staged)?;
+    }
+    Ok(staged.to_string_lossy().to_string())
+}
+
+fn bio_config(ctx: &ExonSession) -> BioConfig {
+    ctx.session
+        .state()
+        .config()
+        .options()
+        .extensions
+        .get::<BioConfig>()
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Compressions a reader decompresses on its own.
+enum NativeDecompression {
+    /// DataFusion CSV-based and line-based readers
+    Any,
+    /// BGZF with a `.gz`/`.bgz` extension
+    Bgzf,
+    /// gzip, BGZF and zstd, if the file extension says so
+    Extension,
+}
+
+/// Registers a delimited text file. Tab is the default delimiter for `.tsv` and `.txt`
+/// files, and the compression is inferred from the file extension unless set explicitly.
+async fn register_csv_table(
+    ctx: &ExonSession,
+    path: &str,
+    table_name: &str,
+    csv_read_options: CsvReadOptions,
+) -> PyResult<()> {
+    let file_extension = get_file_extension(path);
+    let delimiter = match &csv_read_options.delimiter {
+        Some(d) => d.as_bytes()[0],
+        None if [".tsv", ".txt"]
+            .iter()
+            .any(|ext| file_extension.to_lowercase().contains(ext)) =>
+        {
+            b'\t'
+        },
+        None => b',',
+    };
+    let compression = match &csv_read_options.compression {
+        Some(c) => c
+            .parse::<FileCompressionType>()
+            .map_err(|e| PyValueError::new_err(format!("Invalid compression {}: {}", c, e)))?,
+        None => get_compression_type(path),
+    };
+    let mut options = DataFusionCsvReadOptions::new()
+        .delimiter(delimiter)
+        .has_header(csv_read_options.has_header)
+        .file_extension(&file_extension)
+        .file_compression_type(compression);
+    if let Some(comment) = &csv_read_options.comment {
+        options = options.comment(comment.as_bytes()[0]);
+    }
+    let null_values = match csv_read_options.null_values {
+        Some(null_values) if !null_values.is_empty() => null_values,
+        _ => {
+            if let Some(schema) = &csv_read_options.schema {
+                options = options.schema(schema);
+            }
+            ctx.session.register_csv(table_name, path, options).await?;
+            return Ok(());
         },
     };
-    table_name.to_string()
+    // read all columns as strings, replace null markers and cast back to the target types
+    let schema = match csv_read_options.schema {
+        Some(schema) => schema.as_ref().clone(),
+        None => ctx
+            .session
+            .read_csv(path, options.clone())
+            .await?
+            .schema()
+            .as_arrow()
+            .clone(),
+    };
+    let raw_schema = Schema::new(
+        schema
+            .fields()
+            .iter()
+            .map(|f| Field::new(f.name(), DataType::Utf8, true))
+            .collect::<Vec<Field>>(),
+    );
+    let null_values = null_values.into_iter().map(lit).collect::<Vec<_>>();
+    let columns = schema
+        .fields()
+        .iter()
+        .map(|f| {
+            Ok(when(
+                ident(f.name()).in_list(null_values.clone(), false),
+                lit(ScalarValue::Utf8(None)),
+            )
+            .otherwise(ident(f.name()))?
+            .alias(f.name()))
+        })
+        .collect::<datafusion::common::Result<Vec<_>>>()?;
+    let max_records = options.schema_infer_max_records;
+    let df = ctx
+        .session
+        .read_csv(path, options.schema(&raw_schema))
+        .await?
+        .select(columns)?;
+    // string columns may only be strings because of the null markers
+    let types = match csv_read_options.schema {
+        Some(_) => schema
+            .fields()
+            .iter()
+            .map(|f| f.data_type().clone())
+            .collect(),
+        None => infer_types_without_null_markers(&df, &schema, max_records).await?,
+    };
+    let columns = schema
+        .fields()
+        .iter()
+        .zip(types)
+        .map(|(f, data_type)| cast(ident(f.name()), data_type).alias(f.name()))
+        .collect::<Vec<_>>();
+    ctx.session
+        .register_table(table_name, df.select(columns)?.into_view())?;
+    Ok(())
+}
+
+const STRING_CANDIDATE_TYPES: [DataType; 3] =
+    [DataType::Int64, DataType::Float64, DataType::Boolean];
+
+/// Re-infers the type of the columns `schema` has as `Utf8` from the first `max_records`
+/// rows of `df`, whose null markers are already replaced by nulls: the first of
+/// [`STRING_CANDIDATE_TYPES`] all non-null values can be cast to, `Utf8` otherwise.
+async fn infer_types_without_null_markers(
+    df: &DataFrame,
+    schema: &Schema,
+    max_records: usize,
+) -> datafusion::common::Result<Vec<DataType>> {
+    let strings = schema
+        .fields()
+        .iter()
+        .filter(|f| f.data_type() == &DataType::Utf8)
+        .map(|f| f.name().as_str())
+        .collect::<Vec<&str>>();
+    if strings.is_empty() {
+        return Ok(schema
+            .fields()
+            .iter()
+            .map(|f| f.data_type().clone())
+            .collect());
+    }
+    // for every string column: count(value) followed by count(try_cast(value)) per type
+    let counts = strings
+        .iter()
+        .flat_map(|name| {
+            std::iter::once(count(ident(*name))).chain(
+                STRING_CANDIDATE_TYPES
+                    .iter()
+                    .map(|t| count(try_cast(ident(*name), t.clone()))),
+            )
+        })
+        .enumerate()
+        .map(|(i, expr)| expr.alias(format!("count_{}", i)))
+        .collect::<Vec<_>>();
+    let batches = df
+        .clone()
+        .limit(0, Some(max_records))?
+        .aggregate(vec![], counts)?
+        .collect()
+        .await?;
+    let counts = batches[0]
+        .columns()
+        .iter()
+        .map(|c| c.as_primitive::<Int64Type>().value(0))
+        .collect::<Vec<i64>>();
+    let mut inferred = counts
+        .chunks(STRING_CANDIDATE_TYPES.len() + 1)
+        .map(|counts| {
+            STRING_CANDIDATE_TYPES
+                .iter()
+                .zip(&counts[1..])
+                .find(|(_, cast)| **cast == counts[0])
+                .map_or(DataType::Utf8, |(t, _)| t.clone())
+        });
+    Ok(schema
+        .fields()
+        .iter()
+        .map(|f| match f.data_type() {
+            DataType::Utf8 => inferred.next().unwrap(),
+            other => other.clone(),
+        })
+        .collect())
+}
+
+/// Expands glob patterns of local paths into sorted lists of files.
+/// Remote URLs and paths without wildcards are passed through.
+pub(crate) fn expand_paths(paths: Vec<String>) -> PyResult<Vec<String>> {
+    let mut expanded = Vec::with_capacity(paths.len());
+    for path in paths {
+        if path.contains("://") || !path.contains(['*', '?', '[']) {
+            expanded.push(path);
+            continue;
+        }
+        let mut matches = glob(&path)
+            .map_err(|e| PyValueError::new_err(format!("Invalid glob pattern {}: {}", path, e)))?
+            .filter_map(Result::ok)
+            .filter(|p| p.is_file())
+            .map(|p| p.to_string_lossy().to_string())
+            .collect::<Vec<String>>();
+        if matches.is_empty() {
+            return Err(PyValueError::new_err(format!(
+                "No files match pattern: {}",
+                path
+            )));
+        }
+        matches.sort();
+        expanded.append(&mut matches);
+    }
+    Ok(expanded)
+}
+
+/// File name without the directory, compression and format extensions,
+/// e.g. `/data/NA12878.hard-filtered.vcf.gz` -> `NA12878.hard-filtered`.
+pub(crate) fn get_sample_name(path: &str) -> String {
+    let mut file_name = path.split('/').last().unwrap_or(path).to_string();
+    for ext in COMPRESSION_EXTENSIONS {
+        if file_name.to_lowercase().ends_with(ext) {
+            file_name.truncate(file_name.len() - ext.len());
+            break;
+        }
+    }
+    match file_name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem.to_string(),
+        _ => file_name,
+    }
+}
+
+/// Registers many files of the same format as one table with additional `sample`
+/// (see [`get_sample_name`]) and `file` columns. Every file is scanned through a
+/// temporary `{table_name}_part{i}` table and the union keeps their partitions, so
+/// files are scanned in parallel. All files must have the schema of the first one.
+pub(crate) async fn register_multi_table(
+    ctx: &ExonSession,
+    paths: &[String],
+    table_name: &str,
+    format: InputFormat,
+    read_options: Option<ReadOptions>,
+) -> PyResult<()> {
+    let mut union: Option<DataFrame> = None;
+    for (i, path) in paths.iter().enumerate() {
+        let part_name = format!("{}_part{}", table_name, i);
+        register_table(ctx, path, &part_name, format.clone(), read_options.clone()).await?;
+        // the plan keeps the table provider, the name is only needed to build it
+        let df = ctx.session.table(&part_name).await;
+        ctx.session.deregister_table(&part_name)?;
+        let df = df?;
+        if let Some(union) = &union {
+            check_part_schema(union, &df, &paths[0], path)?;
+        }
+        let df = df
+            .with_column(SAMPLE_COLUMN, lit(get_sample_name(path)))?
+            .with_column(FILE_COLUMN, lit(path.as_str()))?;
+        union = Some(match union {
+            Some(union) => union.union(df)?,
+            None => df,
+        });
+    }
+    let union = union.ok_or_else(|| {
+        PyValueError::new_err(format!("No files to register as table: {}", table_name))
+    })?;
+    ctx.session.deregister_table(table_name)?;
+    ctx.session.register_table(table_name, union.into_view())?;
+    Ok(())
+}
+
+/// Fails if the columns of `part` differ from those of the first file of the union,
+/// ignoring the added `sample` and `file` columns.
+fn check_part_schema(union: &DataFrame, part: &DataFrame, first: &str, path: &str) -> PyResult<()> {
+    let expected = union
+        .schema()
+        .fields()
+        .iter()
+        .filter(|f| f.name() != SAMPLE_COLUMN && f.name() != FILE_COLUMN)
+        .map(|f| (f.name().as_str(), f.data_type()))
+        .collect::<Vec<_>>();
+    let actual = part
+        .schema()
+        .fields()
+        .iter()
+        .map(|f| (f.name().as_str(), f.data_type()))
+        .collect::<Vec<_>>();
+    if expected == actual {
+        return Ok(());
+    }
+    let describe = |fields: &[(&str, &DataType)]| {
+        fields
+            .iter()
+            .map(|(name, data_type)| format!("{}: {}", name, data_type))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    Err(PyValueError::new_err(format!(
+        "Schema of {} ({}) differs from the schema of {} ({})",
+        path,
+        describe(&actual),
+        first,
+        describe(&expected)
+    )))
 }
 
+/// Registers `df_path_or_table` as `default_table` if it points to a file,
+/// otherwise it is returned unchanged as the name of an already registered table.
 pub(crate) fn maybe_register_table(
     df_path_or_table: String,
     default_table: &String,
     read_options: Option<ReadOptions>,
     ctx: &ExonSession,
     rt: &Runtime,
-) -> String {
-    let ext: Vec<&str> = df_path_or_table.split('.').collect();
-    debug!("ext: {:?}", ext);
-    if ext.len() == 1 {
-        return df_path_or_table;
-    }
-    match ext.last() {
-        Some(_ext) => {
-            rt.block_on(register_table(
-                ctx,
-                &df_path_or_table,
-                default_table,
-                get_input_format(&df_path_or_table),
-                read_options,
-            ));
-            default_table.to_string()
-        },
-        _ => df_path_or_table,
+) -> PyResult<String> {
+    if !is_path(&df_path_or_table, ctx) {
+        debug!("Using registered table: {}", df_path_or_table);
+        return Ok(df_path_or_table);
+    }
+    let format = rt.block_on(detect_input_format(ctx, &df_path_or_table))?;
+    rt.block_on(register_table(
+        ctx,
+        &df_path_or_table,
+        default_table,
+        format,
+        read_options,
+    ))?;
+    Ok(default_table.to_string())
+}
+
+fn is_path(df_path_or_table: &str, ctx: &ExonSession) -> bool {
+    if ctx.session.table_exist(df_path_or_table).unwrap_or(false) {
+        return false;
     }
-    .to_string()
+    df_path_or_table.contains("://")
+        || df_path_or_table.contains('/')
+        || Path::new(df_path_or_table).is_file()
 }
@@ -0,0 +1,397 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use arrow::array::{
+    Array, ArrayRef, AsArray, Float64Builder, GenericStringArray, Int64Array, Int64Builder,
+    StringBuilder, StringViewArray,
+};
+use arrow_schema::DataType;
+use datafusion::common::{exec_err, Result, ScalarValue};
+use datafusion::logical_expr::{
+    ColumnarValue, ScalarUDF, ScalarUDFImpl, Signature, TypeSignature, Volatility,
+};
+
+const STRING_TYPES: [DataType; 3] = [DataType::Utf8, DataType::LargeUtf8, DataType::Utf8View];
+const DEFAULT_GENETIC_CODE: i64 = 1;
+
+/// Scalar functions of nucleotide sequences, accepting `Utf8`, `LargeUtf8` and
+/// `Utf8View` columns. Bases are compared case-insensitively.
+#[derive(Clone, Copy, Debug)]
+enum SequenceFunction {
+    /// `revcomp(sequence)`, the reverse complement, IUPAC codes included, keeping case
+    Revcomp,
+    /// `gc_content(sequence)`, the fraction of `G` and `C` bases
+    GcContent,
+    /// `translate_dna(sequence [, table])`, the protein of an NCBI genetic code (1 by
+    /// default). Codons with other bases than `ACGTU` are `X`, a trailing partial codon
+    /// is dropped.
+    Translate,
+    /// `complexity(sequence)`, the Shannon entropy of the `ACGT` composition in bits,
+    /// from 0 (homopolymer) to 2
+    Complexity,
+    /// `dust(sequence)`, the DUST low-complexity score of the triplets with `ACGT`
+    /// bases, `sum c_t (c_t - 1) / 2 / (l - 1)` for `l` triplets
+    Dust,
+    /// `hamming(a, b)`, `NULL` for sequences of different lengths
+    Hamming,
+    /// `edit_distance(a, b)`, the Levenshtein distance
+    EditDistance,
+}
+
+impl SequenceFunction {
+    fn name(&self) -> &'static str {
+        match self {
+            SequenceFunction::Revcomp => "revcomp",
+            SequenceFunction::GcContent => "gc_content",
+            SequenceFunction::Translate => "translate_dna",
+            SequenceFunction::Complexity => "complexity",
+            SequenceFunction::Dust => "dust",
+            SequenceFunction::Hamming => "hamming",
+            SequenceFunction::EditDistance => "edit_distance",
+        }
+    }
+
+    fn return_type(&self) -> DataType {
+        match self {
+            SequenceFunction::Revcomp | SequenceFunction::Translate => DataType::Utf8,
+            SequenceFunction::Hamming | SequenceFunction::EditDistance => DataType::Int64,
+            _ => DataType::Float64,
+        }
+    }
+
+    fn signature(&self) -> Signature {
+        let signatures = match self {
+            SequenceFunction::Hamming | SequenceFunction::EditDistance => STRING_TYPES
+                .iter()
+                .flat_map(|a| {
+                    STRING_TYPES
+                        .iter()
+                        .map(|b| TypeSignature::Exact(vec![a.clone(), b.clone()]))
+                })
+                .collect(),
+            SequenceFunction::Translate => STRING_TYPES
+                .iter()
+                .flat_map(|t| {
+                    [
+                        TypeSignature::Exact(vec![t.clone()]),
+                        TypeSignature::Exact(vec![t.clone(), DataType::Int64]),
+                    ]
+                })
+                .collect(),
+            _ => STRING_TYPES
+                .iter()
+                .map(|t| TypeSignature::Exact(vec![t.clone()]))
+                .collect(),
+        };
+        Signature::one_of(signatures, Volatility::Immutable)
+    }
+}
+
+enum SequenceArray<'a> {
+    LargeUtf8(&'a GenericStringArray<i64>),
+    Utf8View(&'a StringViewArray),
+    Utf8(&'a GenericStringArray<i32>),
+}
+
+impl<'a> SequenceArray<'a> {
+    fn try_new(array: &'a ArrayRef) -> Result<Self> {
+        match array.data_type() {
+            DataType::LargeUtf8 => Ok(SequenceArray::LargeUtf8(array.as_string::<i64>())),
+            DataType::Utf8View => Ok(SequenceArray::Utf8View(array.as_string_view())),
+            DataType::Utf8 => Ok(SequenceArray::Utf8(array.as_string::<i32>())),
+            other => exec_err!("Expected a string sequence column, got {}", other),
+        }
+    }
+
+    fn value(&self, i: usize) -> Option<&'a [u8]> {
+        match self {
+            SequenceArray::LargeUtf8(arr) => arr.is_valid(i).then(|| arr.value(i).as_bytes()),
+            SequenceArray::Utf8View(arr) => arr.is_valid(i).then(|| arr.value(i).as_bytes()),
+            SequenceArray::Utf8(arr) => arr.is_valid(i).then(|| arr.value(i).as_bytes()),
+        }
+    }
+}
+
+fn complement(base: u8) -> u8 {
+    let complement = match base.to_ascii_uppercase() {
+        b'A' => b'T',
+        b'C' => b'G',
+        b'G' => b'C',
+        b'T' | b'U' => b'A',
+        b'R' => b'Y',
+        b'Y' => b'R',
+        b'K' => b'M',
+        b'M' => b'K',
+        b'B' => b'V',
+        b'V' => b'B',
+        b'D' => b'H',
+        b'H' => b'D',
+        other => other,
+    };
+    if base.is_ascii_lowercase() {
+        complement.to_ascii_lowercase()
+    } else {
+        complement
+    }
+}
+
+/// Reverse complement of a nucleotide sequence, IUPAC codes included, keeping case.
+pub(crate) fn reverse_complement(seq: &[u8]) -> Vec<u8> {
+    seq.iter().rev().map(|b| complement(*b)).collect()
+}
+
+fn revcomp(seq: &[u8]) -> String {
+    String::from_utf8_lossy(&reverse_complement(seq)).into_owned()
+}
+
+fn gc_content(seq: &[u8]) -> Option<f64> {
+    let gc = seq
+        .iter()
+        .filter(|b| matches!(b.to_ascii_uppercase(), b'G' | b'C'))
+        .count();
+    (!seq.is_empty()).then(|| gc as f64 / seq.len() as f64)
+}
+
+/// Index of a base in `TCAG` order.
+fn codon_base(base: u8) -> Option<usize> {
+    match base.to_ascii_uppercase() {
+        b'T' | b'U' => Some(0),
+        b'C' => Some(1),
+        b'A' => Some(2),
+        b'G' => Some(3),
+        _ => None,
+    }
+}
+
+fn translate(seq: &[u8], code: &[u8; 64]) -> String {
+    seq.chunks_exact(3)
+        .map(|codon| {
+            codon
+                .iter()
+                .try_fold(0, |index, base| Some(index * 4 + codon_base(*base)?))
+                .map_or('X', |index| code[index] as char)
+        })
+        .collect()
+}
+
+/// NCBI genetic code `table`, the amino acids of the codons in `TCAG` order
+/// (`TTT`, `TTC`, `TTA`, `TTG`, `TCT`, ...).
+fn genetic_code(table: i64) -> Result<&'static [u8; 64]> {
+    Ok(match table {
+        1 | 11 => b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        2 => b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG",
+        3 => b"FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        4 => b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        5 => b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG",
+        6 => b"FFLLSSSSYYQQCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        9 => b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        10 => b"FFLLSSSSYY**CCCWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        12 => b"FFLLSSSSYY**CC*WLLLSPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        13 => b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSGGVVVVAAAADDEEGGGG",
+        14 => b"FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        15 => b"FFLLSSSSYY*QCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        16 => b"FFLLSSSSYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        21 => b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        22 => b"FFLLSS*SYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        23 => b"FF*LSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        24 => b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG",
+        25 => b"FFLLSSSSYY**CCGWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        _ => return exec_err!("Unknown genetic code {}", table),
+    })
+}
+
+/// 2-bit code of an `ACGT` base.
+fn base_code(base: u8) -> Option<usize> {
+    match base.to_ascii_uppercase() {
+        b'A' => Some(0),
+        b'C' => Some(1),
+        b'G' => Some(2),
+        b'T' => Some(3),
+        _ => None,
+    }
+}
+
+fn complexity(seq: &[u8]) -> Option<f64> {
+    let mut counts = [0usize; 4];
+    for code in seq.iter().filter_map(|b| base_code(*b)) {
+        counts[code] += 1;
+    }
+    let total = counts.iter().sum::<usize>() as f64;
+    (total > 0.0).then(|| {
+        counts
+            .iter()
+            .filter(|c| **c > 0)
+            .map(|c| {
+                let p = *c as f64 / total;
+                -p * p.log2()
+            })
+            .sum::<f64>()
+            .abs()
+    })
+}
+
+fn dust(seq: &[u8]) -> f64 {
+    let mut counts = [0u64; 64];
+    let mut triplets = 0;
+    for window in seq.windows(3) {
+        let triplet = window
+            .iter()
+            .try_fold(0, |index, base| Some(index * 4 + base_code(*base)?));
+        if let Some(triplet) = triplet {
+            counts[triplet] += 1;
+            triplets += 1;
+        }
+    }
+    if triplets < 2 {
+        return 0.0;
+    }
+    let score = counts
+        .iter()
+        .map(|c| c * c.saturating_sub(1) / 2)
+        .sum::<u64>();
+    score as f64 / (triplets - 1) as f64
+}
+
+fn hamming(a: &[u8], b: &[u8]) -> Option<i64> {
+    (a.len() == b.len()).then(|| {
+        a.iter()
+            .zip(b)
+            .filter(|(x, y)| !x.eq_ignore_ascii_case(y))
+            .count() as i64
+    })
+}
+
+fn edit_distance(a: &[u8], b: &[u8]) -> i64 {
+    let mut previous = (0..=b.len()).collect::<Vec<usize>>();
+    let mut current = vec![0; b.len() + 1];
+    for (i, x) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(!x.eq_ignore_ascii_case(y));
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()] as i64
+}
+
+#[derive(Debug)]
+struct SequenceUdf {
+    function: SequenceFunction,
+    signature: Signature,
+}
+
+impl SequenceUdf {
+    fn new(function: SequenceFunction) -> Self {
+        Self {
+            function,
+            signature: function.signature(),
+        }
+    }
+}
+
+impl ScalarUDFImpl for SequenceUdf {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        self.function.name()
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(self.function.return_type())
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        let scalar = args
+            .iter()
+            .all(|arg| matches!(arg, ColumnarValue::Scalar(_)));
+        let arrays = ColumnarValue::values_to_arrays(args)?;
+        let sequences = SequenceArray::try_new(&arrays[0])?;
+        let len = arrays[0].len();
+
+        let array: ArrayRef = match self.function {
+            SequenceFunction::Revcomp => {
+                let mut builder = StringBuilder::with_capacity(len, 0);
+                for i in 0..len {
+                    builder.append_option(sequences.value(i).map(revcomp));
+                }
+                Arc::new(builder.finish())
+            },
+            SequenceFunction::Translate => {
+                let tables = arrays
+                    .get(1)
+                    .map(|a| a.as_any().downcast_ref::<Int64Array>().unwrap());
+                let mut builder = StringBuilder::with_capacity(len, 0);
+                for i in 0..len {
+                    let table = match tables {
+                        Some(t) if t.is_null(i) => None,
+                        Some(t) => Some(t.value(i)),
+                        None => Some(DEFAULT_GENETIC_CODE),
+                    };
+                    match (sequences.value(i), table) {
+                        (Some(seq), Some(table)) => {
+                            builder.append_value(translate(seq, genetic_code(table)?))
+                        },
+                        _ => builder.append_null(),
+                    }
+                }
+                Arc::new(builder.finish())
+            },
+            SequenceFunction::Hamming | SequenceFunction::EditDistance => {
+                let others = SequenceArray::try_new(&arrays[1])?;
+                let mut builder = Int64Builder::with_capacity(len);
+                for i in 0..len {
+                    builder.append_option(match (sequences.value(i), others.value(i)) {
+                        (Some(a), Some(b)) => match self.function {
+                            SequenceFunction::Hamming => hamming(a, b),
+                            _ => Some(edit_distance(a, b)),
+                        },
+                        _ => None,
+                    });
+                }
+                Arc::new(builder.finish())
+            },
+            SequenceFunction::GcContent | SequenceFunction::Complexity | SequenceFunction::Dust => {
+                let mut builder = Float64Builder::with_capacity(len);
+                for i in 0..len {
+                    builder.append_option(sequences.value(i).and_then(|seq| match self.function {
+                        SequenceFunction::GcContent => gc_content(seq),
+                        SequenceFunction::Complexity => complexity(seq),
+                        _ => Some(dust(seq)),
+                    }));
+                }
+                Arc::new(builder.finish())
+            },
+        };
+        if scalar {
+            Ok(ColumnarValue::Scalar(ScalarValue::try_from_array(
+                &array, 0,
+            )?))
+        } else {
+            Ok(ColumnarValue::Array(array))
+        }
+    }
+}
+
+/// Sequence UDFs: `revcomp`, `gc_content`, `translate_dna`, `complexity`, `dust`,
+/// `hamming` and `edit_distance`.
+pub(crate) fn create_sequence_udfs() -> Vec<ScalarUDF> {
+    [
+        SequenceFunction::Revcomp,
+        SequenceFunction::GcContent,
+        SequenceFunction::Translate,
+        SequenceFunction::Complexity,
+        SequenceFunction::Dust,
+        SequenceFunction::Hamming,
+        SequenceFunction::EditDistance,
+    ]
+    .into_iter()
+    .map(|function| ScalarUDF::from(SequenceUdf::new(function)))
+    .collect()
+}
@@ -0,0 +1,760 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Write};
+use std::sync::Arc;
+
+use arrow::array::{Array, AsArray, Int64Array, MapArray, RecordBatch, StringArray};
+use arrow::datatypes::{Float64Type, Int64Type};
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use datafusion::common::{DataFusionError, Result};
+use exon::ExonSession;
+use log::debug;
+use noodles_sam::alignment::io::Write as _;
+use noodles_sam::alignment::RecordBuf;
+
+use crate::bbi::{write_bigwig, Run};
+use crate::option::OutputFormat;
+
+const VCF_FIXED_COLUMNS: [&str; 8] = [
+    "chrom", "start", "end", "id", "ref", "alt", "qual", "filter",
+];
+pub(crate) const VCF_HEADER_METADATA_KEY: &str = "vcf_header";
+const GFF_COLUMNS: [&str; 9] = [
+    "seqname",
+    "source",
+    "type",
+    "start",
+    "end",
+    "score",
+    "strand",
+    "phase",
+    "attributes",
+];
+const FASTQ_COLUMNS: [&str; 4] = ["name", "description", "sequence", "quality_scores"];
+const MISSING_VALUE: &str = ".";
+const BAM_COLUMNS: [&str; 11] = [
+    "name",
+    "flag",
+    "reference",
+    "start",
+    "mapping_quality",
+    "cigar",
+    "mate_reference",
+    "mate_start",
+    "sequence",
+    "quality_score",
+    "tags",
+];
+const TEMPLATE_LENGTH_COLUMN: &str = "template_length";
+const MISSING_MAPPING_QUALITY: &str = "255";
+const MISSING_SAM_VALUE: &str = "*";
+/// Predefined SAM tags with an integer (`i`) or character (`A`) type, all other
+/// tags with a string value are written with the `Z` type
+const SAM_INTEGER_TAGS: [&str; 19] = [
+    "AM", "AS", "CM", "CP", "FI", "H0", "H1", "H2", "HI", "IH", "MN", "MQ", "NH", "NM", "OP", "PQ",
+    "SM", "TC", "UQ",
+];
+const SAM_CHARACTER_TAGS: [&str; 1] = ["TS"];
+
+/// Output file handle, BGZF-compressed when the path ends with `.gz` or `.bgz`.
+pub(crate) enum SinkWriter {
+    Plain(BufWriter<File>),
+    Bgzf(noodles_bgzf::Writer<File>),
+}
+
+impl SinkWriter {
+    pub(crate) fn create(path: &str) -> Result<Self> {
+        let file = File::create(path)?;
+        let lower = path.to_lowercase();
+        if lower.ends_with(".gz") || lower.ends_with(".bgz") {
+            Ok(SinkWriter::Bgzf(noodles_bgzf::Writer::new(file)))
+        } else {
+            Ok(SinkWriter::Plain(BufWriter::new(file)))
+        }
+    }
+
+    pub(crate) fn finish(self) -> Result<()> {
+        match self {
+            SinkWriter::Plain(mut w) => w.flush()?,
+            SinkWriter::Bgzf(mut w) => {
+                w.try_finish()?;
+            },
+        }
+        Ok(())
+    }
+}
+
+impl Write for SinkWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            SinkWriter::Plain(w) => w.write(buf),
+            SinkWriter::Bgzf(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            SinkWriter::Plain(w) => w.flush(),
+            SinkWriter::Bgzf(w) => w.flush(),
+        }
+    }
+}
+
+/// Writes a stream of record batches to `path` in the given text format.
+/// `contigs` are written as `##contig` lines of a reconstructed VCF header.
+pub(crate) fn write_batches<I>(
+    schema: SchemaRef,
+    batches: I,
+    path: &str,
+    format: OutputFormat,
+    contigs: &[String],
+) -> Result<usize>
+where
+    I: Iterator<Item = Result<RecordBatch>>,
+{
+    let mut writer = SinkWriter::create(path)?;
+    write_header(&mut writer, &schema, &format, contigs)?;
+    let mut rows = 0;
+    for batch in batches {
+        let batch = batch?;
+        rows += batch.num_rows();
+        match format {
+            OutputFormat::Vcf => write_vcf_batch(&mut writer, &batch)?,
+            OutputFormat::Bed => write_delimited_batch(&mut writer, &batch, None)?,
+            OutputFormat::BedGraph => write_delimited_batch(&mut writer, &batch, Some(4))?,
+            OutputFormat::Gff => write_gff_batch(&mut writer, &batch)?,
+            OutputFormat::Fastq => write_fastq_batch(&mut writer, &batch)?,
+            OutputFormat::BigWig => unreachable!("bigWig output is written by write_track"),
+        }
+    }
+    writer.finish()?;
+    debug!("Written {} rows to {} ({})", rows, path, format);
+    Ok(rows)
+}
+
+fn write_header(
+    writer: &mut SinkWriter,
+    schema: &SchemaRef,
+    format: &OutputFormat,
+    contigs: &[String],
+) -> Result<()> {
+    match format {
+        OutputFormat::BigWig => {
+            return Err(DataFusionError::Plan(
+                "bigWig output requires chromosome sizes, use write_track".to_string(),
+            ))
+        },
+        OutputFormat::Vcf => {
+            require_columns(schema, &VCF_FIXED_COLUMNS)?;
+            writer.write_all(vcf_header(schema, contigs).as_bytes())?
+        },
+        OutputFormat::Gff => {
+            require_columns(schema, &GFF_COLUMNS)?;
+            writer.write_all(b"##gff-version 3\n")?
+        },
+        OutputFormat::Fastq => require_columns(schema, &["name", "sequence", "quality_scores"])?,
+        OutputFormat::Bed => {
+            if schema.fields().len() < 3 {
+                return Err(DataFusionError::Plan(
+                    "BED output requires at least 3 columns (chrom, start, end)".to_string(),
+                ));
+            }
+        },
+        OutputFormat::BedGraph => {
+            if schema.fields().len() != 4 {
+                return Err(DataFusionError::Plan(format!(
+                    "bedGraph output requires exactly 4 columns (chrom, start, end, value), got {}",
+                    schema.fields().len()
+                )));
+            }
+        },
+    }
+    Ok(())
+}
+
+fn require_columns(schema: &SchemaRef, columns: &[&str]) -> Result<()> {
+    let missing: Vec<&str> = columns
+        .iter()
+        .filter(|c| schema.field_with_name(c).is_err())
+        .copied()
+        .collect();
+    if !missing.is_empty() {
+        return Err(DataFusionError::Plan(format!(
+            "Missing required columns: {}",
+            missing.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Whether writing `schema` as VCF reconstructs the header, and so needs the
+/// contig names of the data for its `##contig` lines.
+pub(crate) fn needs_vcf_contigs(schema: &SchemaRef, format: &OutputFormat) -> bool {
+    matches!(format, OutputFormat::Vcf) && !schema.metadata().contains_key(VCF_HEADER_METADATA_KEY)
+}
+
+/// Collects the distinct values of the `chrom` column in order of first appearance.
+pub(crate) fn distinct_contigs<I>(batches: I) -> Result<Vec<String>>
+where
+    I: Iterator<Item = Result<RecordBatch>>,
+{
+    let mut seen = HashSet::new();
+    let mut contigs = Vec::new();
+    for batch in batches {
+        let batch = batch?;
+        let chrom = column(&batch, "chrom")?;
+        for i in 0..batch.num_rows() {
+            let contig = format_value(chrom, i, ",")?;
+            if seen.insert(contig.clone()) {
+                contigs.push(contig);
+            }
+        }
+    }
+    Ok(contigs)
+}
+
+/// Rebuilds a VCF header. The meta-information lines of the original header are
+/// reused if it was stored in the schema metadata, otherwise `##contig` lines are
+/// written for `contigs` and INFO lines are derived from the non-fixed columns.
+/// FORMAT and sample columns are not written, so the header line has 8 columns.
+fn vcf_header(schema: &SchemaRef, contigs: &[String]) -> String {
+    let mut header = String::new();
+    if let Some(original) = schema.metadata().get(VCF_HEADER_METADATA_KEY) {
+        for line in original.lines().filter(|line| line.starts_with("##")) {
+            header.push_str(line);
+            header.push('\n');
+        }
+    } else {
+        header.push_str("##fileformat=VCFv4.3\n");
+        for contig in contigs {
+            header.push_str(&format!("##contig=<ID={}>\n", contig));
+        }
+        for field in info_fields(schema) {
+            let (number, ty) = vcf_info_type(field.data_type());
+            let description = field
+                .metadata()
+                .get("description")
+                .cloned()
+                .unwrap_or_else(|| field.name().to_string());
+            header.push_str(&format!(
+                "##INFO=<ID={},Number={},Type={},Description=\"{}\">\n",
+                field.name(),
+                number,
+                ty,
+                description.replace('"', "\\\"")
+            ));
+        }
+    }
+    header.push_str("#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n");
+    header
+}
+
+fn info_fields(schema: &SchemaRef) -> Vec<&arrow_schema::Field> {
+    schema
+        .fields()
+        .iter()
+        .filter(|f| !VCF_FIXED_COLUMNS.contains(&f.name().as_str()))
+        .map(|f| f.as_ref())
+        .collect()
+}
+
+fn vcf_info_type(data_type: &DataType) -> (&'static str, &'static str) {
+    match data_type {
+        DataType::List(f) | DataType::LargeList(f) => (".", vcf_info_type(f.data_type()).1),
+        DataType::Boolean => ("0", "Flag"),
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 => ("1", "Integer"),
+        DataType::UInt8 | DataType::UInt16 | DataType::UInt32 | DataType::UInt64 => {
+            ("1", "Integer")
+        },
+        DataType::Float16 | DataType::Float32 | DataType::Float64 => ("1", "Float"),
+        _ => ("1", "String"),
+    }
+}
+
+fn write_vcf_batch(writer: &mut SinkWriter, batch: &RecordBatch) -> Result<()> {
+    let schema = batch.schema();
+    let columns: Vec<&dyn Array> = ["chrom", "start", "id", "ref", "alt", "qual", "filter"]
+        .iter()
+        .map(|c| column(batch, c))
+        .collect::<Result<_>>()?;
+    let info: Vec<(&arrow_schema::Field, &dyn Array)> = info_fields(&schema)
+        .into_iter()
+        .map(|f| (f, batch.column_by_name(f.name()).unwrap().as_ref()))
+        .collect();
+    for i in 0..batch.num_rows() {
+        let mut fields: Vec<String> = Vec::with_capacity(8);
+        for (j, array) in columns.iter().enumerate() {
+            let sep = if j == 4 { "," } else { ";" };
+            fields.push(format_value(*array, i, sep)?);
+        }
+        fields.push(format_info(&info, i)?);
+        writeln!(writer, "{}", fields.join("\t"))?;
+    }
+    Ok(())
+}
+
+fn format_info(info: &[(&arrow_schema::Field, &dyn Array)], i: usize) -> Result<String> {
+    let mut entries = Vec::new();
+    for (field, array) in info {
+        if array.is_null(i) {
+            continue;
+        }
+        let key = field.name().to_string();
+        match field.data_type() {
+            DataType::Boolean => {
+                if array.as_boolean().value(i) {
+                    entries.push(key);
+                }
+            },
+            _ => entries.push(format!("{}={}", key, format_value(*array, i, ",")?)),
+        }
+    }
+    if entries.is_empty() {
+        Ok(MISSING_VALUE.to_string())
+    } else {
+        Ok(entries.join(";"))
+    }
+}
+
+fn write_delimited_batch(
+    writer: &mut SinkWriter,
+    batch: &RecordBatch,
+    num_columns: Option<usize>,
+) -> Result<()> {
+    let num_columns = num_columns.unwrap_or(batch.num_columns());
+    let columns = &batch.columns()[..num_columns];
+    for i in 0..batch.num_rows() {
+        let fields = columns
+            .iter()
+            .map(|c| format_value(c.as_ref(), i, ","))
+            .collect::<Result<Vec<String>>>()?;
+        writeln!(writer, "{}", fields.join("\t"))?;
+    }
+    Ok(())
+}
+
+fn write_gff_batch(writer: &mut SinkWriter, batch: &RecordBatch) -> Result<()> {
+    let columns: Vec<&dyn Array> = GFF_COLUMNS
+        .iter()
+        .map(|c| column(batch, c))
+        .collect::<Result<_>>()?;
+    for i in 0..batch.num_rows() {
+        let mut fields = Vec::with_capacity(GFF_COLUMNS.len());
+        for array in &columns[..8] {
+            fields.push(format_value(*array, i, ",")?);
+        }
+        fields.push(format_gff_attributes(columns[8], i)?);
+        writeln!(writer, "{}", fields.join("\t"))?;
+    }
+    Ok(())
+}
+
+fn format_gff_attributes(array: &dyn Array, i: usize) -> Result<String> {
+    if array.is_null(i) {
+        return Ok(MISSING_VALUE.to_string());
+    }
+    match array.data_type() {
+        DataType::Map(_, _) => {
+            let map = array.as_any().downcast_ref::<MapArray>().unwrap();
+            let entries = map.value(i);
+            let keys = entries.column(0);
+            let values = entries.column(1);
+            let attributes = (0..entries.len())
+                .filter(|j| !values.is_null(*j))
+                .map(|j| {
+                    Ok(format!(
+                        "{}={}",
+                        format_value(keys.as_ref(), j, ",")?,
+                        format_value(values.as_ref(), j, ",")?
+                    ))
+                })
+                .collect::<Result<Vec<String>>>()?;
+            if attributes.is_empty() {
+                Ok(MISSING_VALUE.to_string())
+            } else {
+                Ok(attributes.join(";"))
+            }
+        },
+        _ => format_value(array, i, ","),
+    }
+}
+
+fn write_fastq_batch(writer: &mut SinkWriter, batch: &RecordBatch) -> Result<()> {
+    let name = column(batch, FASTQ_COLUMNS[0])?;
+    let description = batch.column_by_name(FASTQ_COLUMNS[1]);
+    let sequence = column(batch, FASTQ_COLUMNS[2])?;
+    let quality = column(batch, FASTQ_COLUMNS[3])?;
+    for i in 0..batch.num_rows() {
+        let name = format_value(name, i, ",")?;
+        match description {
+            Some(d) if !d.is_null(i) && !format_value(d.as_ref(), i, ",")?.is_empty() => {
+                writeln!(writer, "@{} {}", name, format_value(d.as_ref(), i, ",")?)?
+            },
+            _ => writeln!(writer, "@{}", name)?,
+        }
+        writeln!(writer, "{}", format_value(sequence, i, ",")?)?;
+        writeln!(writer, "+")?;
+        writeln!(writer, "{}", format_value(quality, i, ",")?)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a dyn Array> {
+    batch
+        .column_by_name(name)
+        .map(|c| c.as_ref())
+        .ok_or_else(|| DataFusionError::Plan(format!("Missing required column: {}", name)))
+}
+
+/// Formats a single value as text. Nulls become `.` and list elements are joined with `sep`.
+pub(crate) fn format_value(array: &dyn Array, i: usize, sep: &str) -> Result<String> {
+    if array.is_null(i) {
+        return Ok(MISSING_VALUE.to_string());
+    }
+    match array.data_type() {
+        DataType::List(_) => {
+            let values = array.as_list::<i32>().value(i);
+            join_values(values.as_ref(), sep)
+        },
+        DataType::LargeList(_) => {
+            let values = array.as_list::<i64>().value(i);
+            join_values(values.as_ref(), sep)
+        },
+        _ => {
+            let options = FormatOptions::default().with_null(MISSING_VALUE);
+            let formatter = ArrayFormatter::try_new(array, &options)?;
+            Ok(formatter.value(i).to_string())
+        },
+    }
+}
+
+fn join_values(values: &dyn Array, sep: &str) -> Result<String> {
+    if values.is_empty() {
+        return Ok(MISSING_VALUE.to_string());
+    }
+    Ok((0..values.len())
+        .map(|j| format_value(values, j, sep))
+        .collect::<Result<Vec<String>>>()?
+        .join(sep))
+}
+
+/// Reads the SAM header of an existing BAM file, used to preserve it in the output.
+pub(crate) fn read_bam_header(path: &str) -> Result<noodles_sam::Header> {
+    let mut reader = noodles_bam::io::reader::Builder::default().build_from_path(path)?;
+    Ok(reader.read_header()?)
+}
+
+/// Builds a query returning `chrom`, `start`, `end` and `value` of a signal table
+/// sorted by chromosome name and start, the order of bigWig chromosome ids.
+pub(crate) fn track_query(table_name: &str, columns: &[String], value_column: &str) -> String {
+    format!(
+        "SELECT CAST(\"{}\" AS VARCHAR) AS chrom, CAST(\"{}\" AS BIGINT) AS start, \
+         CAST(\"{}\" AS BIGINT) AS \"end\", CAST(\"{}\" AS DOUBLE) AS value \
+         FROM {} ORDER BY chrom, start",
+        columns[0], columns[1], columns[2], value_column, table_name
+    )
+}
+
+/// Writes rows of `track_query` as a bedGraph or bigWig track. Intervals are clipped
+/// to `chrom_sizes`, rows with null values are skipped and adjacent intervals with
+/// equal values are merged. Returns the number of written intervals.
+pub(crate) fn write_track<I>(
+    batches: I,
+    path: &str,
+    mut chrom_sizes: Vec<(String, u32)>,
+    format: OutputFormat,
+) -> Result<usize>
+where
+    I: Iterator<Item = Result<RecordBatch>>,
+{
+    chrom_sizes.sort();
+    chrom_sizes.dedup_by(|a, b| a.0 == b.0);
+    let chrom_ids = chrom_sizes
+        .iter()
+        .enumerate()
+        .map(|(i, (name, _))| (name.clone(), i as u32))
+        .collect::<HashMap<String, u32>>();
+    let mut runs: Vec<Run> = Vec::new();
+    for batch in batches {
+        let batch = batch?;
+        let chroms = batch.column(0).as_string::<i32>();
+        let starts = batch.column(1).as_primitive::<Int64Type>();
+        let ends = batch.column(2).as_primitive::<Int64Type>();
+        let values = batch.column(3).as_primitive::<Float64Type>();
+        for i in 0..batch.num_rows() {
+            if chroms.is_null(i) || starts.is_null(i) || ends.is_null(i) || values.is_null(i) {
+                continue;
+            }
+            let chrom_id = *chrom_ids.get(chroms.value(i)).ok_or_else(|| {
+                DataFusionError::Plan(format!(
+                    "Chromosome {} not found in chromosome sizes",
+                    chroms.value(i)
+                ))
+            })?;
+            let size = chrom_sizes[chrom_id as usize].1 as i64;
+            let run = Run {
+                chrom_id,
+                start: starts.value(i).clamp(0, size) as u32,
+                end: ends.value(i).clamp(0, size) as u32,
+                value: values.value(i) as f32,
+            };
+            if run.start < run.end {
+                push_run(&mut runs, run, &chrom_sizes)?;
+            }
+        }
+    }
+    match format {
+        OutputFormat::BigWig => write_bigwig(path, &chrom_sizes, &runs)?,
+        OutputFormat::BedGraph => {
+            let mut writer = SinkWriter::create(path)?;
+            for run in &runs {
+                writeln!(
+                    writer,
+                    "{}\t{}\t{}\t{}",
+                    chrom_sizes[run.chrom_id as usize].0, run.start, run.end, run.value
+                )?;
+            }
+            writer.finish()?;
+        },
+        _ => {
+            return Err(DataFusionError::Plan(format!(
+                "Unsupported track format: {}",
+                format
+            )))
+        },
+    }
+    debug!("Written {} intervals to {} ({})", runs.len(), path, format);
+    Ok(runs.len())
+}
+
+fn push_run(runs: &mut Vec<Run>, run: Run, chrom_sizes: &[(String, u32)]) -> Result<()> {
+    match runs.last_mut() {
+        Some(last) if last.chrom_id == run.chrom_id && run.start < last.end => {
+            Err(DataFusionError::Plan(format!(
+                "Overlapping intervals at {}:{}, a track requires non-overlapping intervals",
+                chrom_sizes[run.chrom_id as usize].0, run.start
+            )))
+        },
+        Some(last)
+            if last.chrom_id == run.chrom_id
+                && last.end == run.start
+                && last.value == run.value =>
+        {
+            last.end = run.end;
+            Ok(())
+        },
+        _ => {
+            runs.push(run);
+            Ok(())
+        },
+    }
+}
+
+/// Builds a query returning `table_name` sorted by the reference sequence order
+/// of `header` and alignment start. The order is taken from `index_table`, a
+/// `(reference, reference_index)` table registered from the header. Unmapped
+/// records are placed last.
+pub(crate) fn coordinate_sorted_query(
+    ctx: &ExonSession,
+    table_name: &str,
+    index_table: &str,
+    header: &noodles_sam::Header,
+) -> Result<String> {
+    let references = header.reference_sequences();
+    if references.is_empty() {
+        return Ok(format!("SELECT * FROM {} ORDER BY start", table_name));
+    }
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("reference", DataType::Utf8, false),
+        Field::new("reference_index", DataType::Int64, false),
+    ]));
+    let batch = RecordBatch::try_new(
+        schema,
+        vec![
+            Arc::new(StringArray::from_iter_values(
+                references.keys().map(|name| name.to_string()),
+            )),
+            Arc::new(Int64Array::from_iter_values(0..references.len() as i64)),
+        ],
+    )?;
+    ctx.session.deregister_table(index_table)?;
+    ctx.session.register_batch(index_table, batch)?;
+    Ok(format!(
+        "SELECT t.* FROM {} t LEFT JOIN {} r ON t.reference = r.reference \
+         ORDER BY COALESCE(r.reference_index, {}), t.start",
+        table_name,
+        index_table,
+        references.len()
+    ))
+}
+
+/// Writes a stream of record batches with the BAM table schema to a BGZF BAM file.
+/// Batches are converted to SAM text and parsed against `header`, so reference
+/// names must be present in the header. The output is indexed if `index` is set.
+pub(crate) fn write_bam<I>(
+    batches: I,
+    path: &str,
+    header: &noodles_sam::Header,
+    index: bool,
+) -> Result<usize>
+where
+    I: Iterator<Item = Result<RecordBatch>>,
+{
+    let mut writer = noodles_bam::io::Writer::new(File::create(path)?);
+    writer.write_header(header)?;
+    let mut record = RecordBuf::default();
+    let mut rows = 0;
+    for batch in batches {
+        let batch = batch?;
+        let sam = format_sam_batch(&batch)?;
+        let mut reader = noodles_sam::io::Reader::new(Cursor::new(sam));
+        loop {
+            match reader.read_record_buf(header, &mut record) {
+                Ok(0) => break,
+                Ok(_) => {
+                    writer.write_alignment_record(header, &record)?;
+                    rows += 1;
+                },
+                Err(e) => {
+                    return Err(DataFusionError::Execution(format!(
+                        "Invalid alignment record {}: {}",
+                        rows + 1,
+                        e
+                    )))
+                },
+            }
+        }
+    }
+    writer.try_finish()?;
+    drop(writer);
+    if index {
+        let index = noodles_bam::fs::index(path)?;
+        noodles_bam::bai::fs::write(format!("{}.bai", path), &index)?;
+        debug!("Written BAM index for {}", path);
+    }
+    debug!("Written {} alignment records to {}", rows, path);
+    Ok(rows)
+}
+
+/// Formats a batch with the BAM table schema as SAM text lines.
+fn format_sam_batch(batch: &RecordBatch) -> Result<Vec<u8>> {
+    let columns: Vec<&dyn Array> = BAM_COLUMNS[..10]
+        .iter()
+        .map(|c| column(batch, c))
+        .collect::<Result<_>>()?;
+    let tags = batch.column_by_name(BAM_COLUMNS[10]);
+    let template_length = batch.column_by_name(TEMPLATE_LENGTH_COLUMN);
+    let mut sam = Vec::new();
+    for i in 0..batch.num_rows() {
+        let reference = sam_value(columns[2], i)?;
+        let mate_reference = match sam_value(columns[6], i)? {
+            m if m != MISSING_SAM_VALUE && m == reference => "=".to_string(),
+            m => m,
+        };
+        let mapping_quality = match format_value(columns[4], i, ",")? {
+            m if m == MISSING_VALUE => MISSING_MAPPING_QUALITY.to_string(),
+            m => m,
+        };
+        let mut fields = vec![
+            sam_value(columns[0], i)?,
+            format_value(columns[1], i, ",")?,
+            reference,
+            sam_position(columns[3], i)?,
+            mapping_quality,
+            sam_value(columns[5], i)?,
+            mate_reference,
+            sam_position(columns[7], i)?,
+            match template_length {
+                Some(template_length) => sam_position(template_length.as_ref(), i)?,
+                None => "0".to_string(),
+            },
+            sam_value(columns[8], i)?,
+            sam_quality_scores(columns[9], i)?,
+        ];
+        if let Some(tags) = tags {
+            fields.extend(sam_tags(tags.as_ref(), i)?);
+        }
+        writeln!(sam, "{}", fields.join("\t"))?;
+    }
+    Ok(sam)
+}
+
+fn sam_value(array: &dyn Array, i: usize) -> Result<String> {
+    match format_value(array, i, ",")? {
+        v if v == MISSING_VALUE || v.is_empty() => Ok(MISSING_SAM_VALUE.to_string()),
+        v => Ok(v),
+    }
+}
+
+fn sam_position(array: &dyn Array, i: usize) -> Result<String> {
+    match format_value(array, i, ",")? {
+        v if v == MISSING_VALUE => Ok("0".to_string()),
+        v => Ok(v),
+    }
+}
+
+/// Quality scores are either a Phred+33 string or a list of raw scores.
+fn sam_quality_scores(array: &dyn Array, i: usize) -> Result<String> {
+    if array.is_null(i) {
+        return Ok(MISSING_SAM_VALUE.to_string());
+    }
+    match array.data_type() {
+        DataType::List(_) | DataType::LargeList(_) => {
+            let scores = format_value(array, i, ",")?;
+            if scores == MISSING_VALUE {
+                return Ok(MISSING_SAM_VALUE.to_string());
+            }
+            scores
+                .split(',')
+                .map(|q| {
+                    q.parse::<u8>()
+                        .map(|q| char::from(q.saturating_add(33)))
+                        .map_err(|e| DataFusionError::Execution(e.to_string()))
+                })
+                .collect()
+        },
+        _ => sam_value(array, i),
+    }
+}
+
+/// Formats optional fields stored as a list of `{tag, value}` structs as `TAG:TYPE:VALUE`.
+/// The type follows the value column if it is numeric, and the SAM specification
+/// of predefined tags otherwise, so string values such as `MD:Z:100` keep their type.
+fn sam_tags(array: &dyn Array, i: usize) -> Result<Vec<String>> {
+    if array.is_null(i) {
+        return Ok(vec![]);
+    }
+    let entries = match array.data_type() {
+        DataType::List(_) => array.as_list::<i32>().value(i),
+        DataType::LargeList(_) => array.as_list::<i64>().value(i),
+        _ => return Ok(vec![]),
+    };
+    let entries = entries.as_struct();
+    let (tags, values) = (entries.column(0), entries.column(1));
+    let mut fields = Vec::with_capacity(entries.len());
+    for j in 0..entries.len() {
+        if values.is_null(j) {
+            continue;
+        }
+        let tag = format_value(tags.as_ref(), j, ",")?;
+        let ty = sam_tag_type(&tag, values.data_type());
+        fields.push(format!(
+            "{}:{}:{}",
+            tag,
+            ty,
+            format_value(values.as_ref(), j, ",")?
+        ));
+    }
+    Ok(fields)
+}
+
+fn sam_tag_type(tag: &str, value_type: &DataType) -> &'static str {
+    match value_type {
+        t if t.is_integer() => "i",
+        t if t.is_floating() => "f",
+        _ if SAM_INTEGER_TAGS.contains(&tag) => "i",
+        _ if SAM_CHARACTER_TAGS.contains(&tag) => "A",
+        _ => "Z",
+    }
+}
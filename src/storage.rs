@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use exon::ExonSession;
+use log::info;
+use object_store::aws::{AmazonS3Builder, AmazonS3ConfigKey};
+use object_store::gcp::{GoogleCloudStorageBuilder, GoogleConfigKey};
+use object_store::http::HttpBuilder;
+use object_store::{ClientConfigKey, ObjectStore};
+use pyo3::exceptions::PyValueError;
+use pyo3::PyResult;
+use url::Url;
+
+/// Builds an object store for the bucket (or host) of `url`. Credentials and endpoints
+/// are read from the environment (e.g. `AWS_ACCESS_KEY_ID`, `AWS_ENDPOINT`) and can be
+/// overridden with `options`, which take the `object_store` configuration keys, e.g.
+/// `endpoint`, `region`, `access_key_id`, `secret_access_key` and `allow_http` for
+/// S3-compatible stores like MinIO.
+fn build_object_store(
+    url: &Url,
+    options: &HashMap<String, String>,
+) -> PyResult<Arc<dyn ObjectStore>> {
+    let to_py_err = |e: object_store::Error| PyValueError::new_err(e.to_string());
+    let store: Arc<dyn ObjectStore> = match url.scheme() {
+        "s3" | "s3a" => {
+            let mut builder = AmazonS3Builder::from_env().with_url(url.as_str());
+            for (key, value) in options {
+                builder = builder
+                    .with_config(key.parse::<AmazonS3ConfigKey>().map_err(to_py_err)?, value);
+            }
+            Arc::new(builder.build().map_err(to_py_err)?)
+        },
+        "gs" => {
+            let mut builder = GoogleCloudStorageBuilder::from_env().with_url(url.as_str());
+            for (key, value) in options {
+                builder =
+                    builder.with_config(key.parse::<GoogleConfigKey>().map_err(to_py_err)?, value);
+            }
+            Arc::new(builder.build().map_err(to_py_err)?)
+        },
+        "http" | "https" => {
+            let mut builder = HttpBuilder::new().with_url(store_url(url).as_str());
+            for (key, value) in options {
+                builder =
+                    builder.with_config(key.parse::<ClientConfigKey>().map_err(to_py_err)?, value);
+            }
+            Arc::new(builder.build().map_err(to_py_err)?)
+        },
+        scheme => {
+            return Err(PyValueError::new_err(format!(
+                "Unsupported object store scheme: {}",
+                scheme
+            )))
+        },
+    };
+    Ok(store)
+}
+
+/// Object stores are registered per scheme and bucket/host, paths are ignored.
+fn store_url(url: &Url) -> Url {
+    let mut store_url = url.clone();
+    store_url.set_path("");
+    store_url.set_query(None);
+    store_url.set_fragment(None);
+    store_url
+}
+
+/// Registers an object store in the session registry, so all tables with paths
+/// under the `url` bucket/host are read through it, including range requests of
+/// indexed formats.
+pub(crate) fn register_object_store(
+    ctx: &ExonSession,
+    url: &str,
+    options: &HashMap<String, String>,
+) -> PyResult<()> {
+    let url = Url::parse(url)
+        .map_err(|e| PyValueError::new_err(format!("Invalid URL {}: {}", url, e)))?;
+    let store = build_object_store(&url, options)?;
+    ctx.session
+        .runtime_env()
+        .register_object_store(&store_url(&url), store);
+    info!("Object store registered for: {}", store_url(&url));
+    Ok(())
+}
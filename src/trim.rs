@@ -0,0 +1,356 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use arrow::array::{
+    Array, ArrayRef, AsArray, Float64Array, Int64Array, StringArray, StringBuilder, StructArray,
+};
+use arrow::buffer::NullBuffer;
+use arrow_schema::{DataType, Field, Fields};
+use datafusion::common::{exec_err, Result, ScalarValue};
+use datafusion::logical_expr::{
+    ColumnarValue, ScalarUDF, ScalarUDFImpl, Signature, TypeSignature, Volatility,
+};
+
+use crate::quality::{decode, DEFAULT_PHRED_OFFSET};
+
+const DEFAULT_MOTT_LIMIT: f64 = 0.05;
+const DEFAULT_ADAPTER_ERROR_RATE: f64 = 0.1;
+const DEFAULT_ADAPTER_MIN_OVERLAP: i64 = 3;
+const DEFAULT_POLY_MIN_LENGTH: i64 = 10;
+/// Like fastp, poly-X tails may contain one mismatch per 8 bases.
+const POLY_BASES_PER_MISMATCH: usize = 8;
+
+/// Read trimming functions. All take the `sequence` and `quality` strings first and
+/// return them trimmed as a `{sequence, quality}` struct.
+#[derive(Clone, Copy, Debug)]
+enum TrimFunction {
+    /// `trim_quality_window(sequence, quality, window, min_quality [, offset])` cuts the
+    /// read at the first window with a mean quality below `min_quality`.
+    Window,
+    /// `trim_quality_mott(sequence, quality [, limit [, offset]])` keeps the segment
+    /// maximizing the sum of `limit - error probability` (the modified Mott algorithm).
+    Mott,
+    /// `trim_adapter(sequence, quality, adapter [, error_rate [, min_overlap]])` cuts a
+    /// 3' adapter, or a prefix of it overlapping the read end by at least `min_overlap`
+    /// bases, with at most `error_rate` mismatches per aligned base.
+    Adapter,
+    /// `trim_poly_tail(sequence, quality, base [, min_length])` cuts a 3' run of `base`
+    /// (e.g. `G` or `A`) of at least `min_length` bases.
+    PolyTail,
+}
+
+impl TrimFunction {
+    fn name(&self) -> &'static str {
+        match self {
+            TrimFunction::Window => "trim_quality_window",
+            TrimFunction::Mott => "trim_quality_mott",
+            TrimFunction::Adapter => "trim_adapter",
+            TrimFunction::PolyTail => "trim_poly_tail",
+        }
+    }
+
+    /// Argument types after `sequence` and `quality`, the optional ones last.
+    fn params(&self) -> (Vec<DataType>, Vec<DataType>) {
+        match self {
+            TrimFunction::Window => (
+                vec![DataType::Int64, DataType::Int64],
+                vec![DataType::Int64],
+            ),
+            TrimFunction::Mott => (vec![], vec![DataType::Float64, DataType::Int64]),
+            TrimFunction::Adapter => (
+                vec![DataType::Utf8],
+                vec![DataType::Float64, DataType::Int64],
+            ),
+            TrimFunction::PolyTail => (vec![DataType::Utf8], vec![DataType::Int64]),
+        }
+    }
+
+    fn signature(&self) -> Signature {
+        let (required, optional) = self.params();
+        let required = [vec![DataType::Utf8, DataType::Utf8], required].concat();
+        let signatures = (0..=optional.len())
+            .map(|n| TypeSignature::Exact([required.clone(), optional[..n].to_vec()].concat()))
+            .collect();
+        Signature::one_of(signatures, Volatility::Immutable)
+    }
+}
+
+/// Cuts the read at the start of the first window of `window` bases with a mean quality
+/// below `min_quality`.
+fn sliding_window_end(scores: &[u8], window: usize, min_quality: i64) -> usize {
+    let window = window.min(scores.len());
+    if window == 0 {
+        return scores.len();
+    }
+    let threshold = min_quality * window as i64;
+    let mut sum = scores[..window].iter().map(|q| *q as i64).sum::<i64>();
+    for start in 0..=scores.len() - window {
+        if start > 0 {
+            sum += scores[start + window - 1] as i64 - scores[start - 1] as i64;
+        }
+        if sum < threshold {
+            return start;
+        }
+    }
+    scores.len()
+}
+
+/// Segment with the maximum sum of `limit - error probability`, empty if no base has
+/// an error probability below `limit`.
+fn mott_range(scores: &[u8], limit: f64) -> (usize, usize) {
+    let (mut sum, mut start) = (0.0, 0);
+    let (mut best, mut range) = (0.0, (0, 0));
+    for (i, q) in scores.iter().enumerate() {
+        sum += limit - 10f64.powf(-(*q as f64) / 10.0);
+        if sum <= 0.0 {
+            sum = 0.0;
+            start = i + 1;
+        } else if sum > best {
+            best = sum;
+            range = (start, i + 1);
+        }
+    }
+    range
+}
+
+/// Start of the first 3' adapter occurrence, see `TrimFunction::Adapter`.
+fn adapter_start(
+    sequence: &[u8],
+    adapter: &[u8],
+    error_rate: f64,
+    min_overlap: usize,
+) -> Option<usize> {
+    let min_overlap = min_overlap.clamp(1, adapter.len().max(1));
+    for start in 0..sequence.len() {
+        let overlap = adapter.len().min(sequence.len() - start);
+        if overlap < min_overlap {
+            break;
+        }
+        let allowed = (error_rate * overlap as f64).floor() as usize;
+        let mismatches = sequence[start..start + overlap]
+            .iter()
+            .zip(adapter)
+            .filter(|(s, a)| !s.eq_ignore_ascii_case(a))
+            .count();
+        if mismatches <= allowed {
+            return Some(start);
+        }
+    }
+    None
+}
+
+/// Start of the longest 3' tail of `base` starting with `base` and with at most one
+/// mismatch per 8 bases, if it is at least `min_length` long.
+fn poly_tail_start(sequence: &[u8], base: u8, min_length: usize) -> Option<usize> {
+    let mut mismatches = 0;
+    let mut longest = 0;
+    for (len, symbol) in (1..).zip(sequence.iter().rev()) {
+        let matched = symbol.eq_ignore_ascii_case(&base);
+        if !matched {
+            mismatches += 1;
+        }
+        if mismatches > len / POLY_BASES_PER_MISMATCH + 1 {
+            break;
+        }
+        if matched && mismatches <= len / POLY_BASES_PER_MISMATCH {
+            longest = len;
+        }
+    }
+    (longest >= min_length.max(1)).then(|| sequence.len() - longest)
+}
+
+fn trimmed_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("sequence", DataType::Utf8, true),
+        Field::new("quality", DataType::Utf8, true),
+    ])
+}
+
+/// Value of the optional argument `index` at row `i`, `None` if missing or `NULL`.
+fn int_at(arrays: &[ArrayRef], index: usize, i: usize) -> Option<i64> {
+    let array = arrays.get(index)?.as_any().downcast_ref::<Int64Array>()?;
+    array.is_valid(i).then(|| array.value(i))
+}
+
+fn float_at(arrays: &[ArrayRef], index: usize, i: usize) -> Option<f64> {
+    let array = arrays.get(index)?.as_any().downcast_ref::<Float64Array>()?;
+    array.is_valid(i).then(|| array.value(i))
+}
+
+fn str_at(arrays: &[ArrayRef], index: usize, i: usize) -> Option<&str> {
+    let array = arrays.get(index)?.as_any().downcast_ref::<StringArray>()?;
+    array.is_valid(i).then(|| array.value(i))
+}
+
+#[derive(Debug)]
+struct TrimUdf {
+    function: TrimFunction,
+    signature: Signature,
+}
+
+impl TrimUdf {
+    fn new(function: TrimFunction) -> Self {
+        Self {
+            function,
+            signature: function.signature(),
+        }
+    }
+
+    /// Kept `[start, end)` range of a read, `None` when a required argument is `NULL`.
+    fn trim(
+        &self,
+        sequence: &[u8],
+        quality: &[u8],
+        arrays: &[ArrayRef],
+        i: usize,
+        scores: &mut Vec<u8>,
+    ) -> Result<Option<(usize, usize)>> {
+        let len = sequence.len();
+        let range = match self.function {
+            TrimFunction::Window => {
+                let (window, min_quality) = match (int_at(arrays, 2, i), int_at(arrays, 3, i)) {
+                    (Some(window), Some(min_quality)) => (window, min_quality),
+                    _ => return Ok(None),
+                };
+                if window <= 0 {
+                    return exec_err!("window must be greater than 0");
+                }
+                decode(
+                    quality,
+                    int_at(arrays, 4, i).unwrap_or(DEFAULT_PHRED_OFFSET),
+                    scores,
+                )?;
+                (0, sliding_window_end(scores, window as usize, min_quality))
+            },
+            TrimFunction::Mott => {
+                decode(
+                    quality,
+                    int_at(arrays, 3, i).unwrap_or(DEFAULT_PHRED_OFFSET),
+                    scores,
+                )?;
+                mott_range(scores, float_at(arrays, 2, i).unwrap_or(DEFAULT_MOTT_LIMIT))
+            },
+            TrimFunction::Adapter => {
+                let adapter = match str_at(arrays, 2, i) {
+                    Some(adapter) => adapter.as_bytes(),
+                    None => return Ok(None),
+                };
+                let error_rate = float_at(arrays, 3, i).unwrap_or(DEFAULT_ADAPTER_ERROR_RATE);
+                let min_overlap = int_at(arrays, 4, i).unwrap_or(DEFAULT_ADAPTER_MIN_OVERLAP);
+                if !(0.0..1.0).contains(&error_rate) {
+                    return exec_err!("error_rate must be between 0 and 1");
+                }
+                let end = adapter_start(sequence, adapter, error_rate, min_overlap.max(1) as usize);
+                (0, end.unwrap_or(len))
+            },
+            TrimFunction::PolyTail => {
+                let base = match str_at(arrays, 2, i).map(|b| b.as_bytes()) {
+                    Some([base]) => *base,
+                    Some(_) => return exec_err!("base must be a single character"),
+                    None => return Ok(None),
+                };
+                let min_length = int_at(arrays, 3, i).unwrap_or(DEFAULT_POLY_MIN_LENGTH);
+                let end = poly_tail_start(sequence, base, min_length.max(0) as usize);
+                (0, end.unwrap_or(len))
+            },
+        };
+        Ok(Some(range))
+    }
+}
+
+impl ScalarUDFImpl for TrimUdf {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        self.function.name()
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Struct(trimmed_fields()))
+    }
+
+    fn invoke(&self, args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        let scalar = args
+            .iter()
+            .all(|arg| matches!(arg, ColumnarValue::Scalar(_)));
+        let arrays = ColumnarValue::values_to_arrays(args)?;
+        let (sequences, qualities) = (arrays[0].as_string::<i32>(), arrays[1].as_string::<i32>());
+        let mut sequence_builder = StringBuilder::new();
+        let mut quality_builder = StringBuilder::new();
+        let mut valid = Vec::with_capacity(sequences.len());
+        let mut scores = Vec::new();
+        for i in 0..sequences.len() {
+            let range = if sequences.is_valid(i) && qualities.is_valid(i) {
+                let (sequence, quality) = (sequences.value(i), qualities.value(i));
+                if sequence.len() != quality.len() {
+                    return exec_err!(
+                        "Sequence and quality lengths differ: {} and {}",
+                        sequence.len(),
+                        quality.len()
+                    );
+                }
+                // reads are cut at byte offsets
+                if !sequence.is_ascii() || !quality.is_ascii() {
+                    return exec_err!("Sequence and quality must be ASCII strings");
+                }
+                self.trim(
+                    sequence.as_bytes(),
+                    quality.as_bytes(),
+                    &arrays,
+                    i,
+                    &mut scores,
+                )?
+                .map(|(start, end)| (sequence, quality, start, end))
+            } else {
+                None
+            };
+            match range {
+                Some((sequence, quality, start, end)) => {
+                    sequence_builder.append_value(&sequence[start..end]);
+                    quality_builder.append_value(&quality[start..end]);
+                    valid.push(true);
+                },
+                None => {
+                    sequence_builder.append_null();
+                    quality_builder.append_null();
+                    valid.push(false);
+                },
+            }
+        }
+        let array: ArrayRef = Arc::new(StructArray::new(
+            trimmed_fields(),
+            vec![
+                Arc::new(sequence_builder.finish()),
+                Arc::new(quality_builder.finish()),
+            ],
+            Some(NullBuffer::from(valid)),
+        ));
+        if scalar {
+            Ok(ColumnarValue::Scalar(ScalarValue::try_from_array(
+                &array, 0,
+            )?))
+        } else {
+            Ok(ColumnarValue::Array(array))
+        }
+    }
+}
+
+/// Read trimming UDFs: `trim_quality_window`, `trim_quality_mott`, `trim_adapter` and
+/// `trim_poly_tail`.
+pub(crate) fn create_trim_udfs() -> Vec<ScalarUDF> {
+    [
+        TrimFunction::Window,
+        TrimFunction::Mott,
+        TrimFunction::Adapter,
+        TrimFunction::PolyTail,
+    ]
+    .into_iter()
+    .map(|function| ScalarUDF::from(TrimUdf::new(function)))
+    .collect()
+}
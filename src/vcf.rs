@@ -0,0 +1,79 @@
+use std::any::Any;
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+
+use arrow_schema::{Schema, SchemaRef};
+use async_trait::async_trait;
+use datafusion::catalog::{Session, TableProvider};
+use datafusion::common::Result;
+use datafusion::datasource::TableType;
+use datafusion::logical_expr::TableProviderFilterPushDown;
+use datafusion::physical_plan::ExecutionPlan;
+use datafusion::prelude::Expr;
+
+use crate::scan::open_decompressed;
+use crate::sink::VCF_HEADER_METADATA_KEY;
+
+/// Reads the header lines of a local, optionally compressed, VCF file.
+pub(crate) fn read_vcf_header(path: &str) -> std::io::Result<String> {
+    let reader = BufReader::new(open_decompressed(path, 1)?);
+    let mut header = String::new();
+    for line in reader.lines() {
+        let line = line?;
+        if !line.starts_with('#') {
+            break;
+        }
+        header.push_str(&line);
+        header.push('\n');
+    }
+    Ok(header)
+}
+
+/// Wraps a VCF table provider to store the original header in the schema
+/// metadata, so that `write_vcf` can reuse it.
+pub(crate) struct VcfHeaderTableProvider {
+    inner: Arc<dyn TableProvider>,
+    schema: SchemaRef,
+}
+
+impl VcfHeaderTableProvider {
+    pub(crate) fn new(inner: Arc<dyn TableProvider>, header: String) -> Self {
+        let schema = inner.schema();
+        let mut metadata = schema.metadata().clone();
+        metadata.insert(VCF_HEADER_METADATA_KEY.to_string(), header);
+        let schema = Arc::new(Schema::new_with_metadata(schema.fields().clone(), metadata));
+        VcfHeaderTableProvider { inner, schema }
+    }
+}
+
+#[async_trait]
+impl TableProvider for VcfHeaderTableProvider {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    fn table_type(&self) -> TableType {
+        self.inner.table_type()
+    }
+
+    fn supports_filters_pushdown(
+        &self,
+        filters: &[&Expr],
+    ) -> Result<Vec<TableProviderFilterPushDown>> {
+        self.inner.supports_filters_pushdown(filters)
+    }
+
+    async fn scan(
+        &self,
+        state: &dyn Session,
+        projection: Option<&Vec<usize>>,
+        filters: &[Expr],
+        limit: Option<usize>,
+    ) -> Result<Arc<dyn ExecutionPlan>> {
+        self.inner.scan(state, projection, filters, limit).await
+    }
+}
@@ -1,8 +1,19 @@
+import gzip
+import os
+import shutil
+import struct
+from pathlib import Path
+
 import bioframe as bf
 import pandas as pd
+import polars as pl
+import pyarrow as pa
+import pytest
 from _expected import DATA_DIR
 
 import polars_bio as pb
+from polars_bio.context import Context
+from polars_bio.polars_bio import RangeOp, RangeOptions, py_get_input_format
 
 
 class TestIOBAM:
@@ -125,3 +136,689 @@ class TestIOTable:
         df_2 = bf.read_table(self.file, schema="bed9")
         df_2 = df_2.sort_values(by=list(df_2.columns)).reset_index(drop=True)
         pd.testing.assert_frame_equal(df_1, df_2)
+
+
+class TestIOWrite:
+    def test_fastq_roundtrip(self, tmp_path):
+        df = pb.read_fastq(f"{DATA_DIR}/io/fastq/test.fastq").collect()
+        out = f"{tmp_path}/test.fastq"
+        assert pb.write_fastq(df, out) == 2
+        df_out = pb.read_fastq(out).collect()
+        assert df_out["name"].to_list() == df["name"].to_list()
+        assert df_out["sequence"].to_list() == df["sequence"].to_list()
+        assert df_out["quality_scores"].to_list() == df["quality_scores"].to_list()
+
+    def test_vcf_roundtrip(self, tmp_path):
+        df = pb.read_vcf(f"{DATA_DIR}/io/vcf/vep.vcf").collect()
+        out = f"{tmp_path}/vep.vcf.gz"
+        assert pb.write_vcf(df, out) == 2
+        df_out = pb.read_vcf(out).collect()
+        assert df_out.columns == df.columns
+        assert df_out.equals(df)
+        with gzip.open(out, "rt") as f:
+            header = [line.rstrip("\n") for line in f if line.startswith("#")]
+        assert header[:2] == ["##fileformat=VCFv4.3", "##contig=<ID=21>"]
+        assert header[2].startswith("##INFO=<ID=CSQ,")
+        assert header[-1] == "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO"
+
+    def test_vcf_table_header(self, tmp_path):
+        vcf = f"{DATA_DIR}/io/vcf/vep.vcf"
+        pb.register_vcf(vcf, "vep_header")
+        out = f"{tmp_path}/vep.vcf"
+        assert pb.write_vcf("vep_header", out) == 2
+        with open(vcf) as f, open(out) as f_out:
+            lines, lines_out = f.read().splitlines(), f_out.read().splitlines()
+        assert lines_out[:2] == lines[:2]
+        assert lines_out[2] == lines[2]
+        assert [line.split("\t")[7] for line in lines_out[3:]] == [
+            line.split("\t")[7] for line in lines[3:]
+        ]
+
+    def test_bed(self, tmp_path):
+        df = pb.read_table(f"{DATA_DIR}/io/bed/test.bed", schema="bed12").collect()
+        out = f"{tmp_path}/test.bed"
+        assert pb.write_bed(df, out) == 3
+        df_out = pb.read_table(out, schema="bed12").collect()
+        assert df_out["chrom"].to_list() == df["chrom"].to_list()
+        assert df_out["end"].to_list() == df["end"].to_list()
+
+    def test_bam_roundtrip(self, tmp_path):
+        bam = f"{DATA_DIR}/io/bam/test.bam"
+        pb.read_bam(bam)
+        pb.register_view("test_bam_flag", "SELECT * FROM test WHERE flag = 1123")
+        expected = len(pb.sql("SELECT * FROM test_bam_flag").collect())
+        out = f"{tmp_path}/filtered.bam"
+        assert pb.write_bam("test_bam_flag", out, header_path=bam, index=True) == expected
+        df_out = pb.read_bam(out).collect()
+        assert len(df_out) == expected
+        assert (df_out["flag"] == 1123).all()
+
+    def test_bam_sam_tag_types(self, tmp_path):
+        bam = f"{DATA_DIR}/io/bam/test.bam"
+        pb.read_bam(bam)
+        pb.register_view(
+            "test_bam_tags",
+            "SELECT name, flag, reference, start, mapping_quality, cigar, mate_reference, "
+            "mate_start, sequence, quality_score, -215 AS template_length, "
+            "[named_struct('tag', 'MD', 'value', '100'), named_struct('tag', 'RG', 'value', '1'), "
+            "named_struct('tag', 'NM', 'value', '2')] AS tags FROM test LIMIT 1",
+        )
+        out = f"{tmp_path}/tags.bam"
+        assert pb.write_bam("test_bam_tags", out, header_path=bam) == 1
+        record = _first_bam_record(out)
+        assert struct.unpack_from("<i", record, 28)[0] == -215
+        assert b"MDZ100\x00" in record
+        assert b"RGZ1\x00" in record
+        assert b"NMC\x02" in record
+
+
+def _first_bam_record(path: str) -> bytes:
+    """Raw bytes of the first alignment record of a BAM file, without its block size."""
+    with gzip.open(path) as f:
+        data = f.read()
+    (l_text,) = struct.unpack_from("<i", data, 4)
+    offset = 8 + l_text
+    (n_ref,) = struct.unpack_from("<i", data, offset)
+    offset += 4
+    for _ in range(n_ref):
+        (l_name,) = struct.unpack_from("<i", data, offset)
+        offset += 8 + l_name
+    (block_size,) = struct.unpack_from("<i", data, offset)
+    return data[offset + 4 : offset + 4 + block_size]
+
+
+class TestIOCSV:
+    file = f"{DATA_DIR}/io/csv/annotations.tsv"
+
+    def test_headerless_tsv_with_comments(self):
+        df = pb.read_csv(
+            self.file, has_header=False, comment="#", null_values=["NA"]
+        ).collect()
+        assert len(df) == 3
+        assert df["column_4"][2] == "GENE3"
+        assert df["column_5"][1] is None
+        assert df["column_5"][2] == 1.5
+
+    def test_explicit_schema(self):
+        schema = pa.schema(
+            [
+                ("chrom", pa.string()),
+                ("start", pa.int64()),
+                ("end", pa.int64()),
+                ("gene", pa.string()),
+                ("score", pa.string()),
+            ]
+        )
+        df = pb.read_csv(self.file, has_header=False, comment="#", schema=schema).collect()
+        assert df.columns == ["chrom", "start", "end", "gene", "score"]
+        assert df["end"][0] == 200
+        assert df["score"][1] == "NA"
+
+    def test_invalid_compression(self):
+        with pytest.raises(ValueError, match="Invalid compression"):
+            pb.read_csv(self.file, has_header=False, comment="#", compression="rar")
+
+
+class TestInputFormatDetection:
+    def test_extensions(self):
+        assert py_get_input_format("s3://bucket/sample.vcf.bgz") == pb.InputFormat.Vcf
+        assert py_get_input_format("reads.fq.gz") == pb.InputFormat.Fastq
+        assert py_get_input_format("annotation.gtf") == pb.InputFormat.Gtf
+        assert py_get_input_format("regions.tsv.gz") == pb.InputFormat.Csv
+
+    def test_magic_bytes(self, tmp_path):
+        bam = tmp_path / "reads"
+        shutil.copy(f"{DATA_DIR}/io/bam/test.bam", bam)
+        assert py_get_input_format(str(bam)) == pb.InputFormat.Bam
+        fastq = tmp_path / "reads_fastq"
+        shutil.copy(f"{DATA_DIR}/io/fastq/test.fastq", fastq)
+        assert py_get_input_format(str(fastq)) == pb.InputFormat.Fastq
+        signal = tmp_path / "signal"
+        shutil.copy(f"{DATA_DIR}/io/bigwig/test.bw", signal)
+        assert py_get_input_format(str(signal)) == pb.InputFormat.BigWig
+
+    def test_indexed(self, tmp_path):
+        assert (
+            py_get_input_format(f"{DATA_DIR}/io/vcf/vep.vcf.bgz")
+            == pb.InputFormat.IndexedVcf
+        )
+        assert (
+            py_get_input_format(f"{DATA_DIR}/io/bam/test.bam")
+            == pb.InputFormat.IndexedBam
+        )
+        bam = tmp_path / "unindexed.bam"
+        shutil.copy(f"{DATA_DIR}/io/bam/test.bam", bam)
+        assert py_get_input_format(str(bam)) == pb.InputFormat.Bam
+
+    def test_unsupported(self):
+        with pytest.raises(ValueError, match="Unsupported"):
+            py_get_input_format("data.unknown")
+
+
+class TestIOBEDTyped:
+    file = f"{DATA_DIR}/io/bed/test.bed"
+
+    def test_bed12_blocks(self):
+        df = pb.read_bed(self.file).collect()
+        assert len(df) == 3
+        assert df["blockCount"][2] == 4
+        assert df["blockSizes"][2].to_list() == [1000, 500, 300, 200]
+        assert df["blockStarts"][1].to_list() == [0, 200, 800]
+
+    def test_bed12_explode_blocks(self):
+        df = pb.read_bed(self.file, explode_blocks=True).collect().sort("chrom", "start")
+        assert len(df) == 9
+        assert df["start"].to_list()[:2] == [1000, 1600]
+        assert df["end"].to_list()[:2] == [1500, 2000]
+
+    def test_bed6(self):
+        df = pb.read_bed(self.file, bed_type="bed6").collect()
+        assert df.columns == ["chrom", "start", "end", "name", "score", "strand"]
+        assert len(df) == 3
+
+    def test_unsupported_bed_type(self):
+        with pytest.raises(ValueError, match="Unsupported BED type"):
+            pb.read_bed(self.file, bed_type="bed13")
+
+    def test_narrow_peak(self):
+        df = pb.read_bed(f"{DATA_DIR}/io/bed/test.narrowPeak").collect()
+        assert len(df) == 2
+        assert df["relSummit"][0] == 50
+        assert df["fc"][1] == 91.0
+
+
+class TestIOGFF:
+    file = f"{DATA_DIR}/io/gff/test.gff"
+
+    def test_attr_fields(self):
+        df = pb.read_gff(self.file, attr_fields=["gene", "gene_biotype"]).collect()
+        assert len(df) == 31
+        genes = df.filter(df["type"] == "pseudogene")
+        assert genes["gene"][0] == "DDX11L1"
+        assert genes["gene_biotype"][0] == "transcribed_pseudogene"
+
+    def test_resolve_parents(self):
+        df = pb.read_gff(self.file, attr_fields=["ID"], resolve_parents=True).collect()
+        exon = df.filter(df["ID"] == "exon-NR_046018.2-1")
+        assert exon["root_id"][0] == "gene-DDX11L1"
+        assert exon["root_type"][0] == "pseudogene"
+
+    def test_quoted_attribute_key(self):
+        df = pb.read_gff(self.file, attr_fields=["gene's"]).collect()
+        assert df["gene's"].null_count() == len(df)
+
+
+class TestIOGTF:
+    file = f"{DATA_DIR}/io/gtf/test.gtf"
+
+    def test_integer_attributes(self):
+        df = pb.read_gff(self.file, attr_fields=["gene_name", "exon_number"]).collect()
+        assert df.schema["exon_number"] == pl.Int64
+        assert df["exon_number"].to_list() == [None, None, 1, 2, 3]
+        assert df["gene_name"][0] == "DDX11L2"
+
+    def test_resolve_parents(self):
+        df = pb.read_gff(self.file, resolve_parents=True).collect()
+        assert df["root_id"].to_list() == ["ENSG00000290825.1"] * 5
+        assert df["root_type"].to_list() == ["gene"] * 5
+
+
+class TestGeneModel:
+    pb.read_gff(f"{DATA_DIR}/io/gff/test.gff")
+    views = pb.register_gene_model("test", prefix="test_model", upstream=100, downstream=10)
+
+    def test_views(self):
+        assert "test_model_introns" in self.views
+        assert "test_model_promoters" in self.views
+
+    def test_introns(self):
+        introns = pb.sql(
+            "SELECT * FROM test_model_introns WHERE transcript_id = 'rna-NR_046018.2' ORDER BY start"
+        ).collect()
+        assert introns["start"].to_list() == [12228, 12722]
+        assert introns["end"].to_list() == [12612, 13220]
+
+    def test_promoters_strand_aware(self):
+        promoters = pb.sql(
+            "SELECT * FROM test_model_promoters WHERE transcript_id IN ('rna-NR_046018.2', 'rna-NR_024540.1') ORDER BY start"
+        ).collect()
+        assert promoters["start"].to_list() == [11774, 29361]
+        assert promoters["end"].to_list() == [11883, 29470]
+
+
+class TestIOBigWig:
+    def test_values(self):
+        df = pb.read_bigwig(f"{DATA_DIR}/io/bigwig/test.bw").collect()
+        assert len(df) == 4
+        assert df["chrom"].to_list() == ["chr1", "chr1", "chr1", "chr2"]
+        assert df["start"].to_list() == [0, 100, 500, 0]
+        assert df["value"].to_list() == [1.0, 2.0, 3.0, 5.0]
+
+    def test_region_filter(self):
+        pb.read_bigwig(f"{DATA_DIR}/io/bigwig/test.bw")
+        df = pb.sql(
+            "SELECT * FROM test_bw WHERE chrom = 'chr1' AND start < 300 AND \"end\" > 50"
+        ).collect()
+        assert df["value"].to_list() == [1.0, 2.0]
+        assert len(pb.sql("SELECT * FROM test_bw WHERE chrom = 'chrX'").collect()) == 0
+
+    def test_zoom_level(self):
+        df = pb.read_bigwig(f"{DATA_DIR}/io/bigwig/test.bw", zoom_level=0).collect()
+        assert len(df) == 2
+        assert df["valid_count"].to_list() == [300, 50]
+        assert df["max"].to_list() == [3.0, 5.0]
+        assert df["mean"].to_list() == [2.0, 5.0]
+
+
+class TestIOBigBed:
+    df = pb.read_bigbed(f"{DATA_DIR}/io/bigbed/test.bb").collect()
+
+    def test_count(self):
+        assert len(self.df) == 3
+
+    def test_fields(self):
+        assert self.df.columns == [
+            "chrom",
+            "start",
+            "end",
+            "name",
+            "score",
+            "strand",
+            "signal",
+        ]
+        assert self.df["name"].to_list() == ["peak1", "peak2", "peak3"]
+        assert self.df["score"].to_list() == [500, 0, 100]
+        assert self.df.schema["signal"] == pl.Float64
+        assert self.df["signal"][1] == 2.5
+
+    def test_invalid_zoom_level(self):
+        with pytest.raises(ValueError, match="Zoom level 5 not available"):
+            pb.read_bigwig(f"{DATA_DIR}/io/bigwig/test.bw", zoom_level=5).collect()
+
+
+class TestIOTrackWrite:
+    df = pl.DataFrame(
+        {
+            "chrom": ["chr1", "chr1", "chr1", "chr1", "chr2"],
+            "start": [0, 10, 20, 40, 0],
+            "end": [10, 20, 30, 50, 100],
+            "coverage": [1, 1, 2, 2, 3],
+        }
+    )
+    chrom_sizes = {"chr1": 1000, "chr2": 50}
+
+    def test_bigwig_roundtrip(self, tmp_path):
+        path = str(tmp_path / "coverage.bw")
+        assert pb.write_bigwig(self.df, path, self.chrom_sizes) == 4
+        df = pb.read_bigwig(path).collect()
+        assert df["chrom"].to_list() == ["chr1", "chr1", "chr1", "chr2"]
+        assert df["start"].to_list() == [0, 20, 40, 0]
+        assert df["end"].to_list() == [20, 30, 50, 50]
+        assert df["value"].to_list() == [1.0, 2.0, 2.0, 3.0]
+
+    def test_bigwig_zoom_levels(self, tmp_path):
+        path = str(tmp_path / "coverage.bw")
+        pb.write_bigwig(self.df, path, self.chrom_sizes)
+        df = pb.read_bigwig(path, zoom_level=0).collect()
+        assert df["valid_count"].to_list() == [40, 50]
+        assert df["mean"].to_list() == [1.5, 3.0]
+
+    def test_bedgraph_chrom_sizes_file(self, tmp_path):
+        sizes = tmp_path / "test.chrom.sizes"
+        sizes.write_text("chr1\t1000\nchr2\t50\n")
+        path = tmp_path / "coverage.bedGraph"
+        assert pb.write_bedgraph(self.df, str(path), str(sizes)) == 4
+        assert path.read_text().splitlines()[0] == "chr1\t0\t20\t1"
+
+    def test_unknown_chrom(self, tmp_path):
+        with pytest.raises(Exception, match="chr2"):
+            pb.write_bigwig(self.df, str(tmp_path / "coverage.bw"), {"chr1": 1000})
+
+
+class TestIOPAF:
+    df = pb.read_paf(f"{DATA_DIR}/io/paf/test.paf").collect()
+
+    def test_count(self):
+        assert len(self.df) == 3
+
+    def test_fields(self):
+        assert self.df["query_name"][0] == "read1"
+        assert self.df["chrom"].to_list() == ["chr1", "chr1", "chr2"]
+        assert self.df["start"][1] == 20000
+        assert self.df["end"][1] == 20500
+        assert self.df["strand"][1] == "-"
+        assert self.df["mapping_quality"].to_list() == [60, 45, 0]
+
+    def test_missing_mapping_quality(self, tmp_path):
+        path = tmp_path / "missing_mapq.paf"
+        path.write_text("read1\t100\t0\t100\t+\tchr1\t1000\t10\t110\t100\t100\t255\n")
+        df = pb.read_paf(str(path)).collect()
+        assert df["mapping_quality"].to_list() == [None]
+
+    def test_tags(self):
+        pb.read_paf(f"{DATA_DIR}/io/paf/test.paf")
+        df = pb.sql("SELECT tags['NM'] AS nm FROM test").collect()
+        assert df["nm"].to_list() == ["30", "20", "10"]
+
+    def test_overlap(self):
+        annotations = pl.DataFrame(
+            {"chrom": ["chr1", "chr2"], "start": [10500, 9000], "end": [10600, 9100]}
+        )
+        alignments = self.df.select(["chrom", "start", "end", "query_name"])
+        result = pb.overlap(alignments, annotations, output_type="polars.DataFrame")
+        assert result["query_name_1"].to_list() == ["read1"]
+
+
+class TestIOSAM:
+    df = pb.read_sam(f"{DATA_DIR}/io/sam/test.sam").collect()
+
+    def test_count(self):
+        assert len(self.df) == 4
+
+    def test_intervals(self):
+        assert self.df["start"].to_list() == [100, 300, 50, None]
+        assert self.df["end"].to_list() == [116, 314, 59, None]
+        assert self.df["strand"].to_list() == ["+", "-", "+", None]
+
+    def test_fields(self):
+        assert self.df["mate_chrom"][0] == "chr1"
+        assert self.df["mate_start"][2] is None
+        assert self.df["quality_scores"][2] is None
+        assert self.df["template_length"][1] == -215
+        assert self.df["mapping_quality"].to_list() == [60, 60, None, 0]
+
+    def test_detect_format(self):
+        assert py_get_input_format(f"{DATA_DIR}/io/sam/test.sam") == pb.InputFormat.Sam
+
+
+class TestIOPairs:
+    df = pb.read_pairs(f"{DATA_DIR}/io/pairs/test.pairs").collect()
+
+    def test_count(self):
+        assert len(self.df) == 4
+
+    def test_fields(self):
+        assert self.df.columns[:6] == [
+            "chrom1",
+            "start1",
+            "end1",
+            "chrom2",
+            "start2",
+            "end2",
+        ]
+        assert self.df["readID"][0] == "read1"
+        assert self.df["pos1"][0] == 1500
+        assert self.df["start1"][0] == 1499
+        assert self.df["end1"][0] == 1500
+        assert self.df["pair_type"].to_list() == ["UU"] * 4
+
+    def test_format_detection(self):
+        path = f"{DATA_DIR}/io/pairs/test.pairs"
+        assert py_get_input_format(path) == pb.InputFormat.Pairs
+
+
+class TestIOBedpe:
+    df = pb.read_bedpe(f"{DATA_DIR}/io/bedpe/test.bedpe").collect()
+
+    def test_count(self):
+        assert len(self.df) == 3
+
+    def test_fields(self):
+        assert self.df["chrom2"].to_list() == ["chr1", "chr2", "chr2"]
+        assert self.df["start2"][0] == 49000
+        assert self.df["score"].to_list() == ["10", ".", "5"]
+        assert self.df["strand2"][0] == "-"
+
+    def test_paired_overlap(self):
+        contacts = pb.read_pairs(f"{DATA_DIR}/io/pairs/test.pairs").collect()
+        result = pb.paired_overlap(
+            self.df, contacts, output_type="polars.DataFrame"
+        ).sort("name_1")
+        assert result["name_1"].to_list() == ["loop1", "loop3"]
+        assert result["readID_2"].to_list() == ["read1", "read3"]
+
+    def test_paired_overlap_files(self):
+        result = (
+            pb.paired_overlap(
+                f"{DATA_DIR}/io/bedpe/test.bedpe", f"{DATA_DIR}/io/pairs/test.pairs"
+            )
+            .collect()
+            .sort("name_1")
+        )
+        assert result["name_1"].to_list() == ["loop1", "loop3"]
+
+    def test_paired_overlap_requires_six_columns(self):
+        with pytest.raises(ValueError, match="6 interval columns"):
+            pb.paired_overlap(self.df, self.df, cols1=["chrom1", "start1", "end1"])
+        with pytest.raises(ValueError, match="6 interval columns"):
+            RangeOptions(
+                range_op=RangeOp.PairedOverlap,
+                columns_1=["chrom1", "start1", "end1"],
+                columns_2=["chrom1", "start1", "end1"],
+            )
+
+
+class TestGetFasta:
+    fasta = f"{DATA_DIR}/io/faidx/test.fa"
+    intervals = pl.DataFrame(
+        {
+            "chrom": ["chr1", "chr1", "chr2", "chr3"],
+            "start": [8, 18, 4, 0],
+            "end": [12, 30, 12, 5],
+            "strand": ["+", "-", "-", "+"],
+        }
+    )
+
+    def test_sequences(self):
+        df = pb.getfasta(self.intervals, self.fasta).collect()
+        assert df["sequence"].to_list() == ["AACC", "CCGGT", "acgtACGT", None]
+
+    def test_reverse_complement(self):
+        df = pb.getfasta(self.intervals, self.fasta, strand_col="strand").collect()
+        assert df["sequence"].to_list() == ["AACC", "ACCGG", "ACGTacgt", None]
+
+    def test_sql(self):
+        df = pb.sql(
+            f"SELECT getfasta('{self.fasta}', 'chr1', 0, 4, '-') AS sequence"
+        ).collect()
+        assert df["sequence"][0] == "ACGT"
+
+    def test_quoted_columns(self):
+        intervals = self.intervals.rename(
+            {"chrom": "Chrom", "start": "Start Pos", "strand": "Strand"}
+        )
+        df = pb.getfasta(
+            intervals, self.fasta, ["Chrom", "Start Pos", "end"], strand_col="Strand"
+        ).collect()
+        assert df["sequence"].to_list() == ["AACC", "ACCGG", "ACGTacgt", None]
+
+    def test_independent_inputs(self):
+        first = pb.getfasta(self.intervals.head(1), self.fasta)
+        second = pb.getfasta(self.intervals.tail(1), self.fasta)
+        assert first.collect()["sequence"].to_list() == ["AACC"]
+        assert second.collect()["sequence"].to_list() == [None]
+
+
+class TestIOMultiFile:
+    def test_glob(self):
+        table = pb.register_table(f"{DATA_DIR}/io/multi/*.bed", "multi_bed")
+        df = pb.sql(
+            f"SELECT sample, count(*) AS cnt FROM {table} GROUP BY sample ORDER BY sample"
+        ).collect()
+        assert df["sample"].to_list() == ["sample_a", "sample_b"]
+        assert df["cnt"].to_list() == [2, 3]
+
+    def test_list(self):
+        paths = [
+            f"{DATA_DIR}/io/multi/sample_b.bed",
+            f"{DATA_DIR}/io/multi/sample_a.bed",
+        ]
+        pb.register_table(paths, "multi_bed_list")
+        df = pb.sql("SELECT DISTINCT file FROM multi_bed_list ORDER BY file").collect()
+        assert df["file"].to_list() == sorted(paths)
+
+    def test_missing_name(self):
+        with pytest.raises(ValueError):
+            pb.register_table(f"{DATA_DIR}/io/multi/*.bed")
+
+    def test_missing_file_raises(self, tmp_path):
+        with pytest.raises(ValueError):
+            pb.register_table(
+                str(tmp_path / "missing.parquet"),
+                "missing_parquet",
+                input_format=pb.InputFormat.Parquet,
+            )
+
+    def test_no_part_tables(self):
+        pb.register_table(f"{DATA_DIR}/io/multi/*.bed", "multi_bed_parts")
+        tables = pb.sql("SHOW TABLES").collect()["table_name"].to_list()
+        assert "multi_bed_parts" in tables
+        assert not any(t.startswith("multi_bed_parts_part") for t in tables)
+
+    def test_single_file_list(self):
+        path = f"{DATA_DIR}/io/multi/sample_a.bed"
+        pb.register_table([path], "multi_bed_single")
+        df = pb.sql("SELECT DISTINCT sample, file FROM multi_bed_single").collect()
+        assert df.rows() == [("sample_a", path)]
+
+    def test_schema_mismatch(self, tmp_path):
+        bed3 = tmp_path / "sample_c.bed"
+        bed3.write_text("chr1\t100\t200\n")
+        with pytest.raises(ValueError, match="sample_c.bed"):
+            pb.register_table(
+                [f"{DATA_DIR}/io/multi/sample_a.bed", str(bed3)], "multi_bed_mixed"
+            )
+
+
+S3_ENDPOINT = os.environ.get("POLARS_BIO_TEST_S3_ENDPOINT")
+
+
+class TestObjectStore:
+    def test_unsupported_scheme(self):
+        with pytest.raises(Exception):
+            pb.register_object_store("ftp://example.com/data")
+
+    @pytest.mark.skipif(
+        S3_ENDPOINT is None,
+        reason="requires an S3-compatible endpoint (e.g. MinIO) in POLARS_BIO_TEST_S3_ENDPOINT",
+    )
+    def test_s3_compatible(self):
+        from pyarrow import fs
+
+        key = os.environ.get("POLARS_BIO_TEST_S3_KEY", "minioadmin")
+        secret = os.environ.get("POLARS_BIO_TEST_S3_SECRET", "minioadmin")
+        scheme, endpoint = S3_ENDPOINT.split("://")
+        s3 = fs.S3FileSystem(
+            access_key=key,
+            secret_key=secret,
+            endpoint_override=endpoint,
+            scheme=scheme,
+            region="us-east-1",
+        )
+        s3.create_dir("polars-bio-test")
+        fs.copy_files(
+            f"{DATA_DIR}/io/multi/sample_a.bed",
+            "polars-bio-test/multi/sample_a.bed",
+            destination_filesystem=s3,
+        )
+        pb.register_object_store(
+            "s3://polars-bio-test",
+            {
+                "endpoint": S3_ENDPOINT,
+                "access_key_id": key,
+                "secret_access_key": secret,
+                "region": "us-east-1",
+                "allow_http": "true",
+            },
+        )
+        pb.register_table("s3://polars-bio-test/multi/sample_a.bed", "s3_bed")
+        df = pb.sql("SELECT count(*) AS cnt FROM s3_bed").collect()
+        assert df["cnt"][0] == 2
+
+    @pytest.mark.skipif(
+        S3_ENDPOINT is None,
+        reason="requires an S3-compatible endpoint (e.g. MinIO) in POLARS_BIO_TEST_S3_ENDPOINT",
+    )
+    def test_s3_indexed_vcf_region(self):
+        from pyarrow import fs
+
+        key = os.environ.get("POLARS_BIO_TEST_S3_KEY", "minioadmin")
+        secret = os.environ.get("POLARS_BIO_TEST_S3_SECRET", "minioadmin")
+        scheme, endpoint = S3_ENDPOINT.split("://")
+        s3 = fs.S3FileSystem(
+            access_key=key,
+            secret_key=secret,
+            endpoint_override=endpoint,
+            scheme=scheme,
+            region="us-east-1",
+        )
+        s3.create_dir("polars-bio-test")
+        for file in ["vep.vcf.bgz", "vep.vcf.bgz.tbi"]:
+            fs.copy_files(
+                f"{DATA_DIR}/io/vcf/{file}",
+                f"polars-bio-test/vcf/{file}",
+                destination_filesystem=s3,
+            )
+        pb.register_object_store(
+            "s3://polars-bio-test",
+            {
+                "endpoint": S3_ENDPOINT,
+                "access_key_id": key,
+                "secret_access_key": secret,
+                "region": "us-east-1",
+                "allow_http": "true",
+            },
+        )
+        path = "s3://polars-bio-test/vcf/vep.vcf.bgz"
+        assert py_get_input_format(path, pb.ctx) == pb.InputFormat.IndexedVcf
+        pb.register_table(path, "s3_vcf")
+        df = pb.sql(
+            "SELECT pos FROM s3_vcf WHERE chrom = '21' AND pos BETWEEN 26960000 AND 26961000"
+        ).collect()
+        assert df["pos"].to_list() == [26960070]
+
+
+class TestCompression:
+    @pytest.fixture
+    def stage_inputs(self):
+        pb.ctx.set_option("bio.stage_inputs", "true")
+        yield
+        pb.ctx.set_option("bio.stage_inputs", "false")
+
+    def test_bzip2_fastq(self, stage_inputs):
+        df = pb.read_fastq(f"{DATA_DIR}/io/compression/test.fastq.bz2").collect()
+        assert len(df) == 2
+        assert df["sequence"][1] == "AGTACACTGGT"
+
+    def test_gzip_without_extension(self, stage_inputs):
+        path = f"{DATA_DIR}/io/compression/gzipped.fastq"
+        df = pb.read_fastq(path).collect()
+        assert len(df) == 2
+
+    def test_bzip2_bed(self, stage_inputs):
+        df = pb.read_bed(f"{DATA_DIR}/io/compression/test.bed.bz2").collect()
+        assert len(df) == 3
+
+    def test_staging_is_opt_in(self):
+        with pytest.raises(ValueError, match="bio.stage_inputs"):
+            pb.read_bed(f"{DATA_DIR}/io/compression/test.bed.bz2").collect()
+
+    def test_staging_dir_in_session_catalog(self, stage_inputs):
+        pb.read_bed(f"{DATA_DIR}/io/compression/test.bed.bz2").collect()
+        staging_dir = Path(Context().session_catalog_dir) / "staging"
+        assert any(p.name.endswith("test.bed") for p in staging_dir.iterdir())
+
+    def test_bgzf_threads(self):
+        pb.ctx.set_option("bio.bgzf_threads", "2")
+        try:
+            df = pb.read_vcf(f"{DATA_DIR}/io/vcf/vep.vcf.bgz").collect()
+        finally:
+            pb.ctx.set_option("bio.bgzf_threads", "1")
+        assert len(df) == len(pb.read_vcf(f"{DATA_DIR}/io/vcf/vep.vcf").collect())
+
+    def test_thread_num_per_read(self):
+        path = f"{DATA_DIR}/io/vcf/vep.vcf.bgz"
+        df = pb.read_vcf(path, thread_num=2).collect()
+        assert len(df) == len(pb.read_vcf(path).collect())
+        assert pb.VcfReadOptions(thread_num=2).thread_num == 2
+        assert pb.VcfReadOptions.default().thread_num is None
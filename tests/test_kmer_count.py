@@ -24,6 +24,68 @@ def test_kmer_count_matches_expected():
     run_kmer_test(3, "fastqc-rs_output/fastqc_kmer3.json")
     run_kmer_test(5, "fastqc-rs_output/fastqc_kmer5.json")
 
+@pytest.mark.parametrize("partitions", ["2", "4", "8"])
+def test_kmer_count_multi_partition_matches_expected(partitions):
+    read_fastq("example.fastq")
+    pb.set_option("datafusion.execution.target_partitions", partitions)
+    try:
+        run_kmer_test(3, "fastqc-rs_output/fastqc_kmer3.json")
+        run_kmer_test(5, "fastqc-rs_output/fastqc_kmer5.json")
+    finally:
+        pb.set_option("datafusion.execution.target_partitions", "1")
+
+def _reference_kmer_counts(k: int) -> list[tuple[str, int]]:
+    complement = str.maketrans("ACGT", "TGCA")
+    counts = {}
+    sequences = pb.sql("SELECT sequence FROM example").collect()["sequence"]
+    for seq in sequences:
+        seq = seq.upper()
+        for i in range(len(seq) - k + 1):
+            kmer = seq[i : i + k]
+            if set(kmer) <= set("ACGT"):
+                kmer = min(kmer, kmer.translate(complement)[::-1])
+                counts[kmer] = counts.get(kmer, 0) + 1
+    return sorted(counts.items())
+
+
+def test_kmer_count_long_kmers_hand_computed():
+    # 'A' * 33 and 'T' * 33 are reverse complements, 'ACGT' * 16 is its own
+    result = pb.sql(
+        f"""
+        SELECT kmer_count(sequence, 33) AS result
+        FROM (VALUES ('{"A" * 33}'), ('{"T" * 33}'), ('{"A" * 32 + "C"}')) AS reads(sequence)
+        """
+    ).collect()
+    result = result.unnest("result").sort("kmer")
+    assert result.rows() == [("A" * 33, 2), ("A" * 32 + "C", 1)]
+
+    result = pb.sql(
+        f"""
+        SELECT kmer_count(sequence, 64) AS result
+        FROM (VALUES ('{"ACGT" * 16 + "A"}')) AS reads(sequence)
+        """
+    ).collect()
+    result = result.unnest("result").sort("kmer")
+    # the reverse complement of the second window is 'T' + 'ACGT' * 15 + 'ACG'
+    assert result.rows() == [("ACGT" * 16, 1), ("CGT" + "ACGT" * 15 + "A", 1)]
+
+
+@pytest.mark.parametrize("k", [31, 32, 33, 64])
+def test_kmer_count_long_kmers(k):
+    read_fastq("example.fastq")
+    result = pb.sql(f"SELECT kmer_count(sequence, {k}) AS result FROM example").collect()
+    result = result.unnest("result").sort("kmer")
+    assert result.rows() == _reference_kmer_counts(k)
+
+
+def test_kmer_count_k_too_large_raises():
+    read_fastq("example.fastq")
+
+    with pytest.raises(Exception) as exc_info:
+        pb.sql("SELECT kmer_count(sequence, 65) AS result FROM example").collect()
+
+    assert "k must not exceed 64" in str(exc_info.value)
+
 def test_kmer_count_invalid_k_raises():
     read_fastq("example.fastq")
 
@@ -38,3 +100,168 @@ def test_plot_kmer_counts_raises_on_top_n_too_high():
 
     with pytest.raises(ValueError, match="must not exceed 100"):
         pb.plot_kmer_counts(result, top_n=101)
+
+
+def test_kmer_counts_table_function_matches_expected():
+    read_fastq("example.fastq")
+    result = pb.sql("SELECT * FROM kmer_counts('example', 'sequence', 5)").collect()
+
+    with open("fastqc-rs_output/fastqc_kmer5.json", "r") as f:
+        expected_json = json.load(f)
+    expected = pl.DataFrame(expected_json["values"]).rename({"k_mer": "kmer"})
+
+    assert result.columns == ["kmer", "count"]
+    assert result.sort("kmer").rows() == expected.select(["kmer", "count"]).sort("kmer").rows()
+
+
+@pytest.mark.parametrize("partitions", ["2", "4"])
+def test_kmer_counts_table_function_multi_partition(partitions):
+    read_fastq("example.fastq")
+    query = "SELECT * FROM kmer_counts('example', 'sequence', 21, NULL, 50)"
+    single = pb.sql(query).collect()
+    pb.set_option("datafusion.execution.target_partitions", partitions)
+    try:
+        result = pb.sql(query).collect()
+    finally:
+        pb.set_option("datafusion.execution.target_partitions", "1")
+    assert result.rows() == single.rows()
+
+
+def test_kmer_counts_table_function_min_count_and_top_n():
+    read_fastq("example.fastq")
+    expected = _reference_kmer_counts(21)
+
+    result = pb.sql("SELECT * FROM kmer_counts('example', 'sequence', 21, 3)").collect()
+    assert sorted(result.rows()) == [(kmer, count) for kmer, count in expected if count >= 3]
+
+    result = pb.sql("SELECT * FROM kmer_counts('example', 'sequence', 21, NULL, 10)").collect()
+    top = sorted(expected, key=lambda e: (-e[1], e[0]))[:10]
+    assert result.rows() == top
+
+
+def test_kmer_counts_table_function_invalid_k_raises():
+    read_fastq("example.fastq")
+
+    with pytest.raises(Exception) as exc_info:
+        pb.sql("SELECT * FROM kmer_counts('example', 'sequence', 65)").collect()
+
+    assert "k must not exceed 64" in str(exc_info.value)
+
+
+def _reference_kmer_counts_with_mode(k: int, canonical: bool, keep_n: bool) -> list[tuple[str, int]]:
+    complement = str.maketrans("ACGTN", "TGCAN")
+    alphabet = set("ACGTN") if keep_n else set("ACGT")
+    counts = {}
+    sequences = pb.sql("SELECT sequence FROM example").collect()["sequence"]
+    for seq in sequences:
+        seq = seq.upper()
+        for i in range(len(seq) - k + 1):
+            kmer = seq[i : i + k]
+            if set(kmer) <= alphabet:
+                if canonical:
+                    kmer = min(kmer, kmer.translate(complement)[::-1])
+                counts[kmer] = counts.get(kmer, 0) + 1
+    return sorted(counts.items())
+
+
+@pytest.mark.parametrize("k", [5, 33])
+def test_kmer_count_forward_strand(k):
+    read_fastq("example.fastq")
+    result = pb.sql(
+        f"SELECT kmer_count(sequence, {k}, 'forward') AS result FROM example"
+    ).collect()
+    result = result.unnest("result").sort("kmer")
+    assert result.rows() == _reference_kmer_counts_with_mode(k, canonical=False, keep_n=False)
+
+
+@pytest.mark.parametrize(
+    "strand,keep_n,expected",
+    [
+        # ACG/CGT, GTA/TAC and GTN/NAC are reverse complements, TNA is its own
+        (
+            "canonical",
+            "true",
+            [("ACG", 7), ("GTA", 3), ("GTN", 3), ("NNA", 1), ("TNA", 1)],
+        ),
+        (
+            "forward",
+            "true",
+            [
+                ("ACG", 4),
+                ("CGT", 3),
+                ("GTA", 2),
+                ("GTN", 1),
+                ("NAC", 2),
+                ("NNA", 1),
+                ("TAC", 1),
+                ("TNA", 1),
+            ],
+        ),
+        ("forward", "false", [("ACG", 4), ("CGT", 3), ("GTA", 2), ("TAC", 1)]),
+    ],
+)
+def test_kmer_count_keep_n(strand, keep_n, expected):
+    result = pb.sql(
+        f"""
+        SELECT kmer_count(sequence, 3, '{strand}', {keep_n}) AS result
+        FROM (VALUES ('ACGTNACGTA'), ('NNACG'), ('ACGTAC')) AS reads(sequence)
+        """
+    ).collect()
+    result = result.unnest("result").sort("kmer")
+    assert result.rows() == expected
+
+
+def test_kmer_count_unknown_strand_raises():
+    read_fastq("example.fastq")
+
+    with pytest.raises(Exception) as exc_info:
+        pb.sql("SELECT kmer_count(sequence, 5, 'reverse') AS result FROM example").collect()
+
+    assert "Unknown k-mer strand mode" in str(exc_info.value)
+
+
+def test_kmer_skipped_windows():
+    # the 3-mers with other symbols than ACGT are GTN, TNA, NAC and CGR, GRA, RAC
+    for keep_n, expected in [("false", 6), ("true", 3)]:
+        result = pb.sql(
+            f"""
+            SELECT kmer_skipped_windows(sequence, 3, 'canonical', {keep_n}) AS skipped
+            FROM (VALUES ('ACGTNACGTA'), ('ACGRAC'), ('AC')) AS reads(sequence)
+            """
+        ).collect()
+        assert result["skipped"].to_list() == [expected]
+
+
+def test_kmer_count_default_fields():
+    result = pb.sql(
+        "SELECT kmer_count(sequence, 3) AS result FROM (VALUES ('ACGRAC')) AS reads(sequence)"
+    ).collect()
+    assert result.unnest("result").columns == ["kmer", "count"]
+
+
+def test_kmer_counts_table_function_strand_and_keep_n():
+    pb.register_view(
+        "kmer_reads",
+        "SELECT * FROM (VALUES ('ACGTNACGTA'), ('NNACG')) AS reads(sequence)",
+    )
+    result = pb.sql(
+        "SELECT * FROM kmer_counts('kmer_reads', 'sequence', 3, NULL, NULL, 'forward', true)"
+    ).collect()
+    assert sorted(result.rows()) == [
+        ("ACG", 3),
+        ("CGT", 2),
+        ("GTA", 1),
+        ("GTN", 1),
+        ("NAC", 2),
+        ("NNA", 1),
+        ("TNA", 1),
+    ]
+
+    result = pb.sql(
+        "SELECT * FROM kmer_counts('kmer_reads', 'sequence', 3, NULL, 3, 'forward', true)"
+    ).collect()
+    assert result.rows() == [("ACG", 3), ("CGT", 2), ("NAC", 2)]
+
+    result = pb.sql("SELECT * FROM kmer_counts('kmer_reads', 'sequence', 3)").collect()
+    # canonical ACGT k-mers only: ACG/CGT are each other's reverse complement
+    assert sorted(result.rows()) == [("ACG", 5), ("GTA", 1)]
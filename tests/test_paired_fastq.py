@@ -0,0 +1,102 @@
+import gzip
+
+import pytest
+
+import polars_bio as pb
+
+COMPLEMENT = str.maketrans("ACGTN", "TGCAN")
+
+
+def _read_fastq(path: str) -> list[tuple[str, str, str]]:
+    with open(path) as f:
+        lines = f.read().splitlines()
+    return [
+        (lines[i][1:].split()[0], lines[i + 1], lines[i + 3])
+        for i in range(0, len(lines), 4)
+    ]
+
+
+READS = _read_fastq("example.fastq")
+MATES = [
+    (name, seq.translate(COMPLEMENT)[::-1], qual[::-1]) for name, seq, qual in READS
+]
+
+
+def _write_fastq(path, records, mate=None, opener=open):
+    with opener(path, "wt") as f:
+        for name, seq, qual in records:
+            suffix = f"/{mate}" if mate else ""
+            f.write(f"@{name}{suffix} comment\n{seq}\n+\n{qual}\n")
+
+
+def _interleave(r1, r2):
+    return [record for pair in zip(r1, r2) for record in pair]
+
+
+class TestPairedFastq:
+    def test_register_paired_fastq(self, tmp_path):
+        r1, r2 = str(tmp_path / "sample_R1.fastq"), str(tmp_path / "sample_R2.fastq.gz")
+        _write_fastq(r1, READS, mate=1)
+        _write_fastq(r2, MATES, mate=2, opener=gzip.open)
+        name = pb.register_paired_fastq(r1, r2, name="sample")
+        assert name == "sample"
+        df = pb.sql("SELECT * FROM sample").collect()
+        assert df.columns == ["name", "seq1", "qual1", "seq2", "qual2"]
+        assert df.rows() == [
+            (name, seq1, qual1, seq2, qual2)
+            for (name, seq1, qual1), (_, seq2, qual2) in zip(READS, MATES)
+        ]
+
+    def test_default_table_name(self, tmp_path):
+        r1, r2 = str(tmp_path / "sample-1_R1.fq"), str(tmp_path / "sample-1_R2.fq")
+        _write_fastq(r1, READS)
+        _write_fastq(r2, MATES)
+        assert pb.register_paired_fastq(r1, r2) == "sample_1_r1"
+
+    def test_interleaved(self, tmp_path):
+        path = str(tmp_path / "interleaved.fastq")
+        _write_fastq(path, _interleave(READS, MATES))
+        df = pb.read_paired_fastq(path).collect()
+        assert len(df) == len(READS)
+        assert df["name"].to_list() == [name for name, _, _ in READS]
+        assert df["seq2"].to_list() == [seq for _, seq, _ in MATES]
+
+    def test_interleaved_odd_reads(self, tmp_path):
+        path = str(tmp_path / "interleaved.fastq")
+        _write_fastq(path, _interleave(READS, MATES)[:-1])
+        with pytest.raises(Exception, match="unpaired last read"):
+            pb.read_paired_fastq(path).collect()
+
+    def test_mismatched_names(self, tmp_path):
+        r1, r2 = str(tmp_path / "R1.fastq"), str(tmp_path / "R2.fastq")
+        _write_fastq(r1, READS, mate=1)
+        _write_fastq(r2, MATES[1:] + MATES[:1], mate=2)
+        pb.register_paired_fastq(r1, r2, name="mismatched")
+        with pytest.raises(Exception, match="do not match"):
+            pb.sql("SELECT * FROM mismatched").collect()
+
+    @pytest.mark.parametrize("mate1,mate2", [(1, 1), (2, 2), (2, 1), (1, None)])
+    def test_wrong_mate_suffixes(self, tmp_path, mate1, mate2):
+        r1, r2 = str(tmp_path / "R1.fastq"), str(tmp_path / "R2.fastq")
+        _write_fastq(r1, READS, mate=mate1)
+        _write_fastq(r2, MATES, mate=mate2)
+        pb.register_paired_fastq(r1, r2, name="wrong_mates")
+        with pytest.raises(Exception, match="do not match"):
+            pb.sql("SELECT * FROM wrong_mates").collect()
+
+    def test_limit_stops_reading(self, tmp_path):
+        # more batches than the reader buffers, the query returns before the files are read
+        r1, r2 = str(tmp_path / "R1.fastq"), str(tmp_path / "R2.fastq")
+        _write_fastq(r1, READS * 200, mate=1)
+        _write_fastq(r2, MATES * 200, mate=2)
+        pb.register_paired_fastq(r1, r2, name="limited")
+        df = pb.sql("SELECT name FROM limited LIMIT 1").collect()
+        assert df["name"].to_list() == [READS[0][0]]
+
+    def test_different_read_counts(self, tmp_path):
+        r1, r2 = str(tmp_path / "R1.fastq"), str(tmp_path / "R2.fastq")
+        _write_fastq(r1, READS)
+        _write_fastq(r2, MATES[:-1])
+        pb.register_paired_fastq(r1, r2, name="truncated")
+        with pytest.raises(Exception, match="has more reads than"):
+            pb.sql("SELECT count(*) FROM truncated").collect()
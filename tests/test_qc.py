@@ -0,0 +1,139 @@
+import json
+import shutil
+from collections import Counter
+
+import pytest
+
+import polars_bio as pb
+
+
+def _reads(path: str) -> list[tuple[str, str]]:
+    with open(path) as f:
+        lines = f.read().splitlines()
+    return [(lines[i + 1], lines[i + 3]) for i in range(0, len(lines), 4)]
+
+
+class TestFastqQc:
+    reads = _reads("example.fastq")
+    qc = pb.fastq_qc("example.fastq")
+
+    def test_modules(self):
+        assert list(self.qc.keys()) == [
+            "per_base_quality",
+            "per_sequence_quality",
+            "per_base_content",
+            "per_sequence_gc",
+            "length_distribution",
+            "duplication_levels",
+            "overrepresented_sequences",
+            "adapter_content",
+        ]
+
+    def test_length_distribution(self):
+        expected = sorted(Counter(len(seq) for seq, _ in self.reads).items())
+        assert self.qc["length_distribution"].rows() == expected
+
+    def test_per_base_quality(self):
+        df = self.qc["per_base_quality"]
+        first = sorted(ord(qual[0]) - 33 for _, qual in self.reads)
+        row = df.row(0, named=True)
+        assert row["position"] == 1
+        assert row["mean"] == pytest.approx(sum(first) / len(first))
+        assert row["median"] == first[(len(first) + 1) // 2 - 1]
+        assert df.height == max(len(qual) for _, qual in self.reads)
+
+    def test_per_sequence_quality(self):
+        expected = Counter(
+            sum(ord(q) - 33 for q in qual) // len(qual) for _, qual in self.reads
+        )
+        assert self.qc["per_sequence_quality"].rows() == sorted(expected.items())
+
+    def test_per_base_content(self):
+        df = self.qc["per_base_content"]
+        bases = [seq[0] for seq, _ in self.reads]
+        acgt = [b for b in bases if b in "ACGT"]
+        row = df.row(0, named=True)
+        assert row["n"] == pytest.approx(bases.count("N") * 100 / len(bases))
+        assert row["a"] == pytest.approx(acgt.count("A") * 100 / len(acgt))
+        assert row["gc"] == pytest.approx(
+            (acgt.count("C") + acgt.count("G")) * 100 / len(acgt)
+        )
+
+    def test_per_sequence_gc(self):
+        df = self.qc["per_sequence_gc"]
+        assert df.height == 101
+        assert df["count"].sum() == len(self.reads)
+
+    def test_duplication_levels(self):
+        df = self.qc["duplication_levels"]
+        counts = Counter(seq[:50] if len(seq) > 75 else seq for seq, _ in self.reads)
+        unique = sum(1 for c in counts.values() if c == 1)
+        assert df.row(0, named=True)["duplication_level"] == "1"
+        assert df.row(0, named=True)["percent_deduplicated"] == pytest.approx(
+            unique * 100 / len(counts)
+        )
+        assert df["percent_total"].sum() == pytest.approx(100)
+
+    def test_adapter_content(self):
+        df = self.qc["adapter_content"]
+        assert df.columns[0] == "position"
+        assert "illumina_universal" in df.columns
+        for column in df.columns[1:]:
+            values = df[column].to_list()
+            assert values == sorted(values)
+
+    def test_json_output(self, tmp_path):
+        pb.fastq_qc("example.fastq", output_dir=str(tmp_path))
+        with open(tmp_path / "fastqc_length_distribution.json") as f:
+            values = json.load(f)["values"]
+        assert values == self.qc["length_distribution"].to_dicts()
+
+    def test_registered_table(self):
+        pb.read_fastq("example.fastq")
+        qc = pb.fastq_qc("example")
+        assert qc["length_distribution"].rows() == self.qc["length_distribution"].rows()
+
+    def test_glob_input(self, tmp_path):
+        for i in range(2):
+            shutil.copy("example.fastq", tmp_path / f"example_{i}.fastq")
+        qc = pb.fastq_qc(str(tmp_path / "example_*.fastq"))
+        expected = sorted(Counter(len(seq) for seq, _ in self.reads * 2).items())
+        assert qc["length_distribution"].rows() == expected
+
+    def test_invalid_phred_offset(self):
+        with pytest.raises(Exception, match="Phred offset"):
+            pb.fastq_qc("example.fastq", phred_offset=20)
+
+
+def _write_distinct_reads(directory, files: int, reads_per_file: int):
+    bases = "ACGT"
+    for f in range(files):
+        with open(directory / f"reads_{f}.fastq", "w") as out:
+            for i in range(f * reads_per_file, (f + 1) * reads_per_file):
+                seq = "".join(bases[(i >> (2 * j)) & 3] for j in range(12)) + "ACGT"
+                # every 1000th sequence is written four times
+                copies = 4 if i % 1000 == 0 else 1
+                for _ in range(copies):
+                    out.write(f"@read{i}\n{seq}\n+\n{'I' * len(seq)}\n")
+
+
+@pytest.mark.parametrize("partitions", ["2", "4"])
+def test_duplication_tracking_limit_independent_of_partitions(tmp_path, partitions):
+    # 120,000 distinct sequences exceed the tracking limit of 100,000
+    _write_distinct_reads(tmp_path, files=4, reads_per_file=30_000)
+    path = str(tmp_path / "reads_*.fastq")
+    modules = ["duplication_levels", "overrepresented_sequences"]
+    single = pb.fastq_qc(path)
+    pb.set_option("datafusion.execution.target_partitions", partitions)
+    try:
+        result = pb.fastq_qc(path)
+    finally:
+        pb.set_option("datafusion.execution.target_partitions", "1")
+    for module in modules:
+        assert result[module].rows() == single[module].rows()
+    levels = single["duplication_levels"]
+    assert levels["percent_total"].sum() == pytest.approx(100)
+    # only the levels "1" and "4" occur, with 1 in 1000 sequences duplicated
+    assert levels.filter(levels["percent_deduplicated"] > 0)[
+        "duplication_level"
+    ].to_list() == ["1", "4"]
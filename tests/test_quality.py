@@ -0,0 +1,73 @@
+import statistics
+
+import pytest
+
+import polars_bio as pb
+from polars_bio.io import read_fastq
+
+
+def _scores(quality: str, offset: int = 33) -> list[int]:
+    return [ord(q) - offset for q in quality]
+
+
+class TestQualityUdfs:
+    read_fastq("example.fastq")
+
+    def test_phred_decode(self):
+        result = pb.sql(
+            "SELECT quality_scores, phred_decode(quality_scores) AS scores FROM example"
+        ).collect()
+        for quality, scores in result.rows():
+            assert scores == _scores(quality)
+
+    def test_phred_decode_offset_64(self):
+        result = pb.sql("SELECT phred_decode('@Jh', 64) AS scores").collect()
+        assert result["scores"].to_list() == [[0, 10, 40]]
+
+    def test_invalid_offset(self):
+        with pytest.raises(Exception, match="Phred offset must be 33 or 64"):
+            pb.sql("SELECT mean_quality('III', 40)").collect()
+
+    def test_invalid_quality_character(self):
+        with pytest.raises(Exception, match="Invalid quality character"):
+            pb.sql("SELECT mean_quality('#II', 64)").collect()
+
+    def test_per_read_statistics(self):
+        result = pb.sql(
+            """
+            SELECT quality_scores,
+                   mean_quality(quality_scores) AS mean,
+                   median_quality(quality_scores) AS median,
+                   expected_errors(quality_scores) AS ee,
+                   bases_at_or_above_q(quality_scores, 30) AS q30
+            FROM example
+            """
+        ).collect()
+        for quality, mean, median, ee, q30 in result.rows():
+            scores = _scores(quality)
+            assert mean == pytest.approx(statistics.mean(scores))
+            assert median == pytest.approx(statistics.median(scores))
+            assert ee == pytest.approx(sum(10 ** (-q / 10) for q in scores))
+            assert q30 == sum(1 for q in scores if q >= 30)
+
+    def test_filter_reads(self):
+        result = pb.sql(
+            """
+            SELECT name FROM example
+            WHERE mean_quality(quality_scores) >= 30 AND expected_errors(quality_scores) < 5
+            """
+        ).collect()
+        reads = pb.sql("SELECT name, quality_scores FROM example").collect()
+        expected = [
+            name
+            for name, quality in reads.rows()
+            if statistics.mean(_scores(quality)) >= 30
+            and sum(10 ** (-q / 10) for q in _scores(quality)) < 5
+        ]
+        assert sorted(result["name"].to_list()) == sorted(expected)
+
+    def test_empty_quality(self):
+        result = pb.sql(
+            "SELECT mean_quality('') AS mean, expected_errors('') AS ee, bases_at_or_above_q('', 20) AS q"
+        ).collect()
+        assert result.rows() == [(None, 0.0, 0)]
@@ -0,0 +1,129 @@
+import math
+from collections import Counter
+
+import pytest
+
+import polars_bio as pb
+from polars_bio.io import read_fastq
+
+COMPLEMENT = str.maketrans("ACGTacgtN", "TGCAtgcaN")
+STANDARD_CODE = "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"
+
+
+def _translate(seq: str) -> str:
+    index = {b: i for i, b in enumerate("TCAG")}
+    protein = ""
+    for i in range(0, len(seq) - 2, 3):
+        codon = seq[i : i + 3].upper()
+        if all(b in index for b in codon):
+            protein += STANDARD_CODE[
+                index[codon[0]] * 16 + index[codon[1]] * 4 + index[codon[2]]
+            ]
+        else:
+            protein += "X"
+    return protein
+
+
+def _complexity(seq: str) -> float:
+    counts = Counter(b for b in seq.upper() if b in "ACGT")
+    total = sum(counts.values())
+    return -sum(c / total * math.log2(c / total) for c in counts.values())
+
+
+def _dust(seq: str) -> float:
+    triplets = Counter(
+        seq[i : i + 3]
+        for i in range(len(seq) - 2)
+        if all(b in "ACGT" for b in seq[i : i + 3])
+    )
+    total = sum(triplets.values())
+    if total < 2:
+        return 0.0
+    return sum(c * (c - 1) // 2 for c in triplets.values()) / (total - 1)
+
+
+def _edit_distance(a: str, b: str) -> int:
+    previous = list(range(len(b) + 1))
+    for i, x in enumerate(a):
+        current = [i + 1]
+        for j, y in enumerate(b):
+            current.append(
+                min(previous[j] + (x != y), previous[j + 1] + 1, current[j] + 1)
+            )
+        previous = current
+    return previous[-1]
+
+
+class TestSequenceUdfs:
+    read_fastq("example.fastq")
+
+    @pytest.mark.parametrize("string_type", ["Utf8", "LargeUtf8", "Utf8View"])
+    def test_string_types(self, string_type):
+        result = pb.sql(
+            f"""
+            SELECT sequence,
+                   revcomp(s) AS revcomp,
+                   gc_content(s) AS gc,
+                   translate_dna(s) AS protein,
+                   complexity(s) AS complexity,
+                   dust(s) AS dust
+            FROM (SELECT sequence, arrow_cast(sequence, '{string_type}') AS s FROM example)
+            """
+        ).collect()
+        for sequence, revcomp, gc, protein, complexity, dust in result.rows():
+            assert revcomp == sequence.translate(COMPLEMENT)[::-1]
+            assert gc == pytest.approx(
+                sum(b in "GC" for b in sequence) / len(sequence)
+            )
+            assert protein == _translate(sequence)
+            assert complexity == pytest.approx(_complexity(sequence))
+            assert dust == pytest.approx(_dust(sequence))
+
+    def test_revcomp_iupac(self):
+        result = pb.sql("SELECT revcomp('ACGTRYKMbdhvNacgu') AS r").collect()
+        assert result["r"][0] == "acgtNbdhvKMRYACGT"
+
+    def test_translate_genetic_code(self):
+        result = pb.sql(
+            "SELECT translate_dna('ATGTGAAGAATA') AS standard, translate_dna('ATGTGAAGAATA', 2) AS mito, "
+            "translate_dna('ATGNNNAT') AS ambiguous"
+        ).collect()
+        assert result.rows() == [("M*RI", "MW*M", "MX")]
+
+    def test_unknown_genetic_code(self):
+        with pytest.raises(Exception, match="Unknown genetic code 7"):
+            pb.sql("SELECT translate_dna('ATG', 7)").collect()
+
+    def test_complexity(self):
+        result = pb.sql(
+            "SELECT complexity('AAAAAAAA') AS homopolymer, complexity('ACGTACGT') AS uniform, "
+            "complexity('NNNN') AS empty, dust('AAAAAAAA') AS dust"
+        ).collect()
+        assert result.rows() == [(0.0, 2.0, None, 3.0)]
+
+    def test_distances(self):
+        reads = pb.sql(
+            "SELECT sequence FROM example ORDER BY name LIMIT 20"
+        ).collect()["sequence"]
+        result = pb.sql(
+            """
+            SELECT a.sequence AS a, b.sequence AS b,
+                   hamming(a.sequence, arrow_cast(b.sequence, 'Utf8View')) AS hamming,
+                   edit_distance(a.sequence, b.sequence) AS edit_distance
+            FROM (SELECT sequence FROM example ORDER BY name LIMIT 20) a
+            CROSS JOIN (SELECT sequence FROM example ORDER BY name LIMIT 20) b
+            """
+        ).collect()
+        assert len(result) == len(reads) ** 2
+        for a, b, hamming, edit_distance in result.rows():
+            if len(a) == len(b):
+                assert hamming == sum(x != y for x, y in zip(a, b))
+            else:
+                assert hamming is None
+            assert edit_distance == _edit_distance(a, b)
+
+    def test_case_insensitive_distances(self):
+        result = pb.sql(
+            "SELECT hamming('ACGT', 'acct') AS hamming, edit_distance('ACGT', 'agt') AS edit_distance"
+        ).collect()
+        assert result.rows() == [(1, 1)]
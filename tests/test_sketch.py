@@ -0,0 +1,169 @@
+import math
+
+import pytest
+
+import polars_bio as pb
+from polars_bio.io import read_fastq
+
+MASK = (1 << 64) - 1
+COMPLEMENT = str.maketrans("ACGT", "TGCA")
+
+
+def _mix64(x: int) -> int:
+    x ^= x >> 30
+    x = (x * 0xBF58476D1CE4E5B9) & MASK
+    x ^= x >> 27
+    x = (x * 0x94D049BB133111EB) & MASK
+    return x ^ (x >> 31)
+
+
+def _hash(kmer: str) -> int:
+    packed = 0
+    for base in kmer:
+        packed = (packed << 2) | "ACGT".index(base)
+    return _mix64((packed & MASK) ^ _mix64(packed >> 64))
+
+
+def _canonical_kmers(seq: str, k: int) -> list[tuple[int, str]]:
+    seq = seq.upper()
+    kmers = []
+    for i in range(len(seq) - k + 1):
+        kmer = seq[i : i + k]
+        if set(kmer) <= set("ACGT"):
+            kmers.append((i, min(kmer, kmer.translate(COMPLEMENT)[::-1])))
+    return kmers
+
+
+def _minimizers(seq: str, k: int, w: int) -> list[str]:
+    kmers = _canonical_kmers(seq, k)
+    runs, run = [], []
+    for pos, kmer in kmers:
+        if run and pos != run[-1][0] + 1:
+            runs.append(run)
+            run = []
+        run.append((pos, kmer))
+    if run:
+        runs.append(run)
+    selected = []
+    for run in runs:
+        size = min(w, len(run))
+        for start in range(len(run) - size + 1):
+            window = run[start : start + size]
+            best = min(window, key=lambda e: _hash(e[1]))
+            if not selected or selected[-1][0] != best[0]:
+                selected.append(best)
+    return [kmer for _, kmer in selected]
+
+
+def _closed_syncmers(seq: str, k: int, s: int) -> list[str]:
+    smers = dict(_canonical_kmers(seq, s))
+    result = []
+    for pos, kmer in _canonical_kmers(seq, k):
+        hashes = [_hash(smers[pos + i]) for i in range(k - s + 1)]
+        if hashes.index(min(hashes)) in (0, k - s):
+            result.append(kmer)
+    return result
+
+
+def _kmer_set(sequences: list[str], k: int) -> set[str]:
+    return {kmer for seq in sequences for _, kmer in _canonical_kmers(seq, k)}
+
+
+class TestSketch:
+    read_fastq("example.fastq")
+
+    def test_minimizers(self):
+        result = pb.sql(
+            "SELECT sequence, minimizers(sequence, 15, 10) AS m FROM example"
+        ).collect()
+        for seq, minimizers in result.rows():
+            assert minimizers == _minimizers(seq, 15, 10)
+
+    def test_syncmers(self):
+        result = pb.sql(
+            "SELECT sequence, syncmers(sequence, 15, 5) AS s FROM example"
+        ).collect()
+        for seq, syncmers in result.rows():
+            assert syncmers == _closed_syncmers(seq, 15, 5)
+
+    def test_minimizers_hand_computed(self):
+        # with w = 1 every canonical k-mer is selected, CGT and GTT give ACG and AAC
+        result = pb.sql(
+            "SELECT minimizers('ACGTTNACG', 3, 1) AS m, minimizers('AAAAAAA', 3, 3) AS a"
+        ).collect()
+        assert result.row(0) == (["ACG", "ACG", "AAC", "ACG"], ["AAA"] * 3)
+
+    def test_syncmers_hand_computed(self):
+        # with s = k every k-mer is a syncmer, equal s-mers make the first one the smallest
+        result = pb.sql(
+            "SELECT syncmers('ACGTT', 3, 3) AS s, syncmers('AAAAAA', 4, 2) AS a"
+        ).collect()
+        assert result.row(0) == (["ACG", "ACG", "AAC"], ["AAAA"] * 3)
+
+    def test_fracminhash_hand_computed(self):
+        # canonical 3-mers {ACG, AAC} and {ACG, CGA, GAA} share 1 of 4
+        result = pb.sql(
+            """
+            WITH a AS (SELECT fracminhash_sketch('ACGTT', 3, 1) AS sketch),
+            b AS (SELECT fracminhash_sketch('ACGAA', 3, 1) AS sketch)
+            SELECT sketch_distance(a.sketch, b.sketch) AS d FROM a CROSS JOIN b
+            """
+        ).collect()
+        distance = result.unnest("d").row(0, named=True)
+        assert distance["jaccard"] == pytest.approx(0.25)
+        assert distance["mash_distance"] == pytest.approx(0.3054302)
+        assert distance["ani"] == pytest.approx(0.6945698)
+
+    def test_syncmers_invalid_s(self):
+        with pytest.raises(Exception, match="s must be between 1 and k"):
+            pb.sql("SELECT syncmers(sequence, 15, 16) FROM example").collect()
+
+    def test_fracminhash_exact_jaccard(self):
+        reads = pb.sql(
+            "SELECT CAST(split_part(name, '.', 2) AS BIGINT) AS id, sequence FROM example"
+        ).collect()
+        a = [seq for id, seq in reads.rows() if id <= 120]
+        b = [seq for id, seq in reads.rows() if id > 80]
+        kmers_a, kmers_b = _kmer_set(a, 21), _kmer_set(b, 21)
+        expected = len(kmers_a & kmers_b) / len(kmers_a | kmers_b)
+
+        result = pb.sql(
+            """
+            WITH reads AS (
+                SELECT CAST(split_part(name, '.', 2) AS BIGINT) AS id, sequence FROM example
+            ),
+            a AS (SELECT fracminhash_sketch(sequence, 21, 1) AS sketch FROM reads WHERE id <= 120),
+            b AS (SELECT fracminhash_sketch(sequence, 21, 1) AS sketch FROM reads WHERE id > 80)
+            SELECT sketch_distance(a.sketch, b.sketch) AS d FROM a CROSS JOIN b
+            """
+        ).collect()
+        distance = result.unnest("d").row(0, named=True)
+        assert distance["jaccard"] == pytest.approx(expected)
+        mash = -1 / 21 * math.log(2 * expected / (1 + expected))
+        assert distance["mash_distance"] == pytest.approx(mash)
+        assert distance["ani"] == pytest.approx(1 - mash)
+
+    def test_minhash_self_distance(self):
+        result = pb.sql(
+            """
+            WITH s AS (SELECT minhash_sketch(sequence, 21, 100) AS sketch FROM example)
+            SELECT sketch_distance(a.sketch, b.sketch) AS d, a.sketch AS sketch
+            FROM s a CROSS JOIN s b
+            """
+        ).collect()
+        sketch = result.unnest("sketch").row(0, named=True)
+        assert sketch["num_hashes"] == 100
+        assert len(sketch["hashes"]) == 100
+        assert sketch["hashes"] == sorted(sketch["hashes"])
+        distance = result.select("d").unnest("d").row(0, named=True)
+        assert distance == {"jaccard": 1.0, "mash_distance": 0.0, "ani": 1.0}
+
+    def test_sketch_distance_different_k_raises(self):
+        with pytest.raises(Exception, match="different k"):
+            pb.sql(
+                """
+                WITH a AS (SELECT minhash_sketch(sequence, 21) AS sketch FROM example),
+                b AS (SELECT minhash_sketch(sequence, 15) AS sketch FROM example)
+                SELECT sketch_distance(a.sketch, b.sketch) FROM a CROSS JOIN b
+                """
+            ).collect()
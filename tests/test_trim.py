@@ -0,0 +1,181 @@
+import shutil
+
+import pytest
+
+import polars_bio as pb
+from polars_bio.io import read_fastq
+
+ADAPTER = "AGATCGGAAGAGC"
+
+
+def _scores(quality: str) -> list[int]:
+    return [ord(q) - 33 for q in quality]
+
+
+def _window_end(scores: list[int], window: int, min_quality: int) -> int:
+    window = min(window, len(scores))
+    for start in range(len(scores) - window + 1):
+        if window and sum(scores[start : start + window]) < min_quality * window:
+            return start
+    return len(scores)
+
+
+def _mott(scores: list[int], limit: float) -> tuple[int, int]:
+    total, start, best, best_range = 0.0, 0, 0.0, (0, 0)
+    for i, q in enumerate(scores):
+        total += limit - 10 ** (-q / 10)
+        if total <= 0:
+            total, start = 0.0, i + 1
+        elif total > best:
+            best, best_range = total, (start, i + 1)
+    return best_range
+
+
+def _trim(sql: str) -> tuple[str, str]:
+    row = pb.sql(f"SELECT {sql} AS t").collect()["t"][0]
+    return row["sequence"], row["quality"]
+
+
+class TestTrimUdfs:
+    read_fastq("example.fastq")
+
+    def test_quality_window(self):
+        result = pb.sql(
+            """
+            SELECT sequence, quality_scores,
+                   trim_quality_window(sequence, quality_scores, 4, 30) AS t
+            FROM example
+            """
+        ).collect()
+        for sequence, quality, trimmed in result.rows():
+            end = _window_end(_scores(quality), 4, 30)
+            assert trimmed == {"sequence": sequence[:end], "quality": quality[:end]}
+
+    def test_quality_mott(self):
+        result = pb.sql(
+            """
+            SELECT sequence, quality_scores,
+                   trim_quality_mott(sequence, quality_scores) AS t
+            FROM example
+            """
+        ).collect()
+        for sequence, quality, trimmed in result.rows():
+            start, end = _mott(_scores(quality), 0.05)
+            assert trimmed == {
+                "sequence": sequence[start:end],
+                "quality": quality[start:end],
+            }
+
+    def test_quality_window_hand_computed(self):
+        # I is Q40 and # is Q2, the window at 3 is the first with a sum below 4 * 30
+        assert _trim(
+            "trim_quality_window('ACGTACGTAC', 'IIIII##III', 4, 30)"
+        ) == ("ACG", "III")
+
+    def test_quality_mott_hand_computed(self):
+        # Q40 adds 0.05 - 0.0001, Q10 (+) adds 0.05 - 0.1 and Q2 (#) resets the sum,
+        # so the best sum 4 * 0.0499 - 0.05 ends at the fifth base
+        assert _trim("trim_quality_mott('ACGTACG', 'II+II##')") == ("ACGTA", "II+II")
+        # the leading Q2 bases are cut as well, the range is [2, 7)
+        assert _trim("trim_quality_mott('ACGTACGTAC', '##IIIII#II')") == (
+            "GTACG",
+            "IIIII",
+        )
+
+    def test_adapter(self):
+        assert _trim(
+            f"trim_adapter('ACGTACGTAGATCGGAAGAGCTTT', 'IIIIIIIIIIIIIIIIIIIIIIII', '{ADAPTER}')"
+        ) == ("ACGTACGT", "IIIIIIII")
+
+    def test_adapter_mismatch(self):
+        # one mismatch in 13 bases is allowed with the default error rate of 0.1
+        assert _trim(
+            "trim_adapter('ACGTACGTAGATCCGAAGAGC', 'IIIIIIIIIIIIIIIIIIIII', 'AGATCGGAAGAGC')"
+        ) == ("ACGTACGT", "IIIIIIII")
+        assert _trim(
+            "trim_adapter('ACGTACGTAGATCCGAAGAGC', 'IIIIIIIIIIIIIIIIIIIII', 'AGATCGGAAGAGC', 0.0)"
+        ) == ("ACGTACGTAGATCCGAAGAGC", "IIIIIIIIIIIIIIIIIIIII")
+
+    def test_partial_adapter(self):
+        assert _trim(
+            f"trim_adapter('ACGTACGTAGAT', 'IIIIIIIIIIII', '{ADAPTER}')"
+        ) == ("ACGTACGT", "IIIIIIII")
+        assert _trim(
+            f"trim_adapter('ACGTACGTAG', 'IIIIIIIIII', '{ADAPTER}', 0.1, 3)"
+        ) == ("ACGTACGTAG", "IIIIIIIIII")
+
+    def test_poly_tail(self):
+        sequence = "ACGTACGTAC" + "G" * 7 + "T" + "G" * 4
+        quality = "I" * len(sequence)
+        assert _trim(f"trim_poly_tail('{sequence}', '{quality}', 'G')") == (
+            "ACGTACGTAC",
+            "I" * 10,
+        )
+        assert _trim(f"trim_poly_tail('{sequence}', '{quality}', 'G', 20)") == (
+            sequence,
+            quality,
+        )
+        assert _trim(f"trim_poly_tail('{sequence}', '{quality}', 'A')") == (
+            sequence,
+            quality,
+        )
+
+    def test_null(self):
+        result = pb.sql(
+            "SELECT trim_adapter('ACGT', 'IIII', CAST(NULL AS VARCHAR)) AS t"
+        ).collect()
+        assert result["t"][0] is None
+
+    def test_length_mismatch(self):
+        with pytest.raises(Exception, match="Sequence and quality lengths differ"):
+            pb.sql("SELECT trim_quality_mott('ACGT', 'III')").collect()
+
+    def test_non_ascii(self):
+        with pytest.raises(Exception, match="must be ASCII"):
+            pb.sql("SELECT trim_quality_mott('ACGé', 'IIIII')").collect()
+
+
+class TestTrimFastq:
+    def test_trim_fastq(self, tmp_path):
+        output = str(tmp_path / "trimmed.fastq")
+        name = pb.trim_fastq(
+            "example.fastq",
+            adapter=ADAPTER,
+            window_size=4,
+            window_quality=30,
+            min_length=50,
+            output_path=output,
+        )
+        assert name == "example_trimmed"
+        trimmed = pb.sql(f"SELECT * FROM {name}").collect()
+        expected = pb.sql(
+            f"""
+            SELECT t['sequence'] AS sequence FROM (
+                SELECT trim_quality_window(a['sequence'], a['quality'], 4, 30) AS t FROM (
+                    SELECT trim_adapter(sequence, quality_scores, '{ADAPTER}') AS a FROM example
+                )
+            ) WHERE length(t['sequence']) >= 50
+            """
+        ).collect()
+        assert sorted(trimmed["sequence"].to_list()) == sorted(
+            expected["sequence"].to_list()
+        )
+        assert all(len(s) >= 50 for s in trimmed["sequence"])
+        written = pb.read_fastq(output)
+        assert sorted(written["sequence"].to_list()) == sorted(
+            trimmed["sequence"].to_list()
+        )
+
+    def test_trim_fastq_glob(self, tmp_path):
+        for i in range(2):
+            shutil.copy("example.fastq", tmp_path / f"example_{i}.fastq")
+        name = pb.trim_fastq(
+            str(tmp_path / "example_*.fastq"), name="glob_trimmed", min_length=50
+        )
+        assert name == "glob_trimmed"
+        single = pb.trim_fastq("example.fastq", name="single_trimmed", min_length=50)
+        count = "SELECT count(*) AS n FROM {}"
+        assert (
+            pb.sql(count.format(name)).collect()["n"][0]
+            == 2 * pb.sql(count.format(single)).collect()["n"][0]
+        )